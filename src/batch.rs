@@ -0,0 +1,112 @@
+
+use crate::{ ProtectFn, ProtectAllFn, RelocElfError, SegmentProtection };
+use core::ops::Range;
+
+
+
+/// A single memory protection request handed to a `ProtectAllFn`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct SegmentDesc {
+    /// The range of memory to protect, as an offset into the loaded memory.
+    pub range: Range<usize>,
+
+    /// The memory protection to apply to the range.
+    pub protection: SegmentProtection,
+}
+
+/// Either kind of memory-protecting callback.
+#[derive(Copy, Clone)]
+pub enum Protector {
+    Each(ProtectFn),
+    All(ProtectAllFn),
+}
+
+
+
+/// Calls `prot` once for all the given requests.
+///
+/// On failure, the reported range spans all the requests, along with the first request's
+/// protection, as there is no telling which one of them failed.
+pub fn request_batch<I>(
+    prot:     ProtectAllFn,
+    p_base:   *mut u8,
+    v_base:   *mut u8,
+    mem_len:  usize,
+    requests: I,
+) -> Result<(), RelocElfError>
+where I: Iterator<Item = (Range<usize>, SegmentProtection)> {
+    let mut descs = DescBuffer::new();
+
+    for (range, protection) in requests {
+        descs.try_push(SegmentDesc { range, protection }).ok_or(RelocElfError::MemProtectFailed {
+            start:   0,
+            end:     mem_len,
+            protect: SegmentProtection::RO,
+        })?;
+    }
+
+    let descs   = descs.as_slice();
+    let start   = descs.first().map(|d| d.range.start).unwrap_or(0);
+    let end     = descs.last ().map(|d| d.range.end  ).unwrap_or(0);
+    let protect = descs.first().map(|d| d.protection ).unwrap_or(SegmentProtection::RO);
+
+    (prot)(p_base, v_base, mem_len, descs.as_ptr(), descs.len())
+        .map_err(|_| RelocElfError::MemProtectFailed { start, end, protect })
+}
+
+
+
+/// Without the `alloc` feature, this holds as many requests as a `ProtectionMap` can yield.
+#[cfg(not(feature = "alloc"))]
+struct DescBuffer {
+    data: [SegmentDesc; crate::BOOT_SEGMENTS_LEN],
+    len:  usize,
+}
+
+#[cfg(not(feature = "alloc"))]
+impl DescBuffer {
+    fn new() -> Self {
+        Self {
+            data: core::array::from_fn(|_| SegmentDesc {
+                range:      0 .. 0,
+                protection: SegmentProtection::RO,
+            }),
+            len:  0,
+        }
+    }
+
+    /// Never fails, as there are never more requests than `BOOT_SEGMENTS_LEN`.
+    fn try_push(&mut self, desc: SegmentDesc) -> Option<()> {
+        *self.data.get_mut(self.len)? = desc;
+        self.len += 1;
+
+        Some(())
+    }
+
+    fn as_slice(&self) -> &[SegmentDesc] {
+        self.data.get(..self.len).unwrap_or(&[])
+    }
+}
+
+#[cfg(feature = "alloc")]
+struct DescBuffer {
+    data: alloc::vec::Vec<SegmentDesc>,
+}
+
+#[cfg(feature = "alloc")]
+impl DescBuffer {
+    fn new() -> Self {
+        Self { data: alloc::vec::Vec::new() }
+    }
+
+    fn try_push(&mut self, desc: SegmentDesc) -> Option<()> {
+        self.data.push(desc);
+
+        Some(())
+    }
+
+    fn as_slice(&self) -> &[SegmentDesc] {
+        &self.data
+    }
+}
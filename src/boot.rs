@@ -0,0 +1,113 @@
+
+use crate::{ ReadyElf, SegmentProtection, SEGMENT_STACK_LEN };
+
+
+
+/// Maximum number of protection ranges a `BootHeader` can describe.
+///
+/// This is the most ranges a `ProtectionMap` can ever yield, unless the `alloc` feature lifts
+/// the limit on segments.
+pub const BOOT_SEGMENTS_LEN: usize = 2 * SEGMENT_STACK_LEN + 1;
+
+/// A tiny, fixed-size header to prepend to a ready ELF's memory image.
+///
+/// It carries just enough information to place the image anywhere suitably aligned,
+/// memory-protect it, and jump into it, without parsing any ELF data ever again. Note that
+/// the image is already re-located, so it must be placed at the ready ELF's `v_mem`.
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct BootHeader {
+    /// Always `BootHeader::MAGIC` for a filled-in header.
+    pub magic: [u8; 8],
+
+    /// Offset of the entry function into the memory image.
+    pub entry_off: u64,
+
+    /// Size of the memory image, in bytes.
+    pub mem_len: u64,
+
+    /// Number of valid entries in `segments`.
+    pub seg_count: u64,
+
+    /// Non-overlapping, ascending memory protection ranges that cover the whole image.
+    pub segments: [BootSegment; BOOT_SEGMENTS_LEN],
+}
+
+/// A range of the memory image and its final memory protection.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct BootSegment {
+    /// Offset into the memory image, in bytes.
+    pub start: u64,
+
+    /// Length of the range, in bytes.
+    pub len: u64,
+
+    /// Memory protection to apply to the range.
+    pub protect: SegmentProtection,
+}
+
+// Arrays of more than 32 elements don't implement `Default`.
+impl Default for BootHeader {
+    fn default() -> Self {
+        Self {
+            magic:     [0; 8],
+            entry_off: 0,
+            mem_len:   0,
+            seg_count: 0,
+            segments:  [BootSegment::default(); BOOT_SEGMENTS_LEN],
+        }
+    }
+}
+
+impl Default for BootSegment {
+    fn default() -> Self {
+        Self { start: 0, len: 0, protect: SegmentProtection::RO }
+    }
+}
+
+impl BootHeader {
+    /// Magic number at the start of every filled-in header.
+    pub const MAGIC: [u8; 8] = *b"ELFLBOOT";
+
+    /// Whether the header has the right magic number and a sane segment count.
+    pub fn is_valid(&self) -> bool {
+        (self.magic == Self::MAGIC) & (self.seg_count <= (BOOT_SEGMENTS_LEN as u64))
+    }
+
+    /// The valid protection ranges, or none at all if the header is not valid.
+    pub fn segments(&self) -> &[BootSegment] {
+        match self.is_valid() {
+            true  => self.segments.get(..(self.seg_count as usize)).unwrap_or(&[]),
+            false => &[],
+        }
+    }
+}
+
+
+
+pub fn fill_boot_header(elf: &ReadyElf<'_>, out: &mut BootHeader) {
+    let map = elf.effective_protection_map();
+
+    *out = BootHeader {
+        magic:     BootHeader::MAGIC,
+        entry_off: elf.entry     as u64,
+        mem_len:   elf.mem.len() as u64,
+        ..BootHeader::default()
+    };
+
+    for (range, protect) in map {
+        // Only possible with the `alloc` feature, which lifts the limit on segments.
+        let seg = match out.segments.get_mut(out.seg_count as usize) {
+            Some(seg) => seg,
+            None      => { out.magic = [0; 8]; return; },
+        };
+
+        *seg = BootSegment {
+            start: range.start as u64,
+            len:   (range.end - range.start) as u64,
+            protect,
+        };
+        out.seg_count += 1;
+    }
+}
@@ -0,0 +1,146 @@
+
+use crate::{ Elf, LoadedElf, ReadyElf, ElfError, ProtectFn, ResolveFn };
+use alloc::boxed::Box;
+use core::{ ptr, slice };
+
+
+
+/// What all fallible functions return on success. Errors are `ElfError::code`s, which are
+/// never zero.
+pub const ELF_OK: u16 = 0;
+
+/// Parses the `len` bytes at `raw`, see `Elf::try_parse`, and stores the handle in `*out`.
+///
+/// # Safety
+///
+/// `raw` must point to `len` readable bytes, which must outlive the returned handle. `out`
+/// must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn elf_parse(raw: *const u8, len: usize, out: *mut *mut Elf<'static>)
+-> u16 {
+    let raw = slice::from_raw_parts(raw, len);
+
+    finish(Elf::try_parse(raw).map_err(ElfError::from), out)
+}
+
+/// The minimum size of the buffer to hand to `elf_load`, see `Elf::mem_len`.
+///
+/// # Safety
+///
+/// `elf` must be a live handle returned by `elf_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn elf_mem_len(elf: *const Elf<'static>) -> u32 {
+    (*elf).mem_len()
+}
+
+/// The minimum alignment of the buffer to hand to `elf_load`, see `Elf::mem_align`.
+///
+/// # Safety
+///
+/// `elf` must be a live handle returned by `elf_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn elf_mem_align(elf: *const Elf<'static>) -> u32 {
+    (*elf).mem_align()
+}
+
+/// Loads the ELF into the `mem_len` bytes at `mem`, see `Elf::try_load`, and stores the
+/// handle in `*out`. The `elf` handle stays valid, and can be loaded again.
+///
+/// # Safety
+///
+/// `elf` must be a live handle returned by `elf_parse`. `mem` must point to `mem_len`
+/// writable bytes, which must neither be touched nor freed while the returned handle, or a
+/// handle made from it, lives. `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn elf_load(
+    elf:     *const Elf<'static>,
+    mem:     *mut u8,
+    mem_len: usize,
+    out:     *mut *mut LoadedElf<'static>,
+) -> u16 {
+    let mem = slice::from_raw_parts_mut(mem, mem_len);
+
+    finish((*elf).try_load(mem).map_err(ElfError::from), out)
+}
+
+/// Re-locates and protects the loaded ELF, see `LoadedElf::try_reloc`, and stores the handle
+/// in `*out`. A null `base` re-locates the ELF to run right where it was loaded.
+///
+/// This consumes the `loaded` handle, whether it fails or not.
+///
+/// # Safety
+///
+/// `loaded` must be a live handle returned by `elf_load`. `out` must be writable.
+#[allow(improper_ctypes_definitions)]
+#[no_mangle]
+pub unsafe extern "C" fn elf_reloc(
+    loaded:  *mut LoadedElf<'static>,
+    base:    *mut u8,
+    prot:    Option<ProtectFn>,
+    resolve: Option<ResolveFn>,
+    out:     *mut *mut ReadyElf<'static>,
+) -> u16 {
+    let mut loaded = *Box::from_raw(loaded);
+    let base       = if base.is_null() { loaded.loader_base() } else { base };
+    let res        = loaded.try_reloc(base, prot, resolve).map_err(|(_, e)| e.into());
+
+    finish(res, out)
+}
+
+/// The entry function in the loader's address space, see `ReadyElf::try_p_entry`, or null if
+/// there is none.
+///
+/// # Safety
+///
+/// `ready` must be a live handle returned by `elf_reloc`.
+#[no_mangle]
+pub unsafe extern "C" fn elf_entry(ready: *const ReadyElf<'static>) -> *const () {
+    (*ready).try_p_entry().unwrap_or(ptr::null())
+}
+
+/// Frees a handle returned by `elf_parse`. Null is fine.
+///
+/// # Safety
+///
+/// `elf` must be null or a live handle returned by `elf_parse`, which is dead afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn elf_free(elf: *mut Elf<'static>) {
+    free(elf)
+}
+
+/// Frees a handle returned by `elf_load`, without re-locating it. Null is fine.
+///
+/// # Safety
+///
+/// `loaded` must be null or a live handle returned by `elf_load`, which is dead afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn elf_loaded_free(loaded: *mut LoadedElf<'static>) {
+    free(loaded)
+}
+
+/// Frees a handle returned by `elf_reloc`. Null is fine.
+///
+/// The loaded memory stays just as it is, protection included. Freeing it is up to the host.
+///
+/// # Safety
+///
+/// `ready` must be null or a live handle returned by `elf_reloc`, which is dead afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn elf_ready_free(ready: *mut ReadyElf<'static>) {
+    free(ready)
+}
+
+
+
+unsafe fn finish<T>(res: Result<T, ElfError>, out: *mut *mut T) -> u16 {
+    match res {
+        Ok(val) => { *out = Box::into_raw(Box::new(val)); ELF_OK },
+        Err(e)  => e.code(),
+    }
+}
+
+unsafe fn free<T>(handle: *mut T) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
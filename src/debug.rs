@@ -0,0 +1,99 @@
+
+use crate::{
+    Elf, LoadedElf, ReadyElf,
+    ProgramHeaders, LoadedSegments, Segment, SegmentKind, SegmentProtection,
+};
+use core::fmt;
+
+
+
+// None of these print the memory itself, just enough to tell at a glance what went wrong.
+
+impl fmt::Debug for Elf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Elf")
+            .field("machine",   &self.file.machine)
+            .field("elf_type",  &self.file.kind)
+            .field("entry",     &format_args!("{:#x}", self.entry))
+            .field("mem_len",   &format_args!("{:#x}", self.mem_len))
+            .field("mem_align", &format_args!("{:#x}", self.mem_align()))
+            .field("segments",  &PhSummary(self.program_headers()))
+            .finish()
+    }
+}
+
+impl fmt::Debug for LoadedElf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LoadedElf")
+            .field("p_mem",     &self.mem.as_ptr())
+            .field("entry",     &format_args!("{:#x}", self.entry))
+            .field("mem_len",   &format_args!("{:#x}", self.mem.len()))
+            .field("mem_align", &format_args!("{:#x}", self.mem_align))
+            .field("segments",  &SegmentSummary(self.protect.as_slice()))
+            .finish()
+    }
+}
+
+impl fmt::Debug for ReadyElf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReadyElf")
+            .field("p_mem",    &self.mem.as_ptr())
+            .field("v_mem",    &self.base)
+            .field("entry",    &format_args!("{:#x}", self.entry))
+            .field("mem_len",  &format_args!("{:#x}", self.mem.len()))
+            .field("segments", &LoadedSummary(self.segments()))
+            .finish()
+    }
+}
+
+
+
+/// One line per program header, with its kind, protection and range of loaded memory.
+struct PhSummary<'a>(ProgramHeaders<'a>);
+
+impl fmt::Debug for PhSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.clone().map(|ph| {
+                let range = ph.load_range.to_byte_range();
+                Line(ph.kind, ph.protection, range.start, range.end)
+            }))
+            .finish()
+    }
+}
+
+/// One line per protected segment, with its range as an offset into the loaded memory.
+struct SegmentSummary<'a>(&'a [Segment]);
+
+impl fmt::Debug for SegmentSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|seg| {
+                let range = seg.range.to_byte_range();
+                Line(seg.kind, seg.protect, range.start, range.end)
+            }))
+            .finish()
+    }
+}
+
+/// One line per protected segment, with its range in the ready ELF's address space.
+struct LoadedSummary<'a>(LoadedSegments<'a>);
+
+impl fmt::Debug for LoadedSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.clone().map(|seg| {
+                Line(seg.kind, seg.protection, seg.v_range.start, seg.v_range.end)
+            }))
+            .finish()
+    }
+}
+
+/// A single segment's summary, e.g. `Load RX 0x1000..0x2000`.
+struct Line(SegmentKind, SegmentProtection, usize, usize);
+
+impl fmt::Debug for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} {:?} {:#x}..{:#x}", self.0, self.1, self.2, self.3)
+    }
+}
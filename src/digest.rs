@@ -0,0 +1,78 @@
+
+use crate::elf::{
+    DT_REL, DT_RELSZ, DT_RELENT, DT_RELA, DT_RELASZ, DT_RELAENT,
+    DT_JMPREL, DT_PLTREL, DT_PLTRELSZ, DT_RELR, DT_RELRSZ, DT_RELRENT,
+};
+use crate::dynamic::FileDyns;
+use crate::Elf;
+
+
+
+/// A 64-bit FNV-1a hasher. Not cryptographic, but tiny, stable across builds and platforms,
+/// and good enough to tell apart two ELFs.
+pub struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+    const PRIME:        u64 = 0x0000_0100_0000_01B3;
+
+    pub fn new() -> Self {
+        Fnv1a(Self::OFFSET_BASIS)
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 = (self.0 ^ (*b as u64)).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// Hashes a `u64` in little-endian byte order, so that fingerprints are stable
+    /// across hosts of differing endianness.
+    pub fn write_u64(&mut self, x: u64) {
+        self.write(&x.to_le_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+
+
+pub fn reloc_fingerprint(elf: &Elf<'_>) -> u64 {
+    let mut h = Fnv1a::new();
+
+    for ph in elf.program_headers.inner.clone() {
+        h.write_u64(ph.p_type  as u64);
+        h.write_u64(ph.p_flags as u64);
+        h.write_u64(ph.p_vaddr);
+        h.write_u64(ph.p_memsz);
+        h.write_u64(ph.p_align);
+    }
+
+    for d in FileDyns::new(elf) {
+        match d.d_tag {
+            | DT_REL    | DT_RELSZ    | DT_RELENT
+            | DT_RELA   | DT_RELASZ   | DT_RELAENT
+            | DT_JMPREL | DT_PLTREL   | DT_PLTRELSZ
+            | DT_RELR   | DT_RELRSZ   | DT_RELRENT => {
+                h.write_u64(d.d_tag);
+                h.write_u64(d.d_val);
+            },
+            _ => (), // Anything else does not affect the re-location layout.
+        }
+    }
+
+    h.finish()
+}
+
+pub fn content_digest(elf: &Elf<'_>) -> u64 {
+    let mut h = Fnv1a::new();
+
+    // Just the raw bytes, so that neither headers nor re-ordered sections matter.
+    for ph in elf.program_headers().loadable() {
+        h.write(ph.copy_from);
+    }
+
+    h.finish()
+}
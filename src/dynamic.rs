@@ -0,0 +1,332 @@
+
+use crate::elf::{
+    ElfDyn, ElfFormat,
+    DT_NULL, DT_NEEDED, DT_SONAME, DT_STRSZ, DT_STRTAB, DT_SYMTAB, DT_HASH, DT_GNU_HASH,
+    DT_REL, DT_RELSZ, DT_RELENT, DT_RELA, DT_RELASZ, DT_RELAENT,
+    DT_JMPREL, DT_PLTREL, DT_PLTRELSZ, DT_RELR, DT_RELRSZ, DT_RELRENT,
+    DT_RELCOUNT, DT_RELACOUNT,
+    DT_SYMBOLIC, DT_TEXTREL, DT_BIND_NOW, DT_FLAGS, DT_FLAGS_1,
+    DT_INIT_ARRAY, DT_INIT_ARRAYSZ, DT_FINI_ARRAY, DT_FINI_ARRAYSZ,
+    DT_PREINIT_ARRAY, DT_PREINIT_ARRAYSZ,
+    DT_VERSYM, DT_VERDEF, DT_VERDEFNUM, DT_VERNEED, DT_VERNEEDNUM,
+    DF_SYMBOLIC, DF_TEXTREL, DF_BIND_NOW, DF_STATIC_TLS, DF_1_NOW, DF_1_NODELETE, DF_1_PIE,
+};
+use crate::symbol::SymTabs;
+use crate::Elf;
+use core::convert::TryFrom;
+use core::mem;
+use core::slice::{ self, ChunksExact };
+use core::str;
+
+
+
+/// Iterates the `Dyn` entries of the ELF file's `PT_DYNAMIC` segment, up to `DT_NULL`.
+///
+/// The entries are read straight from the ELF buffer, before any loading took place. As
+/// program header file offsets carry no alignment guarantees, every entry is read unaligned.
+/// ELF32 entries are widened to ELF64 ones, and foreign byte order is swapped to native.
+pub struct FileDyns<'a> {
+    inner:  ChunksExact<'a, u8>,
+    format: ElfFormat,
+}
+
+impl<'a> FileDyns<'a> {
+    pub fn new(elf: &Elf<'a>) -> Self {
+        let raw = elf.program_headers().dynamic()
+            .next()
+            .map(|ph| ph.copy_from)
+            .unwrap_or(&[]);
+
+        let format = elf.program_headers.inner.format();
+
+        Self { inner: raw.chunks_exact(format.class.dyn_len()), format }
+    }
+}
+
+impl<'a> Iterator for FileDyns<'a> {
+    type Item = ElfDyn;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let d = self.format.read_dyn(self.inner.next()?)?;
+
+        if d.d_tag == DT_NULL {
+            let done: &[u8] = &[];
+            self.inner = done.chunks_exact(self.format.class.dyn_len());
+            return None;
+        }
+
+        Some(d)
+    }
+}
+
+/// Everything this loader cares about in a `PT_DYNAMIC` segment, collected in a single pass.
+///
+/// Nothing here is checked yet, that's up to whoever reads a field. Offsets are zero for absent
+/// tables, and entry sizes are `None` if there is no tag for them.
+#[derive(Copy, Clone, Default)]
+pub struct DynInfo {
+    pub rel:           DynTable,
+    pub rela:          DynTable,
+    pub relr:          DynTable,
+    pub jmprel:        DynTable,
+    pub init_array:    DynTable,
+    pub fini_array:    DynTable,
+    pub preinit_array: DynTable,
+    pub rel_ent:       Option<u64>,
+    pub rela_ent:      Option<u64>,
+    pub relr_ent:      Option<u64>,
+    /// `DT_PLTREL`, i.e. whether `jmprel` holds `Rel` or `Rela` entries.
+    pub plt_rel:       Option<u64>,
+    pub rel_count:     u64,
+    pub rela_count:    u64,
+    pub tabs:          SymTabs,
+    pub flags:         DynFlags,
+}
+
+impl DynInfo {
+    /// Makes all table addresses relative to the ELF's link-time base, see `Elf::link_base`.
+    ///
+    /// Addresses below the base can't be right, and end up out of bounds instead.
+    pub fn rebase(mut self, link_base: u64) -> Self {
+        let at = |addr: u64| match addr {
+            0    => 0,
+            addr => addr.checked_sub(link_base).unwrap_or(u64::MAX),
+        };
+
+        let tables = [
+            &mut self.rel, &mut self.rela, &mut self.relr, &mut self.jmprel,
+            &mut self.init_array, &mut self.fini_array, &mut self.preinit_array,
+        ];
+
+        for table in tables {
+            table.off = at(table.off);
+        }
+
+        let tabs = &mut self.tabs;
+
+        tabs.symtab    = at(tabs.symtab);
+        tabs.strtab    = at(tabs.strtab);
+        tabs.gnu_hash  = at(tabs.gnu_hash);
+        tabs.hash      = at(tabs.hash);
+        tabs.versym    = at(tabs.versym);
+        tabs.verdef    = at(tabs.verdef);
+        tabs.verneed   = at(tabs.verneed);
+        tabs.link_base = link_base;
+
+        self
+    }
+}
+
+/// Offset and byte length of a table pointed at by some `PT_DYNAMIC` entries.
+#[derive(Copy, Clone, Default)]
+pub struct DynTable {
+    pub off: u64,
+    pub len: u64,
+}
+
+impl DynTable {
+    /// The table's words within the loaded memory, e.g. the function pointers of a
+    /// `DT_INIT_ARRAY`. Absent tables are empty.
+    ///
+    /// Returns `None` if the table does not lie within `mem`, or if it is misaligned.
+    pub fn words(self, mem: &[u8]) -> Option<&[u64]> {
+        if self.off == 0 { return Some(&[]); }
+
+        let start = usize::try_from(self.off).ok()?;
+        let end   = usize::try_from(self.off.checked_add(self.len)?).ok()?;
+        let bytes = mem.get(start..end)?;
+
+        if !(bytes.as_ptr() as usize).is_multiple_of(mem::align_of::<u64>()) {
+            return None;
+        }
+
+        // A trailing partial word is not part of the table.
+        Some(unsafe { slice::from_raw_parts(
+            bytes.as_ptr() as *const u64,
+            bytes.len() / mem::size_of::<u64>()
+        )})
+    }
+}
+
+/// Collects all the `DynInfo` of either the ELF file's or the loaded ELF's `Dyn` entries.
+pub fn parse_dynamic<I: IntoIterator<Item = ElfDyn>>(dyns: I) -> DynInfo {
+    let mut out = DynInfo::default();
+
+    for d in dyns {
+        let v = d.d_val;
+
+        match d.d_tag {
+            DT_REL             => out.rel.off           = v,
+            DT_RELSZ           => out.rel.len           = v,
+            DT_RELENT          => out.rel_ent           = Some(v),
+            DT_RELA            => out.rela.off          = v,
+            DT_RELASZ          => out.rela.len          = v,
+            DT_RELAENT         => out.rela_ent          = Some(v),
+            DT_RELR            => out.relr.off          = v,
+            DT_RELRSZ          => out.relr.len          = v,
+            DT_RELRENT         => out.relr_ent          = Some(v),
+            DT_JMPREL          => out.jmprel.off        = v,
+            DT_PLTRELSZ        => out.jmprel.len        = v,
+            DT_PLTREL          => out.plt_rel           = Some(v),
+            DT_RELCOUNT        => out.rel_count         = v,
+            DT_RELACOUNT       => out.rela_count        = v,
+            DT_SYMTAB          => out.tabs.symtab       = v,
+            DT_STRTAB          => out.tabs.strtab       = v,
+            DT_STRSZ           => out.tabs.strsz        = v,
+            DT_GNU_HASH        => out.tabs.gnu_hash     = v,
+            DT_HASH            => out.tabs.hash         = v,
+            DT_VERSYM          => out.tabs.versym       = v,
+            DT_VERDEF          => out.tabs.verdef       = v,
+            DT_VERDEFNUM       => out.tabs.verdefnum    = v,
+            DT_VERNEED         => out.tabs.verneed      = v,
+            DT_VERNEEDNUM      => out.tabs.verneednum   = v,
+            DT_FLAGS           => out.flags.flags       |= v,
+            DT_FLAGS_1         => out.flags.flags_1     |= v,
+            DT_SYMBOLIC        => out.flags.flags       |= DF_SYMBOLIC,
+            DT_TEXTREL         => out.flags.flags       |= DF_TEXTREL,
+            DT_BIND_NOW        => out.flags.flags       |= DF_BIND_NOW,
+            DT_INIT_ARRAY      => out.init_array.off    = v,
+            DT_INIT_ARRAYSZ    => out.init_array.len    = v,
+            DT_FINI_ARRAY      => out.fini_array.off    = v,
+            DT_FINI_ARRAYSZ    => out.fini_array.len    = v,
+            DT_PREINIT_ARRAY   => out.preinit_array.off = v,
+            DT_PREINIT_ARRAYSZ => out.preinit_array.len = v,
+            _ => (), // Other `DT_DYNAMIC` entries are of no interest to us.
+        }
+    }
+
+    out
+}
+
+/// Maps a virtual address range to the ELF file bytes a `PT_LOAD` segment copies there.
+///
+/// Returns `None` if the range is not fully backed by file data, e.g. if it lies in `.bss`.
+pub fn file_range<'a>(elf: &Elf<'a>, vaddr: u64, len: u64) -> Option<&'a [u8]> {
+    elf.program_headers().loadable()
+        .find_map(|ph| {
+            let off   = vaddr.checked_sub(elf.link_base)?
+                             .checked_sub(ph.load_range.start as u64)?;
+            let start = usize::try_from(off).ok()?;
+            let end   = usize::try_from(off.checked_add(len)?).ok()?;
+
+            ph.copy_from.get(start..end)
+        })
+}
+
+/// Finds the string table within the ELF buffer, as pointed at by `DT_STRTAB` and `DT_STRSZ`.
+fn file_strtab<'a>(elf: &Elf<'a>) -> Option<&'a [u8]> {
+    let tabs = elf.dyn_info.tabs;
+
+    if tabs.strtab == 0 {
+        return None;
+    }
+
+    file_range(elf, tabs.strtab, tabs.strsz)
+}
+
+/// Reads a NUL-terminated UTF-8 string at some offset into a string table.
+fn str_at(strs: &[u8], off: u64) -> Option<&str> {
+    let name = strs.get(usize::try_from(off).ok()?..)?;
+    let len  = name.iter().position(|c| *c == 0)?;
+
+    str::from_utf8(name.get(..len)?).ok()
+}
+
+/// The `DT_SONAME` string, read straight from the ELF buffer's string table.
+pub fn soname<'a>(elf: &Elf<'a>) -> Option<&'a str> {
+    let soname = FileDyns::new(elf).find(|d| d.d_tag == DT_SONAME)?;
+
+    str_at(file_strtab(elf)?, soname.d_val)
+}
+
+/// An iterator over the names of the shared objects an ELF depends on, as listed by its
+/// `DT_NEEDED` entries.
+///
+/// Entries that don't name a valid UTF-8 string within the string table are skipped.
+pub struct NeededLibraries<'a> {
+    dyns: FileDyns<'a>,
+    strs: &'a [u8],
+}
+
+impl<'a> NeededLibraries<'a> {
+    pub(crate) fn new(elf: &Elf<'a>) -> Self {
+        Self { dyns: FileDyns::new(elf), strs: file_strtab(elf).unwrap_or(&[]) }
+    }
+}
+
+impl<'a> Iterator for NeededLibraries<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let d = self.dyns.next()?;
+
+            if d.d_tag != DT_NEEDED { continue; }
+
+            if let Some(name) = str_at(self.strs, d.d_val) {
+                return Some(name);
+            }
+        }
+    }
+}
+
+
+
+/// The `DT_FLAGS` and `DT_FLAGS_1` of an ELF, see `LoadedElf::dyn_flags`.
+///
+/// The legacy `DT_SYMBOLIC`, `DT_TEXTREL` and `DT_BIND_NOW` tags are folded into the
+/// matching `DT_FLAGS` bits.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DynFlags {
+    flags:   u64,
+    flags_1: u64,
+}
+
+impl DynFlags {
+    /// All the `DF_*` bits.
+    pub fn flags(&self) -> u64 {
+        self.flags
+    }
+
+    /// All the `DF_1_*` bits.
+    pub fn flags_1(&self) -> u64 {
+        self.flags_1
+    }
+
+    /// `DF_BIND_NOW` or `DF_1_NOW`, i.e. all symbols are to be resolved before running.
+    ///
+    /// This loader always binds eagerly, so this is just a hint for other loaders.
+    pub fn bind_now(&self) -> bool {
+        ((self.flags & DF_BIND_NOW) | (self.flags_1 & DF_1_NOW)) != 0
+    }
+
+    /// `DF_TEXTREL`, i.e. there are re-locations within read-only or executable segments.
+    ///
+    /// Without this flag, `LoadedElf::try_reloc` refuses to re-locate executable segments.
+    /// With it, these are fine, as memory is only protected after re-locating. Only if the
+    /// code is shared among processes would they be a problem.
+    pub fn text_rel(&self) -> bool {
+        (self.flags & DF_TEXTREL) != 0
+    }
+
+    /// `DF_SYMBOLIC`, i.e. symbols are to be looked up in this ELF first.
+    pub fn symbolic(&self) -> bool {
+        (self.flags & DF_SYMBOLIC) != 0
+    }
+
+    /// `DF_STATIC_TLS`, i.e. the ELF uses the static TLS model and can't be loaded later on.
+    pub fn static_tls(&self) -> bool {
+        (self.flags & DF_STATIC_TLS) != 0
+    }
+
+    /// `DF_1_NODELETE`, i.e. the ELF is never to be unloaded.
+    pub fn no_delete(&self) -> bool {
+        (self.flags_1 & DF_1_NODELETE) != 0
+    }
+
+    /// `DF_1_PIE`, i.e. this is a position-independent executable, not a shared object.
+    pub fn pie(&self) -> bool {
+        (self.flags_1 & DF_1_PIE) != 0
+    }
+}
@@ -1,18 +1,27 @@
 #![allow(missing_docs)]
 
+use core::{ mem, ptr };
 
 
-pub const EI_CLASS:    usize   =   4;
-pub const EI_DATA:     usize   =   5;
-pub const ET_DYN:      u16     =   3;
-pub const ELFMAG:      [u8; 4] = [b'\x7F', b'E', b'L', b'F'];
-pub const SELFMAG:     usize   =   4;
-pub const ELFCLASS64:  u8      =   2;
-pub const ELFDATA2LSB: u8      =   1;
-pub const ELFDATA2MSB: u8      =   2;
-pub const EM_X86_64:   u16     =  62;
-pub const EM_AARCH64:  u16     = 183;
-pub const EM_RISCV:    u16     = 243;
+
+pub const EI_CLASS:      usize   =   4;
+pub const EI_DATA:       usize   =   5;
+pub const EI_VERSION:    usize   =   6;
+pub const EI_OSABI:      usize   =   7;
+pub const EV_CURRENT:    u8      =   1;
+pub const ELFOSABI_SYSV: u8      =   0;
+pub const ELFOSABI_GNU:  u8      =   3;
+pub const ET_EXEC:       u16     =   2;
+pub const ET_DYN:        u16     =   3;
+pub const ELFMAG:        [u8; 4] = [b'\x7F', b'E', b'L', b'F'];
+pub const SELFMAG:       usize   =   4;
+pub const ELFCLASS32:    u8      =   1;
+pub const ELFCLASS64:    u8      =   2;
+pub const ELFDATA2LSB:   u8      =   1;
+pub const ELFDATA2MSB:   u8      =   2;
+pub const EM_X86_64:     u16     =  62;
+pub const EM_AARCH64:    u16     = 183;
+pub const EM_RISCV:      u16     = 243;
 
 pub const PF_X:   u32 = 0b001;
 pub const PF_W:   u32 = 0b010;
@@ -20,22 +29,124 @@ pub const PF_R:   u32 = 0b100;
 pub const PF_RW:  u32 = 0b110;
 pub const PF_RX:  u32 = 0b101;
 
-pub const PT_NULL:      u32 = 0;
-pub const PT_LOAD:      u32 = 1;
-pub const PT_DYNAMIC:   u32 = 2;
-pub const PT_GNU_STACK: u32 = 0x6474E551;
-pub const PT_GNU_RELRO: u32 = 0x6474E552;
+pub const PT_NULL:         u32 = 0;
+pub const PT_LOAD:         u32 = 1;
+pub const PT_DYNAMIC:      u32 = 2;
+pub const PT_INTERP:       u32 = 3;
+pub const PT_NOTE:         u32 = 4;
+pub const PT_PHDR:         u32 = 6;
+pub const PT_TLS:          u32 = 7;
+pub const PT_GNU_EH_FRAME: u32 = 0x6474E550;
+pub const PT_GNU_STACK:    u32 = 0x6474E551;
+pub const PT_GNU_RELRO:    u32 = 0x6474E552;
+pub const PT_GNU_PROPERTY: u32 = 0x6474E553;
+
+pub const NT_GNU_BUILD_ID:        u32 = 3;
+pub const NT_GNU_PROPERTY_TYPE_0: u32 = 5;
+
+pub const GNU_PROPERTY_X86_FEATURE_1_AND:   u32 = 0xC000_0002;
+pub const GNU_PROPERTY_X86_FEATURE_1_IBT:   u32 = 0b01;
+pub const GNU_PROPERTY_X86_FEATURE_1_SHSTK: u32 = 0b10;
+
+pub const DT_NULL:            u64 =  0;
+pub const DT_NEEDED:          u64 =  1;
+pub const DT_PLTRELSZ:        u64 =  2;
+pub const DT_HASH:            u64 =  4;
+pub const DT_STRTAB:          u64 =  5;
+pub const DT_SYMTAB:          u64 =  6;
+pub const DT_STRSZ:           u64 = 10;
+pub const DT_SONAME:          u64 = 14;
+pub const DT_SYMBOLIC:        u64 = 16;
+pub const DT_REL:             u64 = 17;
+pub const DT_RELSZ:           u64 = 18;
+pub const DT_RELENT:          u64 = 19;
+pub const DT_RELA:            u64 =  7;
+pub const DT_RELASZ:          u64 =  8;
+pub const DT_RELAENT:         u64 =  9;
+pub const DT_PLTREL:          u64 = 20;
+pub const DT_TEXTREL:         u64 = 22;
+pub const DT_JMPREL:          u64 = 23;
+pub const DT_BIND_NOW:        u64 = 24;
+pub const DT_INIT_ARRAY:      u64 = 25;
+pub const DT_FINI_ARRAY:      u64 = 26;
+pub const DT_INIT_ARRAYSZ:    u64 = 27;
+pub const DT_FINI_ARRAYSZ:    u64 = 28;
+pub const DT_FLAGS:           u64 = 30;
+pub const DT_PREINIT_ARRAY:   u64 = 32;
+pub const DT_PREINIT_ARRAYSZ: u64 = 33;
+pub const DT_RELRSZ:          u64 = 35;
+pub const DT_RELR:            u64 = 36;
+pub const DT_RELRENT:         u64 = 37;
+pub const DT_GNU_HASH:        u64 = 0x6FFFFEF5;
+pub const DT_RELACOUNT:       u64 = 0x6FFFFFF9;
+pub const DT_RELCOUNT:        u64 = 0x6FFFFFFA;
+pub const DT_FLAGS_1:         u64 = 0x6FFFFFFB;
+pub const DT_VERSYM:          u64 = 0x6FFFFFF0;
+pub const DT_VERDEF:          u64 = 0x6FFFFFFC;
+pub const DT_VERDEFNUM:       u64 = 0x6FFFFFFD;
+pub const DT_VERNEED:         u64 = 0x6FFFFFFE;
+pub const DT_VERNEEDNUM:      u64 = 0x6FFFFFFF;
+
+pub const DF_SYMBOLIC:   u64 = 0x02;
+pub const DF_TEXTREL:    u64 = 0x04;
+pub const DF_BIND_NOW:   u64 = 0x08;
+pub const DF_STATIC_TLS: u64 = 0x10;
+
+pub const DF_1_NOW:      u64 = 0x0000_0001;
+pub const DF_1_NODELETE: u64 = 0x0000_0008;
+pub const DF_1_PIE:      u64 = 0x0800_0000;
+
+pub const SHT_NOBITS: u32 = 8;
+
+pub const SHN_UNDEF: u16 = 0;
+pub const SHN_ABS:   u16 = 0xFFF1;
+
+pub const STN_UNDEF: u32 = 0;
 
-pub const DT_REL:     u64 = 17;
-pub const DT_RELSZ:   u64 = 18;
-pub const DT_RELENT:  u64 = 19;
-pub const DT_RELA:    u64 =  7;
-pub const DT_RELASZ:  u64 =  8;
-pub const DT_RELAENT: u64 =  9;
+pub const VER_NDX_GLOBAL: u16 = 1;
+pub const VERSYM_HIDDEN:  u16 = 0x8000;
+pub const VERSYM_VERSION: u16 = 0x7FFF;
 
-pub const R_X86_64_NONE:     u32 = 0;
-pub const R_X86_64_COPY:     u32 = 5;
-pub const R_X86_64_RELATIVE: u32 = 8;
+pub const STB_LOCAL:      u8 =  0;
+pub const STB_GLOBAL:     u8 =  1;
+pub const STB_WEAK:       u8 =  2;
+pub const STB_GNU_UNIQUE: u8 = 10;
+
+pub const STT_NOTYPE:    u8 =  0;
+pub const STT_OBJECT:    u8 =  1;
+pub const STT_FUNC:      u8 =  2;
+pub const STT_SECTION:   u8 =  3;
+pub const STT_FILE:      u8 =  4;
+pub const STT_COMMON:    u8 =  5;
+pub const STT_TLS:       u8 =  6;
+pub const STT_GNU_IFUNC: u8 = 10;
+
+pub const R_X86_64_NONE:      u32 = 0;
+pub const R_X86_64_64:        u32 = 1;
+pub const R_X86_64_PC32:      u32 = 2;
+pub const R_X86_64_COPY:      u32 = 5;
+pub const R_X86_64_GLOB_DAT:  u32 = 6;
+pub const R_X86_64_JUMP_SLOT: u32 = 7;
+pub const R_X86_64_RELATIVE:  u32 = 8;
+pub const R_X86_64_32:        u32 = 10;
+pub const R_X86_64_32S:       u32 = 11;
+pub const R_X86_64_DTPMOD64:  u32 = 16;
+pub const R_X86_64_DTPOFF64:  u32 = 17;
+pub const R_X86_64_TPOFF64:   u32 = 18;
+pub const R_X86_64_IRELATIVE: u32 = 37;
+
+pub const R_AARCH64_NONE:      u32 =    0;
+pub const R_AARCH64_ABS64:     u32 =  257;
+pub const R_AARCH64_COPY:      u32 = 1024;
+pub const R_AARCH64_GLOB_DAT:  u32 = 1025;
+pub const R_AARCH64_JUMP_SLOT: u32 = 1026;
+pub const R_AARCH64_RELATIVE:  u32 = 1027;
+
+pub const R_RISCV_NONE:      u32 = 0;
+pub const R_RISCV_64:        u32 = 2;
+pub const R_RISCV_RELATIVE:  u32 = 3;
+pub const R_RISCV_COPY:      u32 = 4;
+pub const R_RISCV_JUMP_SLOT: u32 = 5;
 
 
 
@@ -58,19 +169,43 @@ pub struct ElfFileHeader {
     pub e_shstrndx:  u16,
 }
 
-#[derive(Copy, Clone)]
+/// A raw ELF64 program header, as found in the ELF data, see `ProgramHeader::raw`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 #[repr(C)]
 pub struct ElfProgramHeader {
+    /// The segment's type, e.g. `PT_LOAD`.
     pub p_type:   u32,
+    /// The segment's `PF_*` flags.
     pub p_flags:  u32,
+    /// Offset of the segment's data into the ELF data.
     pub p_offset: u64,
+    /// Virtual address of the segment.
     pub p_vaddr:  u64,
+    /// Physical address of the segment.
     pub p_paddr:  u64,
+    /// Size of the segment's data in the ELF data.
     pub p_filesz: u64,
+    /// Size of the segment in memory.
     pub p_memsz:  u64,
+    /// Alignment of the segment, in bytes.
     pub p_align:  u64,
 }
 
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct ElfSectionHeader {
+    pub sh_name:      u32,
+    pub sh_type:      u32,
+    pub sh_flags:     u64,
+    pub sh_addr:      u64,
+    pub sh_offset:    u64,
+    pub sh_size:      u64,
+    pub sh_link:      u32,
+    pub sh_info:      u32,
+    pub sh_addralign: u64,
+    pub sh_entsize:   u64,
+}
+
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub struct ElfDyn {
@@ -93,9 +228,396 @@ pub struct ElfRela {
     pub r_addend: i64,
 }
 
+/// A version definition, i.e. a `DT_VERDEF` entry. Offsets are relative to the entry.
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct ElfVerdef {
+    pub vd_version: u16,
+    pub vd_flags:   u16,
+    pub vd_ndx:     u16,
+    pub vd_cnt:     u16,
+    pub vd_hash:    u32,
+    pub vd_aux:     u32,
+    pub vd_next:    u32,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct ElfVerdaux {
+    pub vda_name: u32,
+    pub vda_next: u32,
+}
+
+/// A version dependency, i.e. a `DT_VERNEED` entry. Offsets are relative to the entry.
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct ElfVerneed {
+    pub vn_version: u16,
+    pub vn_cnt:     u16,
+    pub vn_file:    u32,
+    pub vn_aux:     u32,
+    pub vn_next:    u32,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct ElfVernaux {
+    pub vna_hash:  u32,
+    pub vna_flags: u16,
+    pub vna_other: u16,
+    pub vna_name:  u32,
+    pub vna_next:  u32,
+}
+
+
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct ElfFileHeader32 {
+    pub e_ident:     [u8; 16],
+    pub e_type:      u16,
+    pub e_machine:   u16,
+    pub e_version:   u32,
+    pub e_entry:     u32,
+    pub e_phoff:     u32,
+    pub e_shoff:     u32,
+    pub e_flags:     u32,
+    pub e_ehsize:    u16,
+    pub e_phentsize: u16,
+    pub e_phnum:     u16,
+    pub e_shentsize: u16,
+    pub e_shnum:     u16,
+    pub e_shstrndx:  u16,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct ElfProgramHeader32 {
+    pub p_type:   u32,
+    pub p_offset: u32,
+    pub p_vaddr:  u32,
+    pub p_paddr:  u32,
+    pub p_filesz: u32,
+    pub p_memsz:  u32,
+    pub p_flags:  u32,
+    pub p_align:  u32,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct ElfSectionHeader32 {
+    pub sh_name:      u32,
+    pub sh_type:      u32,
+    pub sh_flags:     u32,
+    pub sh_addr:      u32,
+    pub sh_offset:    u32,
+    pub sh_size:      u32,
+    pub sh_link:      u32,
+    pub sh_info:      u32,
+    pub sh_addralign: u32,
+    pub sh_entsize:   u32,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct ElfDyn32 {
+    pub d_tag: u32,
+    pub d_val: u32,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct ElfRel32 {
+    pub r_offset: u32,
+    pub r_info:   u32,
+}
+
+// ELF32 `Rela` tables are never re-located, so only the size of their entries matters.
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct ElfRela32 {
+    pub r_offset: u32,
+    pub r_info:   u32,
+    pub r_addend: i32,
+}
+
+// The ELF32 structs are only ever read, and then widened to their ELF64 counterparts, so that
+// the rest of the parser only has to deal with one set of structs.
+
+impl From<ElfFileHeader32> for ElfFileHeader {
+    fn from(h: ElfFileHeader32) -> Self {
+        Self {
+            e_ident:     h.e_ident,
+            e_type:      h.e_type,
+            e_machine:   h.e_machine,
+            e_version:   h.e_version,
+            e_entry:     h.e_entry as u64,
+            e_phoff:     h.e_phoff as u64,
+            e_shoff:     h.e_shoff as u64,
+            e_flags:     h.e_flags,
+            e_ehsize:    h.e_ehsize,
+            e_phentsize: h.e_phentsize,
+            e_phnum:     h.e_phnum,
+            e_shentsize: h.e_shentsize,
+            e_shnum:     h.e_shnum,
+            e_shstrndx:  h.e_shstrndx,
+        }
+    }
+}
+
+impl From<ElfProgramHeader32> for ElfProgramHeader {
+    fn from(ph: ElfProgramHeader32) -> Self {
+        Self {
+            p_type:   ph.p_type,
+            p_flags:  ph.p_flags,
+            p_offset: ph.p_offset as u64,
+            p_vaddr:  ph.p_vaddr  as u64,
+            p_paddr:  ph.p_paddr  as u64,
+            p_filesz: ph.p_filesz as u64,
+            p_memsz:  ph.p_memsz  as u64,
+            p_align:  ph.p_align  as u64,
+        }
+    }
+}
+
+impl From<ElfSectionHeader32> for ElfSectionHeader {
+    fn from(sh: ElfSectionHeader32) -> Self {
+        Self {
+            sh_name:      sh.sh_name,
+            sh_type:      sh.sh_type,
+            sh_flags:     sh.sh_flags     as u64,
+            sh_addr:      sh.sh_addr      as u64,
+            sh_offset:    sh.sh_offset    as u64,
+            sh_size:      sh.sh_size      as u64,
+            sh_link:      sh.sh_link,
+            sh_info:      sh.sh_info,
+            sh_addralign: sh.sh_addralign as u64,
+            sh_entsize:   sh.sh_entsize   as u64,
+        }
+    }
+}
+
+impl From<ElfDyn32> for ElfDyn {
+    fn from(d: ElfDyn32) -> Self {
+        // Tags are signed, so that the processor-specific ones stay negative.
+        Self { d_tag: d.d_tag as i32 as u64, d_val: d.d_val as u64 }
+    }
+}
+
+impl From<ElfRel32> for ElfRel {
+    fn from(r: ElfRel32) -> Self {
+        Self { r_offset: r.r_offset as u64, r_info: r_info32(r.r_info) }
+    }
+}
+
+
+
+
+/// The ELF file class, i.e. whether all the structs are of their 32-bit or 64-bit flavour.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ElfClass {
+    Elf64,
+    Elf32,
+}
+
+impl ElfClass {
+    pub fn header_len(self) -> usize {
+        match self {
+            ElfClass::Elf64 => mem::size_of::<ElfFileHeader  >(),
+            ElfClass::Elf32 => mem::size_of::<ElfFileHeader32>(),
+        }
+    }
+
+    pub fn ph_len(self) -> usize {
+        match self {
+            ElfClass::Elf64 => mem::size_of::<ElfProgramHeader  >(),
+            ElfClass::Elf32 => mem::size_of::<ElfProgramHeader32>(),
+        }
+    }
+
+    pub fn ph_align(self) -> usize {
+        match self {
+            ElfClass::Elf64 => mem::align_of::<ElfProgramHeader  >(),
+            ElfClass::Elf32 => mem::align_of::<ElfProgramHeader32>(),
+        }
+    }
+
+    pub fn sh_len(self) -> usize {
+        match self {
+            ElfClass::Elf64 => mem::size_of::<ElfSectionHeader  >(),
+            ElfClass::Elf32 => mem::size_of::<ElfSectionHeader32>(),
+        }
+    }
+
+    pub fn dyn_len(self) -> usize {
+        match self {
+            ElfClass::Elf64 => mem::size_of::<ElfDyn  >(),
+            ElfClass::Elf32 => mem::size_of::<ElfDyn32>(),
+        }
+    }
+
+    pub fn rel_len(self) -> usize {
+        match self {
+            ElfClass::Elf64 => mem::size_of::<ElfRel  >(),
+            ElfClass::Elf32 => mem::size_of::<ElfRel32>(),
+        }
+    }
+
+    pub fn rela_len(self) -> usize {
+        match self {
+            ElfClass::Elf64 => mem::size_of::<ElfRela  >(),
+            ElfClass::Elf32 => mem::size_of::<ElfRela32>(),
+        }
+    }
+}
+
+/// How to read the ELF's structs, i.e. their class and whether their byte order is foreign.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct ElfFormat {
+    pub class: ElfClass,
+    pub swap:  bool,
+}
+
+impl ElfFormat {
+    /// Reads the file header at the start of `raw`, widened to ELF64.
+    pub fn read_header(self, raw: &[u8]) -> Option<ElfFileHeader> {
+        match self.class {
+            ElfClass::Elf64 => read::<ElfFileHeader  >(raw, self.swap),
+            ElfClass::Elf32 => read::<ElfFileHeader32>(raw, self.swap).map(Into::into),
+        }
+    }
+
+    /// Reads a program header at the start of `raw`, widened to ELF64.
+    pub fn read_ph(self, raw: &[u8]) -> Option<ElfProgramHeader> {
+        match self.class {
+            ElfClass::Elf64 => read::<ElfProgramHeader  >(raw, self.swap),
+            ElfClass::Elf32 => read::<ElfProgramHeader32>(raw, self.swap).map(Into::into),
+        }
+    }
+
+    /// Reads a section header at the start of `raw`, widened to ELF64.
+    pub fn read_sh(self, raw: &[u8]) -> Option<ElfSectionHeader> {
+        match self.class {
+            ElfClass::Elf64 => read::<ElfSectionHeader  >(raw, self.swap),
+            ElfClass::Elf32 => read::<ElfSectionHeader32>(raw, self.swap).map(Into::into),
+        }
+    }
+
+    /// Reads a `Dyn` entry at the start of `raw`, widened to ELF64.
+    pub fn read_dyn(self, raw: &[u8]) -> Option<ElfDyn> {
+        match self.class {
+            ElfClass::Elf64 => read::<ElfDyn  >(raw, self.swap),
+            ElfClass::Elf32 => read::<ElfDyn32>(raw, self.swap).map(Into::into),
+        }
+    }
+
+    /// Reads a `Rel` entry, or the `Rel` part of a `Rela` entry, at the start of `raw`,
+    /// widened to ELF64.
+    pub fn read_rel(self, raw: &[u8]) -> Option<ElfRel> {
+        match self.class {
+            ElfClass::Elf64 => read::<ElfRel  >(raw, self.swap),
+            ElfClass::Elf32 => read::<ElfRel32>(raw, self.swap).map(Into::into),
+        }
+    }
+}
+
+/// Structs that can be read in foreign byte order, by swapping the bytes of every field.
+pub trait SwapBytes {
+    fn swap_bytes(self) -> Self;
+}
+
+macro_rules! impl_swap_bytes {
+    ($($ty:ident { $($field:ident),* })*) => {$(
+        impl SwapBytes for $ty {
+            fn swap_bytes(mut self) -> Self {
+                $(self.$field = self.$field.swap_bytes();)*
+                self
+            }
+        }
+    )*};
+}
+
+impl_swap_bytes! {
+    ElfFileHeader      { e_type, e_machine, e_version, e_entry, e_phoff, e_shoff, e_flags,
+                         e_ehsize, e_phentsize, e_phnum, e_shentsize, e_shnum, e_shstrndx }
+    ElfFileHeader32    { e_type, e_machine, e_version, e_entry, e_phoff, e_shoff, e_flags,
+                         e_ehsize, e_phentsize, e_phnum, e_shentsize, e_shnum, e_shstrndx }
+    ElfProgramHeader   { p_type, p_flags, p_offset, p_vaddr, p_paddr, p_filesz, p_memsz, p_align }
+    ElfProgramHeader32 { p_type, p_flags, p_offset, p_vaddr, p_paddr, p_filesz, p_memsz, p_align }
+    ElfSectionHeader   { sh_name, sh_type, sh_flags, sh_addr, sh_offset, sh_size, sh_link,
+                         sh_info, sh_addralign, sh_entsize }
+    ElfSectionHeader32 { sh_name, sh_type, sh_flags, sh_addr, sh_offset, sh_size, sh_link,
+                         sh_info, sh_addralign, sh_entsize }
+    ElfDyn             { d_tag, d_val }
+    ElfDyn32           { d_tag, d_val }
+    ElfRel             { r_offset, r_info }
+    ElfRel32           { r_offset, r_info }
+}
+
+/// A bounds-checked read of a `T` at the start of `raw`, without alignment requirements.
+///
+/// Never casts `raw` to a `&T`, as neither alignment nor byte order are guaranteed to match.
+fn read<T: Copy + SwapBytes>(raw: &[u8], swap: bool) -> Option<T> {
+    if raw.len() < mem::size_of::<T>() {
+        return None;
+    }
+
+    let x = unsafe { ptr::read_unaligned(raw.as_ptr() as *const T) };
+
+    Some(if swap { x.swap_bytes() } else { x })
+}
+
+
+
+/// A raw ELF64 symbol table entry, as found in the loaded ELF, see `Symbol::raw`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct ElfSym {
+    pub st_name:  u32,
+    pub st_info:  u8,
+    pub st_other: u8,
+    pub st_shndx: u16,
+    pub st_value: u64,
+    pub st_size:  u64,
+}
+
+impl ElfSym {
+    /// The `STB_*` binding in the upper half of `st_info`.
+    pub fn st_bind(&self) -> u8 {
+        st_bind(self.st_info)
+    }
+
+    /// The `STT_*` type in the lower half of `st_info`.
+    pub fn st_type(&self) -> u8 {
+        st_type(self.st_info)
+    }
+}
+
 
 
 #[inline(always)]
 pub fn r_type(info: u64) -> u32 {
     (info & 0xFFFFFFFF) as u32
 }
+
+#[inline(always)]
+pub fn r_sym(info: u64) -> u32 {
+    (info >> 32) as u32
+}
+
+/// Widens an ELF32 `r_info`, which packs the symbol index into 24 and the type into 8 bits.
+#[inline(always)]
+pub fn r_info32(info: u32) -> u64 {
+    (((info >> 8) as u64) << 32) | ((info & 0xFF) as u64)
+}
+
+#[inline(always)]
+pub fn st_bind(info: u8) -> u8 {
+    info >> 4
+}
+
+#[inline(always)]
+pub fn st_type(info: u8) -> u8 {
+    info & 0xF
+}
@@ -1,9 +1,12 @@
 
+use crate::SegmentProtection;
 use core::fmt;
+use core::ops::Range;
 
 
 
 /// A combined error for all things about toying with ELFs, for your convenience.
+#[allow(clippy::manual_non_exhaustive)]
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum ElfError {
     /// An error that might occur while trying to parse ELF data.
@@ -21,13 +24,15 @@ pub enum ElfError {
 
 
 /// An error that might occur while trying to parse ELF data.
+#[allow(clippy::manual_non_exhaustive)]
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 #[repr(u8)]
 pub enum ParseElfError {
-    /// ELF (section) header does not fit inside this buffer, or the buffer is at least 4GiB big.
+    /// ELF (section) header does not fit inside this buffer.
     BadBufferSize = 0,
 
-    /// Raw ELF data buffer does not have the correct alignment.
+    /// The program header table is misaligned within the ELF data buffer. The buffer itself
+    /// needs no particular alignment.
     BadBufferAlignment = 1,
 
     /// Buffer does not start with the ELF magic number.
@@ -41,10 +46,12 @@ pub enum ParseElfError {
     /// used by this loader.
     BadProgramHeaderSize = 4,
 
-    /// This loader currently only supports parsing 64-bit ELF data.
+    /// This loader only supports parsing 64-bit ELF data, unless the `elf32` feature is
+    /// enabled, which adds support for 32-bit ELF data.
     NotElf64 = 5,
 
-    /// ELF does not contain a position-independent executable.
+    /// ELF does not contain a position-independent executable, but a fixed-address one, i.e.
+    /// `ET_EXEC`. Parse it with `Elf::try_parse_exec` instead.
     NotPic = 6,
 
     /// The ELF data has an endianness differing from the target system's.
@@ -53,14 +60,17 @@ pub enum ParseElfError {
     /// The ELF data contains code of an incompatible instruction set architecture (ISA).
     BadIsa = 8,
 
-    /// The reported buffer range of the ELF program headers overflows or goes past the end of the
-    /// entire ELF buffer.
+    /// The reported buffer range of the ELF program headers overflows, goes past the end of the
+    /// entire ELF buffer, or past its first 4GiB.
     ProgramHeaderOverflow = 9,
 
-    /// The ELF data contains an entry point that does not lie within the `.text` section.
+    /// The ELF data contains an entry point that does not lie within the `.text` section, i.e.
+    /// not within the file-backed part of an executable segment, or that is not aligned to
+    /// whole instructions.
     BadEntry = 10,
 
-    /// A program header requests copying data outside the ELF buffer's range.
+    /// A program header requests copying data outside the ELF buffer's range, or past its first
+    /// 4GiB.
     BadPhRange = 11,
 
     /// A program header wants some impressive virtual memory allocation.
@@ -72,12 +82,42 @@ pub enum ParseElfError {
     /// A program header wants to align its segment to more than 4GiB.
     ExcessiveAlignment = 14,
 
+    /// The ELF header reports an ELF section header struct size that does not match the struct
+    /// used by this loader.
+    BadSectionHeaderSize = 15,
+
+    /// The reported buffer range of the ELF section headers overflows or goes past the end of the
+    /// entire ELF buffer.
+    SectionHeaderOverflow = 16,
+
+    /// A loaded segment asks to be both writable and executable, which `Elf::try_parse_strict`
+    /// refuses to downgrade to read-execute.
+    WritableAndExecutable = 17,
+
+    /// The ELF data is neither an `ET_DYN` nor an `ET_EXEC` executable, but e.g. an object
+    /// file or a core dump.
+    NotExecutable = 18,
+
+    /// A program header wants to align its segment to something other than a power of two.
+    BadAlignment = 19,
+
+    /// Two `PT_LOAD` program headers want to load their segments to overlapping addresses.
+    OverlappingSegments = 20,
+
+    /// The ELF identification or header reports an ELF version other than `EV_CURRENT`.
+    BadVersion = 21,
+
+    /// The ELF identification reports an OS/ABI this loader is not validated against, see
+    /// `Elf::try_parse_strict`.
+    BadOsAbi = 22,
+
     #[doc(hidden)] _Reserved,
 }
 
 
 
 /// An error that might occur while trying to load ELF segments.
+#[allow(clippy::manual_non_exhaustive)]
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 #[repr(u8)]
 pub enum LoadElfError {
@@ -91,8 +131,8 @@ pub enum LoadElfError {
     /// of memory protection.
     ///
     /// Typically, only 3 or 4 segments of type `LOAD` and 1 of type `GNU_RELRO` are
-    /// needed. The ELF loader supports a few more than that. The typical `LOAD`
-    /// segments are:
+    /// needed. The ELF loader supports up to 16, or any number with the `alloc` feature.
+    /// The typical `LOAD` segments are:
     ///
     /// - `LOAD` with `PF_R | PF_W` for the `DYNAMIC` segment.
     /// - `LOAD` with `PF_R | PF_X` for read-only data and executable code.
@@ -100,8 +140,9 @@ pub enum LoadElfError {
     /// - `LOAD` with `PF_R | PF_W` for initialised and uninitialised static data.
     /// - `GNU_RELRO` to make the loaded `DYNAMIC` segment read-only.
     ///
-    /// If you get this error, then you most likely want to check your linker script.
-    TooManySegments = 2,
+    /// If you get this error, then you most likely want to check your linker script. `seen`
+    /// is how many such segments there are, capped at 255, and `max` how many are supported.
+    TooManySegments { seen: u8, max: u8 } = 2,
 
     /// The ELF data contains more than one `DYNAMIC` segment. This dead simple ELF
     /// parser/loader only supports one, though.
@@ -115,12 +156,49 @@ pub enum LoadElfError {
     /// only accepts re-locatable executables.
     NoDynamicSegments = 4,
 
+    /// The ELF data contains more than one `TLS` segment, but a module only ever has a
+    /// single TLS template.
+    MultipleTlsSegments = 5,
+
+    /// The ELF was parsed with `Elf::try_parse_foreign`, or with `Elf::try_parse_for` while not
+    /// being of the native ISA, and is thus only good for inspection.
+    ForeignElf = 6,
+
+    /// `Elf::load_boxed` could not allocate the load buffer.
+    OutOfMemory = 7,
+
+    /// The `LoadedElfMeta` given to `LoadedElf::from_parts` is inconsistent, e.g. its
+    /// alignment is not a power of two, or its `DYNAMIC` segment is misaligned.
+    BadMetadata = 8,
+
+    /// A read-write and a read-execute segment overlap or share a page, e.g. due to linking
+    /// with `-z noseparate-code` and without page-aligned segments. No protection would suit
+    /// both of them without violating `W^X`.
+    SharedPageConflict = 9,
+
+    /// The `PT_DYNAMIC` segment is not fully covered by any `PT_LOAD` segment, as is the
+    /// case for malformed or badly stripped ELFs. Its contents would be garbage.
+    DynamicNotLoadable = 10,
+
+    /// There is a program header of an unknown type, `p_type`, which
+    /// `LoadOptions::strict_unknown_segments` refuses to ignore.
+    UnsupportedSegment { p_type: u32 } = 11,
+
+    /// `Elf::try_load_in_place` was given an ELF whose segments would have to be moved, i.e.
+    /// whose file layout is not its memory layout.
+    NotInPlaceLoadable = 12,
+
     #[doc(hidden)] _Reserved,
 }
 
 
 
 /// An error that might occur while trying to re-locate and memory-protect an ELF.
+///
+/// An ELF may have any mix of `DT_REL`, `DT_RELA` and `DT_RELR` tables, plus a `DT_JMPREL`
+/// PLT table of either `Rel` or `Rela` entries. Each table is checked on its own, e.g.
+/// `BadRelSize` only ever concerns `DT_REL`, and `BadPltSize` only `DT_JMPREL`.
+#[allow(clippy::manual_non_exhaustive)]
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 #[repr(u8)]
 pub enum RelocElfError {
@@ -167,7 +245,83 @@ pub enum RelocElfError {
 
     /// An attempt of restricting memory access rights for a region of the loaded ELF's
     /// memory failed.
-    MemProtectFailed = 13,
+    ///
+    /// Protection requests are made in a fixed order. All requests before the failed one
+    /// succeeded, and no further ones were made. See `protect_failure`.
+    MemProtectFailed {
+        /// Start of the byte range that failed to be protected.
+        start:   usize,
+        /// End of that byte range, exclusive.
+        end:     usize,
+        /// The memory protection that failed to be applied.
+        protect: SegmentProtection,
+    } = 13,
+
+    /// The `PT_DYNAMIC` segment reported a string table that is out of bounds.
+    BadStrTabRange = 14,
+
+    /// The `PT_DYNAMIC` segment reported a symbol table that is out of bounds or
+    /// under-aligned.
+    BadSymTabRange = 15,
+
+    /// A re-location refers to a symbol that lies past the end of the symbol table.
+    BadSymbolIndex = 16,
+
+    /// A re-location refers to a symbol that neither the ELF nor the resolver define.
+    UnresolvedSymbol = 17,
+
+    /// A thread-local storage re-location was found, but there is no `TLS` segment that
+    /// would define a TLS block.
+    NoTlsSegment = 18,
+
+    /// The `PT_DYNAMIC` segment reported a bad `Relr` size.
+    BadRelrSize = 19,
+
+    /// The `PT_DYNAMIC` segment reported a `Relr` table that is out of bounds or
+    /// under-aligned, or a `Relr` entry wants to modify memory out of range.
+    BadRelrRange = 20,
+
+    /// Re-locating is currently only supported for ELF64 data, but this is ELF32 data.
+    UnsupportedElfClass = 21,
+
+    /// A fixed-address executable can only be re-located to its link-time base address.
+    BadFixedBase = 22,
+
+    /// Fixed-address executables are only supported if they need no dynamic linking at all,
+    /// but this one has a `PT_DYNAMIC` segment.
+    DynamicFixedBase = 23,
+
+    /// There is an `IRELATIVE` re-location, but running its resolver was not allowed via
+    /// `LoadedElf::run_ifunc_resolvers`.
+    IfuncDisabled = 24,
+
+    /// `IRELATIVE` resolvers run in the ELF loader's address space, so the base address must
+    /// be `LoadedElf::loader_base`.
+    IfuncBaseMismatch = 25,
+
+    /// The `PT_DYNAMIC` segment reported a PLT re-location table, i.e. `DT_JMPREL`, that is
+    /// out of bounds or not properly aligned.
+    BadPltRange = 26,
+
+    /// The `PT_DYNAMIC` segment reported a `DT_PLTREL` that is neither `DT_REL` nor `DT_RELA`,
+    /// or a `DT_PLTRELSZ` that is no multiple of the entry size.
+    BadPltSize = 27,
+
+    /// The `PT_DYNAMIC` segment reported a `DT_RELCOUNT` or `DT_RELACOUNT` that exceeds the
+    /// number of entries of its re-location table.
+    BadRelCount = 28,
+
+    /// A re-location wants to modify an executable segment, but the ELF did not ask for text
+    /// re-locations via `DF_TEXTREL`.
+    TextRelocation = 29,
+
+    /// A 32-bit re-location computed a value that does not fit into its 32 bits, e.g. because
+    /// the ELF got re-located too far away from a symbol it refers to.
+    RelocationOverflow = 30,
+
+    /// The ELF was already re-located once, and it has `Rel` or `Relr` entries, whose implicit
+    /// addends the first re-location overwrote.
+    ImplicitAddends = 31,
 
     #[doc(hidden)] _Reserved,
 }
@@ -180,29 +334,31 @@ impl ParseElfError {
         use self::ParseElfError::*;
 
         match *self {
-            BadBufferSize         => "The ELF buffer is over 4GiB in size or smaller than a header",
-            BadBufferAlignment    => "The ELF buffer is not properly aligned for one of the many \
-                                      ELF headers; to be extra sure, page-align your ELF buffer",
+            BadBufferSize         => "The ELF buffer is smaller than a header",
+            BadBufferAlignment    => "The ELF buffer's program header table is misaligned",
             BufferNotElf          => "The ELF buffer does not contain an ELF magic number",
             BadHeaderSize         => "The ELF buffer's reported header size does not match the \
-                                      loader's expected header size of 64 bytes",
+                                      loader's expected header size of 64 bytes, or 52 for ELF32",
             BadProgramHeaderSize  => "The ELF buffer's reported program header size does not match \
-                                      the loader's expected program header size of 56 bytes",
-            NotElf64              => "Currently, this loader only supports the ELF64 format, but \
-                                      the given buffer does not contain ELF64 data",
+                                      the loader's expected program header size of 56 bytes, or \
+                                      32 for ELF32",
+            NotElf64              => "The given buffer does not contain ELF64 data, or ELF32 data \
+                                      with the `elf32` feature enabled",
             NotPic                => "The ELF buffer does not contain position-independent code, \
-                                      which is not supported - Ensure the ELF type is set to \
-                                      `ET_DYN`",
+                                      which is only supported by `Elf::try_parse_exec` - Ensure \
+                                      the ELF type is set to `ET_DYN`",
             BadEndian             => "The ELF buffer is not in the native endian format, which is \
                                       currently and probably forever unsupported",
             BadIsa                => "The ELF buffers code is not compiled for the native ISA, as \
                                       in e.g. trying to run RISC-V code on an ARM chip",
             ProgramHeaderOverflow => "The ELF buffer reports a program headers range that goes \
-                                      past the end of the buffer or overflows",
+                                      past the end of the buffer or its first 4GiB, or overflows",
             BadEntry              => "The ELF's reported entry point does not lie within the \
-                                      virtual address range of an executable segment",
+                                      file-backed part of an executable segment, or is \
+                                      misaligned",
             BadPhRange            => "One of the ELF's program headers reported a physical buffer \
-                                      range that goes past the end of the whole ELF buffer",
+                                      range that goes past the end of the whole ELF buffer or its \
+                                      first 4GiB",
             BadVmemRange          => "One of the ELF's program headers reported a virtual buffer \
                                       range that is over 4GiB in size or goes past the 4GiB \
                                       virtual address range",
@@ -211,6 +367,20 @@ impl ParseElfError {
                                       memory size",
             ExcessiveAlignment    => "One of the ELF's program headers reported a segment \
                                       alignment to more than 4GiB",
+            BadSectionHeaderSize  => "The ELF buffer's reported section header size does not match \
+                                      the loader's expected section header size of 64 bytes, or \
+                                      40 for ELF32",
+            SectionHeaderOverflow => "The ELF buffer reports a section headers range that goes \
+                                      past the end of the buffer or overflows",
+            WritableAndExecutable => "One of the ELF's loaded segments asks to be writable and \
+                                      executable at the same time",
+            NotExecutable         => "The ELF buffer contains neither an `ET_DYN` nor an `ET_EXEC` \
+                                      executable",
+            BadAlignment          => "One of the ELF's program headers reported a segment \
+                                      alignment that is not a power of two",
+            OverlappingSegments   => "Two of the ELF's loaded segments overlap each other",
+            BadVersion            => "The ELF buffer reports an unknown ELF version",
+            BadOsAbi              => "The ELF buffer reports an unsupported OS/ABI",
 
             _Reserved => "",
         }
@@ -229,10 +399,22 @@ impl LoadElfError {
         match *self {
             BadBufferSize           => "The given buffer is not big enough to load the ELF into",
             BadBufferAlignment      => "The given buffer is not properly aligned",
-            TooManySegments         => "The program headers describe more than 8 segments", // TODO
+            TooManySegments { .. }  => "The program headers describe more segments than \
+                                        supported",
             MultipleDynamicSegments => "There is more than one `PT_DYNAMIC` segment",
             NoDynamicSegments       => "There is no `PT_DYNAMIC` segment, but this loader only \
                                         supports re-locatable ELFs",
+            MultipleTlsSegments     => "There is more than one `PT_TLS` segment",
+            ForeignElf              => "The ELF was parsed for inspection only, as it might be of \
+                                        foreign byte order or ISA",
+            OutOfMemory             => "The load buffer could not be allocated",
+            BadMetadata             => "The loaded ELF's metadata is inconsistent",
+            SharedPageConflict      => "A read-write and a read-execute segment share a page",
+            DynamicNotLoadable      => "The `PT_DYNAMIC` segment is not part of any `PT_LOAD` \
+                                        segment",
+            UnsupportedSegment { .. } => "There is a program header of an unknown type",
+            NotInPlaceLoadable      => "The ELF's file layout is not its memory layout, so it \
+                                        can't be loaded in place",
 
             _Reserved => "",
         }
@@ -240,7 +422,18 @@ impl LoadElfError {
 }
 
 impl fmt::Display for LoadElfError {
-    #[inline] fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str(self.as_str()) }
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadElfError::TooManySegments { seen, max } => write!(f,
+                "The program headers describe {} segments, but at most {} are supported",
+                seen, max,
+            ),
+            LoadElfError::UnsupportedSegment { p_type } => write!(f,
+                "There is a program header of the unknown type {:#X}", p_type,
+            ),
+            _ => f.write_str(self.as_str()),
+        }
+    }
 }
 
 impl RelocElfError {
@@ -275,14 +468,62 @@ impl RelocElfError {
                                          CPU architecture",
             UnsupportedRelaType      => "A `Rela` table entry requires an unsupported re-location \
                                          method",
-            MemProtectFailed         => "The given memory protection function failed to restrict \
+            MemProtectFailed { .. }  => "The given memory protection function failed to restrict \
                                          access to a given range of memory",
+            BadStrTabRange           => "The `PT_DYNAMIC` segment reported a string table that \
+                                         goes past the bounds of the loaded ELF's memory region",
+            BadSymTabRange           => "The `PT_DYNAMIC` segment reported a symbol table that \
+                                         goes past the bounds of the loaded ELF's memory region \
+                                         or is not properly aligned for `Sym` structs",
+            BadSymbolIndex           => "A re-location table entry refers to a symbol that lies \
+                                         past the bounds of the loaded ELF's memory region",
+            UnresolvedSymbol         => "A re-location table entry refers to a symbol that is \
+                                         not defined by the ELF and could not be resolved",
+            NoTlsSegment             => "There is a TLS re-location, but no `PT_TLS` segment",
+            BadRelrSize              => "The `PT_DYNAMIC` segment reported a bad `Relr` entry \
+                                         size",
+            BadRelrRange             => "The `Relr` table is out of bounds or not properly \
+                                         aligned, or one of its entries points out of bounds",
+            UnsupportedElfClass      => "Re-locating ELF32 data is currently not supported",
+            BadFixedBase             => "The given base address differs from the fixed-address \
+                                         executable's link-time base address",
+            DynamicFixedBase         => "Fixed-address executables with a `PT_DYNAMIC` segment \
+                                         are not supported",
+            IfuncDisabled            => "There is an `IRELATIVE` re-location, but running ifunc \
+                                         resolvers is not allowed",
+            IfuncBaseMismatch        => "Running ifunc resolvers requires the base address to be \
+                                         the loader base",
+            BadPltRange              => "The PLT re-location table is out of bounds or not \
+                                         properly aligned",
+            BadPltSize               => "The PLT re-location table has a bad entry type or size",
+            BadRelCount              => "The count of leading `RELATIVE` re-locations exceeds \
+                                         its re-location table",
+            TextRelocation           => "A re-location wants to modify an executable segment, \
+                                         but the ELF does not allow text re-locations",
+            RelocationOverflow       => "A 32-bit re-location computed a value that does not \
+                                         fit into 32 bits",
+            ImplicitAddends          => "The ELF was already re-located, which overwrote the \
+                                         addends its `Rel` or `Relr` entries need",
 
             _Reserved => "",
         }
     }
 }
 
+impl RelocElfError {
+    /// The byte range and protection of the failed request, if this is `MemProtectFailed`.
+    ///
+    /// With this, callers can roll back or retry protecting just what is needed.
+    pub fn protect_failure(&self) -> Option<(Range<usize>, SegmentProtection)> {
+        match *self {
+            RelocElfError::MemProtectFailed { start, end, protect } => {
+                Some((start .. end, protect))
+            },
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for RelocElfError {
     #[inline] fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str(self.as_str()) }
 }
@@ -305,15 +546,166 @@ impl fmt::Display for ElfError {
     #[inline] fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use self::ElfError::*;
 
-        write!(f, "{}: {}",
-            match *self {
-                Parse(_)  => "Error trying to parse an ELF",
-                Load( _)  => "Error trying to load an ELF",
-                Reloc(_)  => "Error trying to re-locate and memory-protect an ELF",
-                _Reserved => "",
-            },
-            self.as_str()
-        )
+        match *self {
+            Parse(e)  => write!(f, "Error trying to parse an ELF: {}", e),
+            Load( e)  => write!(f, "Error trying to load an ELF: {}", e),
+            Reloc(e)  => write!(f, "Error trying to re-locate and memory-protect an ELF: {}", e),
+            _Reserved => f.write_str(": "),
+        }
+    }
+}
+
+
+
+impl ParseElfError {
+    /// The error's numeric code, which is its `repr(u8)` discriminant.
+    pub fn code(&self) -> u8 {
+        *self as u8
+    }
+
+    /// The error of the given `code`, if there is one.
+    pub fn from_code(code: u8) -> Option<Self> {
+        use self::ParseElfError::*;
+
+        Some(match code {
+             0 => BadBufferSize,
+             1 => BadBufferAlignment,
+             2 => BufferNotElf,
+             3 => BadHeaderSize,
+             4 => BadProgramHeaderSize,
+             5 => NotElf64,
+             6 => NotPic,
+             7 => BadEndian,
+             8 => BadIsa,
+             9 => ProgramHeaderOverflow,
+            10 => BadEntry,
+            11 => BadPhRange,
+            12 => BadVmemRange,
+            13 => PhSmallerThanVmem,
+            14 => ExcessiveAlignment,
+            15 => BadSectionHeaderSize,
+            16 => SectionHeaderOverflow,
+            17 => WritableAndExecutable,
+            18 => NotExecutable,
+            19 => BadAlignment,
+            20 => OverlappingSegments,
+            21 => BadVersion,
+            22 => BadOsAbi,
+            _  => return None,
+        })
+    }
+}
+
+impl LoadElfError {
+    /// The error's numeric code, which is its `repr(u8)` discriminant.
+    pub fn code(&self) -> u8 {
+        // The discriminant of a `repr(u8)` enum is its first byte, even with fields.
+        unsafe { *(self as *const Self as *const u8) }
+    }
+
+    /// The error of the given `code`, if there is one.
+    ///
+    /// A code carries no payload, so the fields of `TooManySegments` and `UnsupportedSegment`
+    /// are zeroed.
+    pub fn from_code(code: u8) -> Option<Self> {
+        use self::LoadElfError::*;
+
+        Some(match code {
+             0 => BadBufferSize,
+             1 => BadBufferAlignment,
+             2 => TooManySegments { seen: 0, max: 0 },
+             3 => MultipleDynamicSegments,
+             4 => NoDynamicSegments,
+             5 => MultipleTlsSegments,
+             6 => ForeignElf,
+             7 => OutOfMemory,
+             8 => BadMetadata,
+             9 => SharedPageConflict,
+            10 => DynamicNotLoadable,
+            11 => UnsupportedSegment { p_type: 0 },
+            12 => NotInPlaceLoadable,
+            _  => return None,
+        })
+    }
+}
+
+impl RelocElfError {
+    /// The error's numeric code, which is its `repr(u8)` discriminant.
+    pub fn code(&self) -> u8 {
+        // The discriminant of a `repr(u8)` enum is its first byte, even with fields.
+        unsafe { *(self as *const Self as *const u8) }
+    }
+
+    /// The error of the given `code`, if there is one.
+    ///
+    /// A code carries no payload, so the fields of `MemProtectFailed` are zeroed.
+    pub fn from_code(code: u8) -> Option<Self> {
+        use self::RelocElfError::*;
+
+        Some(match code {
+             0 => BadBaseAddressAlignment,
+             1 => BadDynRange,
+             2 => BadDynAlignment,
+             3 => BadRelSize,
+             4 => BadRelaSize,
+             5 => BadRelRelaTableRange,
+             6 => BadRelRelaTableAlignment,
+             7 => BadRelOffset,
+             8 => UnsupportedRelType,
+             9 => UnsupportedRelArch,
+            10 => BadRelaOffset,
+            11 => UnsupportedRelaType,
+            12 => UnsupportedRelaArch,
+            13 => MemProtectFailed { start: 0, end: 0, protect: SegmentProtection::RO },
+            14 => BadStrTabRange,
+            15 => BadSymTabRange,
+            16 => BadSymbolIndex,
+            17 => UnresolvedSymbol,
+            18 => NoTlsSegment,
+            19 => BadRelrSize,
+            20 => BadRelrRange,
+            21 => UnsupportedElfClass,
+            22 => BadFixedBase,
+            23 => DynamicFixedBase,
+            24 => IfuncDisabled,
+            25 => IfuncBaseMismatch,
+            26 => BadPltRange,
+            27 => BadPltSize,
+            28 => BadRelCount,
+            29 => TextRelocation,
+            30 => RelocationOverflow,
+            31 => ImplicitAddends,
+            _  => return None,
+        })
+    }
+}
+
+impl ElfError {
+    /// A single numeric code for any error, e.g. for C APIs. It is never zero.
+    ///
+    /// The high byte tells the kind, `1` for `Parse`, `2` for `Load`, and `3` for `Reloc`,
+    /// and the low byte is the sub-error's `code`.
+    pub fn code(&self) -> u16 {
+        use self::ElfError::*;
+
+        match *self {
+            Parse(e)  => 0x100 | (e.code() as u16),
+            Load( e)  => 0x200 | (e.code() as u16),
+            Reloc(e)  => 0x300 | (e.code() as u16),
+            _Reserved => 0xFF00,
+        }
+    }
+
+    /// The error of the given `code`, if there is one. See the sub-errors' `from_code`.
+    pub fn from_code(code: u16) -> Option<Self> {
+        let sub = code as u8;
+
+        match code >> 8 {
+            1 => ParseElfError::from_code(sub).map(ElfError::Parse),
+            2 => LoadElfError ::from_code(sub).map(ElfError::Load ),
+            3 => RelocElfError::from_code(sub).map(ElfError::Reloc),
+            _ => None,
+        }
     }
 }
 
@@ -333,12 +725,39 @@ impl From<RelocElfError> for ElfError {
 
 
 
+#[cfg(feature = "std")]
+impl std::error::Error for ParseElfError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LoadElfError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RelocElfError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ElfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use self::ElfError::*;
+
+        match self {
+            Parse(e)  => Some(e),
+            Load( e)  => Some(e),
+            Reloc(e)  => Some(e),
+            _Reserved => None,
+        }
+    }
+}
+
+
+
 #[allow(dead_code)]
 mod static_assert {
     use core::mem::size_of as sz;
     use crate::elf::*;
 
-    const fn assert(expr: bool) -> () {
+    // Indexing out of bounds in a `const` fails to compile, rather than `panic!`ing.
+    #[allow(clippy::indexing_slicing)]
+    const fn assert(expr: bool) {
         const A: [(); 1] = [()];
 
         A[(!expr) as usize]
@@ -348,4 +767,14 @@ mod static_assert {
     const SZ_PRG_HDR_64: () = assert(sz::<ElfProgramHeader>() == 56);
     const SZ_REL_16:     () = assert(sz::<ElfRel          >() == 16);
     const SZ_RELA_24:    () = assert(sz::<ElfRela         >() == 24);
+    const SZ_SYM_24:     () = assert(sz::<ElfSym          >() == 24);
+
+    const SZ_SEC_HDR_64: () = assert(sz::<ElfSectionHeader>() == 64);
+
+    const SZ_ELF_HDR_32: () = assert(sz::<ElfFileHeader32   >() == 52);
+    const SZ_PRG_HDR_32: () = assert(sz::<ElfProgramHeader32>() == 32);
+    const SZ_SEC_HDR_32: () = assert(sz::<ElfSectionHeader32>() == 40);
+    const SZ_DYN_8:      () = assert(sz::<ElfDyn32          >() ==  8);
+    const SZ_REL_8:      () = assert(sz::<ElfRel32          >() ==  8);
+    const SZ_RELA_12:    () = assert(sz::<ElfRela32         >() == 12);
 }
@@ -0,0 +1,101 @@
+
+use crate::Elf;
+use crate::dynamic::file_range;
+use crate::elf::{
+    DT_REL,
+    PF_X, PT_GNU_STACK, PT_INTERP,
+    r_sym,
+};
+use core::ffi::CStr;
+
+
+
+/// How a host should go about launching an ELF, see `Elf::launch_kind`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[repr(u8)]
+pub enum LaunchKind {
+    /// A self-contained position-independent executable. Just re-locate and run it.
+    StaticPie,
+
+    /// A position-independent executable that needs a program interpreter or symbols
+    /// from other objects, i.e. full dynamic linking.
+    DynamicPie,
+
+    /// An ELF without entry point, which is only good for looking up symbols.
+    Library,
+
+    /// A fixed-address `ET_EXEC` executable. Place it at its `Elf::fixed_base` and run it.
+    FixedExec,
+}
+
+
+
+pub fn interpreter<'a>(elf: &Elf<'a>) -> Option<&'a CStr> {
+    let raw = elf.program_headers.elf;
+    let ph  = elf.program_headers.inner.clone().find(|ph| ph.p_type == PT_INTERP)?;
+
+    // Parsing already checked the range against the ELF buffer.
+    let path = raw.get((ph.p_offset as usize) .. ((ph.p_offset + ph.p_filesz) as usize))?;
+
+    CStr::from_bytes_until_nul(path).ok()
+}
+
+pub fn stack_is_executable(elf: &Elf<'_>) -> bool {
+    elf.program_headers.inner.clone()
+        .find(|ph| ph.p_type == PT_GNU_STACK)
+        .map(|ph| (ph.p_flags & PF_X) != 0)
+        .unwrap_or(false)
+}
+
+pub fn stack_size_hint(elf: &Elf<'_>) -> Option<u64> {
+    elf.program_headers.inner.clone()
+        .find(|ph| ph.p_type == PT_GNU_STACK)
+        .map(|ph| ph.p_memsz)
+        .filter(|len| *len != 0)
+}
+
+pub fn launch_kind(elf: &Elf<'_>) -> LaunchKind {
+    // Zero is a fine entry point for ELFs linked above address zero, as it is relative to
+    // their link-time base.
+    if (elf.entry == 0) & (elf.link_base == 0) {
+        return LaunchKind::Library;
+    }
+
+    if elf.fixed_base.is_some() {
+        return LaunchKind::FixedExec;
+    }
+
+    let has_interp = elf.program_headers.inner.clone().any(|ph| ph.p_type == PT_INTERP);
+
+    if has_interp || needs_symbols(elf) {
+        LaunchKind::DynamicPie
+    } else {
+        LaunchKind::StaticPie
+    }
+}
+
+/// Whether any re-location refers to a symbol. Unreadable tables count as needing symbols,
+/// as they certainly can't be handled by simply re-locating.
+fn needs_symbols(elf: &Elf<'_>) -> bool {
+    let format   = elf.program_headers.inner.format();
+    let rel_len  = format.class.rel_len()  as u64;
+    let rela_len = format.class.rela_len() as u64;
+
+    let info    = elf.dyn_info;
+    let plt_len = if info.plt_rel == Some(DT_REL) { rel_len } else { rela_len };
+    let tabs    = [
+        (info.rel   .off, info.rel   .len, rel_len ),
+        (info.rela  .off, info.rela  .len, rela_len),
+        (info.jmprel.off, info.jmprel.len, plt_len ),
+    ];
+
+    tabs.iter().filter(|(off, len, _)| (*off != 0) & (*len != 0)).any(|&(off, len, ent_len)| {
+        let table = match file_range(elf, off, len) { Some(t) => t, None => return true };
+
+        // The first two fields of `Rel` and `Rela` are the same.
+        table.chunks(ent_len as usize).any(|ent| match format.read_rel(ent) {
+            Some(rel) => r_sym(rel.r_info) != 0,
+            None      => true,
+        })
+    })
+}
@@ -14,19 +14,32 @@ A dead simple crate for ELF64 parsing and loading.
   happy path.
 - This crate does its job in a quite small amount of code, despite all the error checking.
 - No dependencies, except for `libcore`.
+- ELF32 data can be parsed and loaded, too, if you enable the `elf32` feature.
+- With the `alloc` feature, `Elf::load_boxed` allocates and frees the load buffer for you, and
+  `Elf::bootstrap` goes from raw ELF data to a ready ELF in a single call.
+- With the `std` feature, all error types implement `std::error::Error`.
+- With the `capi` feature, the `capi` module exports `extern "C"` functions to parse, load and
+  re-locate ELFs from C or C++ hosts.
+- With the `serde` feature, `LoadedElfMeta` can be serialised, e.g. to load an ELF in one
+  process and to re-locate it in another one.
+- With the `no_panic` feature, `cargo clippy` rejects indexing, slicing, `unwrap` and the like
+  within this crate, i.e. most ways to `panic!`.
 
 ## TODOs
 
-- Currently, only page-aligned re-locatable `x86_64` executables are supported. However, at least
-  support for AArch64 and RISC-V is planned.
-- An other "not yet implemented" feature is dynamic linking. This is required to eventually make
-  this crate a minimal drop-in replacement for `dlopen`. You cannot currently look up symbols, so
-  all you get from loading an ELF is its entry point.
+- Currently, only page-aligned re-locatable `x86_64` executables are fully supported. AArch64
+  and RV64 ones only get the basic `Rela` re-locations, without thread-local storage or ifuncs.
+- Dynamic linking is only half done. Undefined symbols can be resolved with a `ResolveFn`,
+  exported symbols can be looked up, and `needed_libraries` lists the `DT_NEEDED` entries, but
+  loading those is up to you. Likewise, TLS re-locations are applied and `tls_template` hands out
+  the thread-local storage image, but setting up each thread's TLS block is up to you. I.e. this
+  crate is not yet a minimal drop-in replacement for `dlopen`.
 - Currently, custom linker scripts have to be used that page-align all loadable sections. To relax
   this requirement, I'd need help finding and understanding the source code of `ld.so` from `glibc`.
   I.e. this crate does not currently act as a program interpreter.
 - Just `Ctrl`+`F` this crate for `TODO` and `FIXME`. ಥ‿ಥ
-- Guarantee 100% that no `panic!`s will occur.
+- Guarantee 100% that no `panic!`s will occur. The `no_panic` feature and a fuzzing test get
+  close, but arithmetic overflow is not covered yet.
 
 ## Getting Started
 
@@ -35,7 +48,7 @@ is as easy as following these few steps:
 
 1. Have a byte slice containing all of your ELF data. This might originate from loading a
    file or from invoking `include_bytes!("path/to/elf")`. Note that shared objects (`.so`)
-   are also ELF files.
+   are also ELF files. The slice needs no particular alignment.
 2. Call `Elf::try_parse` with your ELF slice. On success it will return a small parsed `Elf`
    struct.
 3. Call the `Elf`'s `mem_len` and `mem_align` functions. Those will give you the layout information
@@ -48,11 +61,12 @@ is as easy as following these few steps:
    callback. The base address is where the final running program will think its first memory page is
    located. This allows you to re-locate an ELF from within a different address space. If you don't
    change the memory mapping of the loaded ELF, then the base address is the pointer of your
-   allocated memory block's slice. You can get this pointer from `LoadedElf::loader_base`.
+   allocated memory block's slice. You can get this pointer from `LoadedElf::loader_base`. If the
+   ELF refers to symbols it does not define itself, also pass a symbol resolving callback.
 6. The memory protection function receives base addresses, a slice, and the requested memory
    protection level. You can use this callback to actually apply memory protection flags as
    specified by the ELF data. Do not assume that protection regions won't overlap and just blindly
-   handle each request in order.
+   handle each request in order, unless you opted into `LoadedElf::coalesce_protection`.
 7. On success, the `LoadedElf::try_reloc` function returns a `ReadyElf`. This struct provides
    functions needed to run the ELF or grab its memory range.
 
@@ -60,7 +74,6 @@ is as easy as following these few steps:
 
 ```
 # use elf_loader::*;
-# use std::mem;
 # fn get_aligned_buffer() -> &'static [u8] { &[][..] }
 # fn alloc_aligned(_: usize, _: usize) -> &'static mut [u8] { &mut [][..] }
 # fn dealloc(_: &[u8]) {}
@@ -75,8 +88,8 @@ let elf = Elf::try_parse(elf_data)?;
 
 // For the next step, we need to allocate a bunch of page-aligned memory.
 // You might as well use a pre-allocated buffer from your `.bss` section.
-let align = elf.mem_align() as usize;
-let size  = elf.mem_len()   as usize;
+let align = elf.mem_align_usize();
+let size  = elf.mem_len_usize();
 let mem   = alloc_aligned(size, align);
 
 // Now, load the ELF into our allocated memory. After that, you are free to throw
@@ -90,7 +103,7 @@ drop(elf_data);
 // space, you can use `loader_base` as a base address, which is just `mem.as_ptr()`.
 // Otherwise, you need a base address within the loaded ELF's address space.
 let base  = loaded_elf.loader_base();
-let ready = match loaded_elf.try_reloc(base, Some(protection_fn)) {
+let ready = match loaded_elf.try_reloc(base, Some(protection_fn), None) {
     Ok(r) => r,
 
     // In case of an error, you get back your memory slice to de-allocate or inspect
@@ -106,7 +119,7 @@ let ready = match loaded_elf.try_reloc(base, Some(protection_fn)) {
 
 // Now you can grab an entry function pointer for whichever address space.
 // Go on and have fun!
-let main: fn() = unsafe { mem::transmute(ready.p_entry()) };
+let main: fn() = unsafe { ready.entry_fn() };
 unsafe { (main)() };
 
 // Done? Better not leak all the precious memory. Only you have control
@@ -136,12 +149,28 @@ calling the entry function.
  */
 
 #![no_std]
+#![cfg_attr(feature = "no_panic", deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used,
+    clippy::unreachable,
+    clippy::todo,
+    clippy::unimplemented,
+))]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 // TODO IMPORTANT guarantee 100% that this can't `panic!`, at all, not counting Debug/Display
-// TODO add thread-local storage (TLS) support
 
-use core::slice::{ self, Iter };
+use core::convert::TryFrom;
+use core::iter;
+use core::slice;
 use core::marker::PhantomData;
+use core::ffi::CStr;
 use core::ops::Range;
 use core::mem;
 
@@ -152,18 +181,71 @@ mod error;
 mod parse;
 mod load;
 mod reloc;
+mod dynamic;
+mod digest;
+mod protect;
+mod symbol;
+mod version;
+mod boot;
+mod plt;
+mod launch;
+mod tls;
+mod section;
+mod note;
+mod parts;
+mod debug;
+mod batch;
+#[cfg(all(feature = "alloc", target_arch = "x86_64"))]
+mod run;
+#[cfg(feature = "alloc")]
+mod owned;
+/// `extern "C"` functions for the whole pipeline, to embed this loader in non-Rust hosts.
+///
+/// Handles are boxed and opaque, and errors are `ElfError::code`s.
+#[cfg(feature = "capi")]
+pub mod capi;
 
 pub use self::error::{ ElfError, ParseElfError, LoadElfError, RelocElfError };
+pub use self::protect::{ ProtectionMap, LoadedSegment, LoadedSegments };
+pub use self::boot::{ BootHeader, BootSegment, BOOT_SEGMENTS_LEN };
+pub use self::launch::LaunchKind;
+pub use self::dynamic::{ NeededLibraries, DynFlags };
+pub use self::note::CetFeatures;
+pub use self::tls::TlsTemplate;
+pub use self::section::{ SectionHeaders, SectionHeader };
+pub use self::symbol::{ SymbolIter, Symbol, SymKind, SymBinding };
+pub use self::parts::LoadedElfMeta;
+pub use self::load::LoadOptions;
+pub use self::batch::SegmentDesc;
+pub use self::elf::{ ElfProgramHeader, ElfSym };
+#[cfg(feature = "alloc")]
+pub use self::owned::{ OwnedLoadedElf, OwnedReadyElf, BasePolicy };
 
 use self::elf::{
-    ElfProgramHeader, ElfDyn,
+    ElfClass, ElfDyn, DT_NULL,
     PF_R, PF_W, PF_X, PF_RW, PF_RX,
-    PT_DYNAMIC, PT_GNU_RELRO, PT_GNU_STACK, PT_LOAD, PT_NULL,
+    PT_DYNAMIC, PT_GNU_RELRO, PT_GNU_STACK, PT_LOAD, PT_NULL, PT_TLS,
 };
 
-use self::parse::try_parse_elf;
-use self::load::try_load_elf;
-use self::reloc::try_reloc_elf;
+use self::parse::{
+    try_parse_elf, try_parse_elf_strict, try_parse_elf_exec, try_parse_elf_foreign,
+    try_parse_elf_for,
+    try_parse_elf_lazy, validate_lazy_elf,
+    check_ph_range, RawProgramHeaders,
+};
+use self::load::{ try_load_elf, try_load_elf_in_place };
+use self::reloc::{ try_reloc_elf, reloc_elf_into, validate_reloc_elf, request_protection };
+use self::digest::{ reloc_fingerprint, content_digest };
+use self::symbol::SymTabs;
+use self::boot::fill_boot_header;
+use self::protect::shares_pages;
+use self::plt::verify_plt;
+use self::launch::{ interpreter, launch_kind, stack_is_executable, stack_size_hint };
+use self::dynamic::{ DynInfo, soname };
+use self::note::{ build_id, cet_features };
+use self::tls::TlsSegment;
+use self::parts::{ into_parts, try_from_parts, copy_into };
+use self::batch::Protector;
 
 
 
@@ -171,29 +253,174 @@ use self::reloc::try_reloc_elf;
 /// to the program headers required for loading an ELF.
 ///
 /// This struct only accepts ELF data that fits within a 4GiB address range if
-/// loaded at address zero.
+/// loaded at address zero. Only offsets into the loaded memory are 32-bit, though. The base
+/// address the ELF is re-located to is a full pointer, so the ELF may well run above 4GiB.
+/// Likewise, the ELF buffer may be larger than 4GiB, as long as the program headers and the
+/// data they copy lie within its first 4GiB.
 #[derive(Clone)]
 pub struct Elf<'a> {
     program_headers: ProgramHeaders<'a>,
-    mem_len:   u32,
-    mem_align: u32,
-    entry:     u32,
+    section_headers: Result<SectionHeaders<'a>, ParseElfError>,
+    mem_len:    u32,
+    align_log2: u8,
+    entry:      u32,
+    link_base:  u64,
+    fixed_base: Option<u64>,
+    foreign:    bool,
+    file:       FileInfo,
+    dyn_info:   DynInfo,
+}
+
+/// The few file header fields that are of any interest after parsing.
+#[derive(Copy, Clone)]
+struct FileInfo {
+    kind:    u16,
+    machine: u16,
+    flags:   u32,
+    os_abi:  u8,
+    phoff:   u64,
 }
 
 impl<'a> Elf<'a> {
     /// Tries parsing a buffer as an ELF binary and partially verifies ELF headers.
+    ///
+    /// This is lenient about segments that ask to be writable and executable at the same time.
+    /// Those are silently made read-execute only. Use `try_parse_strict` to reject them.
     pub fn try_parse(raw: &'a [u8]) -> Result<Self, ParseElfError> {
         try_parse_elf(raw)
     }
 
+    /// Like `try_parse`, but fails with `ParseElfError::WritableAndExecutable` if any loaded
+    /// segment asks to be writable and executable, instead of making it read-execute.
+    ///
+    /// This also fails with `ParseElfError::BadOsAbi` for any `os_abi` other than System V
+    /// or GNU, as this loader is not validated against any other.
+    pub fn try_parse_strict(raw: &'a [u8]) -> Result<Self, ParseElfError> {
+        try_parse_elf_strict(raw)
+    }
+
+    /// Like `try_parse`, but also accepts fixed-address executables, i.e. `ET_EXEC` ones.
+    ///
+    /// Their segments are treated as relative to `fixed_base`, and must all lie within the
+    /// 4GiB above it. Such an ELF may only be re-located to its `fixed_base`, and only if it
+    /// has no `PT_DYNAMIC` segment, as re-locating it then boils down to not touching it at all.
+    /// Dynamic section look-ups like `soname` work just like for `ET_DYN` ELFs, though.
+    pub fn try_parse_exec(raw: &'a [u8]) -> Result<Self, ParseElfError> {
+        try_parse_elf_exec(raw)
+    }
+
+    /// Like `try_parse`, but for inspecting ELF data of any byte order and ISA.
+    ///
+    /// All multi-byte fields are byte-swapped on access if need be. The result reports the
+    /// very same things as an `Elf` from `try_parse` would, but refuses to be loaded.
+    pub fn try_parse_foreign(raw: &'a [u8]) -> Result<Self, ParseElfError> {
+        try_parse_elf_foreign(raw)
+    }
+
+    /// Like `try_parse`, but accepts exactly those ISAs, i.e. `e_machine`s, that `accept`
+    /// says yes to, native or not. E.g. cross-tooling may inspect `EM_X86_64` ELFs on an
+    /// AArch64 host this way.
+    ///
+    /// Only ELFs of the native ISA may be loaded. All others fail to load with
+    /// `LoadElfError::ForeignElf`, so that nothing ever runs non-native code. Unlike
+    /// `try_parse_foreign`, this still insists on native byte order.
+    pub fn try_parse_for(raw: &'a [u8], accept: fn(machine: u16) -> bool)
+    -> Result<Self, ParseElfError> {
+        try_parse_elf_for(raw, accept)
+    }
+
+    /// Like `try_parse`, but only verifies the ELF header and the program header table.
+    ///
+    /// All the per-segment range checks are deferred to `LazyElf::validate`, which is handy
+    /// for inspection tools that only care about a few specific program headers.
+    pub fn try_parse_lazy(raw: &'a [u8]) -> Result<LazyElf<'a>, ParseElfError> {
+        try_parse_elf_lazy(raw)
+    }
+
     /// Tries loading the ELF into some page-aligned buffer.
     ///
     /// This does not yet re-locate or memory-protect the loaded ELF, in case you want to
     /// delay those steps or handle them in another process or thread.
     ///
     /// The given buffer must have `mem_align` alignment and be at least `mem_len` bytes in size.
+    ///
+    /// An `Elf` is never changed by loading it. It may be loaded any number of times into
+    /// distinct buffers, and each resulting `LoadedElf` is independent of the others and of
+    /// the `Elf` itself, e.g. to map the same plugin into many slots at different bases.
     pub fn try_load<'b>(&self, mem: &'b mut [u8]) -> Result<LoadedElf<'b>, LoadElfError> {
-        try_load_elf(self, mem)
+        try_load_elf(self, mem, LoadOptions::default())
+    }
+
+    /// Like `try_load`, but with some `LoadOptions`.
+    ///
+    /// With `assume_zeroed`, the caller promises that the buffer is all zeroes, and the
+    /// loader trusts that promise instead of zero-filling it. See `LoadOptions`.
+    pub fn try_load_with<'b>(&self, mem: &'b mut [u8], opts: LoadOptions)
+    -> Result<LoadedElf<'b>, LoadElfError> {
+        try_load_elf(self, mem, opts)
+    }
+
+    /// Parses and loads the ELF file in `mem` right where it is, without copying any segments.
+    ///
+    /// This suits ELF files that are already laid out like their loaded memory, e.g. ones
+    /// linked with page-aligned segments whose `p_offset` equals their `p_vaddr`, starting
+    /// with one that maps the file header at address zero. Only the
+    /// zero-filled tails past `p_filesz` are written. If any segment would have to be moved,
+    /// this fails with `LoadElfError::NotInPlaceLoadable`, and `mem` stays untouched.
+    ///
+    /// `mem` starts with the ELF file, and may extend past its end to fit `mem_len`. It is
+    /// parsed just like with `try_parse`.
+    pub fn try_load_in_place(mem: &mut [u8]) -> Result<LoadedElf<'_>, ElfError> {
+        try_load_elf_in_place(mem)
+    }
+
+    /// Like `try_load`, but allocates a suitable load buffer on the heap.
+    ///
+    /// The buffer's size is rounded up to a multiple of `mem_align`. It is freed as soon as
+    /// the resulting `OwnedLoadedElf`, or the `OwnedReadyElf` it turns into, is dropped, or
+    /// if re-locating fails.
+    #[cfg(feature = "alloc")]
+    pub fn load_boxed(&self) -> Result<OwnedLoadedElf, LoadElfError> {
+        owned::load_boxed(self)
+    }
+
+    /// Parses, loads and re-locates raw ELF data in one go, into a heap-allocated buffer.
+    ///
+    /// This is `try_parse`, `load_boxed` and `OwnedLoadedElf::try_reloc` without any symbol
+    /// resolver. The same requirements for `raw` apply. On any failure, the buffer is freed.
+    #[cfg(feature = "alloc")]
+    pub fn bootstrap(raw: &[u8], base: BasePolicy, prot: Option<ProtectFn>)
+    -> Result<OwnedReadyElf, ElfError> {
+        owned::bootstrap(raw, base, prot)
+    }
+
+    /// Tries loading and re-locating the ELF into a buffer that already held a ready ELF,
+    /// e.g. to hot-reload a rebuilt plugin.
+    ///
+    /// As the buffer and `base` stay the same, the new `ReadyElf` lives at the very same
+    /// addresses as the old one. Entry and symbol pointers must nonetheless be fetched anew.
+    ///
+    /// If `prot` is given, the whole buffer is made read-write first, so that the buffer can
+    /// be zeroed again regardless of how the previous ELF protected it. See `try_load` and
+    /// `LoadedElf::try_reloc` for all other requirements.
+    pub fn try_reload_into<'b>(
+        &self,
+        mem:     &'b mut [u8],
+        base:    *mut u8,
+        prot:    Option<ProtectFn>,
+        resolve: Option<ResolveFn>,
+    ) -> Result<ReadyElf<'b>, ElfError> {
+        if let Some(prot) = prot {
+            let mem_len = mem.len();
+
+            let p_base  = mem.as_mut_ptr();
+
+            request_protection(prot, SegmentProtection::RW, p_base, base, mem_len, 0 .. mem_len)?;
+        }
+
+        self.try_load(mem)?
+            .try_reloc(base, prot, resolve)
+            .map_err(|(_, e)| e.into())
     }
 
     /// Provides an iterator over the ELF's program headers.
@@ -201,56 +428,474 @@ impl<'a> Elf<'a> {
         self.program_headers.clone()
     }
 
+    /// Provides an iterator over the ELF's section headers.
+    ///
+    /// Loaders ignore section headers, and so does parsing. A broken section header table just
+    /// looks empty here. Use `try_section_headers` to tell it apart from a stripped one.
+    pub fn section_headers(&self) -> SectionHeaders<'a> {
+        let format = self.program_headers.inner.format();
+
+        self.try_section_headers().unwrap_or_else(|_| SectionHeaders::empty(format))
+    }
+
+    /// Like `section_headers`, but fails if the section header table is out of bounds, or if
+    /// its entries are of the wrong size.
+    pub fn try_section_headers(&self) -> Result<SectionHeaders<'a>, ParseElfError> {
+        self.section_headers.clone()
+    }
+
     /// Minimum number of bytes to allocate to load this ELF.
     pub fn mem_len(&self) -> u32 {
         self.mem_len
     }
 
+    /// Offset of the entry function into the loaded ELF's memory, or zero if there is none.
+    ///
+    /// For ELFs linked above address zero, see `link_base`, zero may just as well be the very
+    /// first loaded byte. Shared objects linked at zero with an `e_entry` of zero have no
+    /// entry function, and thus need no executable segment at all.
+    pub fn entry(&self) -> u32 {
+        self.entry
+    }
+
     /// Minimum alignment, in bytes, of the to-be-allocated load buffer.
+    ///
+    /// This is always a power of two.
     pub fn mem_align(&self) -> u32 {
-        self.mem_align
+        1 << self.align_log2
+    }
+
+    /// `mem_len`, as a `usize`, e.g. to allocate the load buffer.
+    pub fn mem_len_usize(&self) -> usize {
+        self.mem_len as usize
+    }
+
+    /// `mem_align`, as a `usize`, e.g. to allocate the load buffer.
+    pub fn mem_align_usize(&self) -> usize {
+        1 << self.align_log2
+    }
+
+    /// The log2 of `mem_align`, e.g. for allocators taking an alignment order.
+    pub fn mem_align_log2(&self) -> u8 {
+        self.align_log2
+    }
+
+    /// The file header's `e_type`, i.e. `ET_DYN` or, for `try_parse_exec`, `ET_EXEC`.
+    pub fn elf_type(&self) -> u16 {
+        self.file.kind
+    }
+
+    /// The file header's `e_machine`, i.e. the ISA, like `EM_X86_64`.
+    pub fn machine(&self) -> u16 {
+        self.file.machine
+    }
+
+    /// The identification's `EI_OSABI` byte, e.g. `ELFOSABI_SYSV` (0) or `ELFOSABI_GNU` (3).
+    ///
+    /// Only `try_parse_strict` cares about this.
+    pub fn os_abi(&self) -> u8 {
+        self.file.os_abi
+    }
+
+    /// The file header's processor-specific `e_flags`, e.g. to tell RISC-V ABI variants apart.
+    pub fn flags(&self) -> u32 {
+        self.file.flags
+    }
+
+    /// The link-time address the loaded memory starts at, i.e. what `mem_len` and all offsets
+    /// into the loaded memory are relative to.
+    ///
+    /// This is the lowest `PT_LOAD` address, rounded down to `mem_align`. It is zero for most
+    /// position-independent ELFs, but some shared objects are linked at a preferred address.
+    /// Those may still be re-located anywhere, unlike fixed-address executables.
+    pub fn link_base(&self) -> u64 {
+        self.link_base
+    }
+
+    /// The link-time base address of a fixed-address executable, see `try_parse_exec`.
+    ///
+    /// This is `link_base`, but `None` for position-independent ELFs, which may be re-located
+    /// anywhere.
+    pub fn fixed_base(&self) -> Option<u64> {
+        self.fixed_base
+    }
+
+    /// Rounds a raw pointer up to the next multiple of `mem_align`.
+    ///
+    /// Allocate at least `mem_len + mem_align - 1` bytes to be able to fit the whole ELF
+    /// behind the aligned pointer. The result is also a suitable `base` for `try_reloc`.
+    pub fn align_base(&self, raw_ptr: *mut u8) -> *mut u8 {
+        let mask = self.mem_align_usize() - 1;
+
+        match (raw_ptr as usize) & mask {
+            0   => raw_ptr,
+            rem => raw_ptr.wrapping_add(mask + 1 - rem),
+        }
+    }
+
+    /// The path of the program interpreter requested by a `PT_INTERP` header, if any.
+    pub fn interpreter(&self) -> Option<&'a CStr> {
+        interpreter(self)
+    }
+
+    /// Like `interpreter`, but as a string slice.
+    ///
+    /// Returns `None` if there is no `PT_INTERP` header, if the path lacks its terminating NUL
+    /// within the header's file range, or if it is not valid UTF-8.
+    pub fn interpreter_str(&self) -> Option<&'a str> {
+        interpreter(self)?.to_str().ok()
+    }
+
+    /// The GNU build ID, i.e. the descriptor of the `NT_GNU_BUILD_ID` note of a `PT_NOTE`
+    /// segment, as put there by `ld --build-id`.
+    ///
+    /// Returns `None` if there is no such note, or if the notes before it are broken.
+    pub fn build_id(&self) -> Option<&'a [u8]> {
+        build_id(self)
+    }
+
+    /// The Intel CET features of the `GNU_PROPERTY_X86_FEATURE_1_AND` property, as found in
+    /// the `PT_GNU_PROPERTY` segment of ELFs built with `-fcf-protection`.
+    ///
+    /// A host may refuse to run an ELF with shadow stacks or indirect branch tracking enabled,
+    /// unless the ELF is marked as compatible with them. Everything is `false` for ELFs of
+    /// other ISAs and for ones without such a property. Properties past the first broken one
+    /// are ignored.
+    pub fn cet_features(&self) -> CetFeatures {
+        cet_features(self)
+    }
+
+    /// The shared object name from the `DT_SONAME` tag.
+    ///
+    /// Returns `None` if there is no such tag, no string table, or if the name is not
+    /// valid UTF-8.
+    pub fn soname(&self) -> Option<&'a str> {
+        soname(self)
+    }
+
+    /// Provides an iterator over the names of the shared objects this ELF depends on.
+    pub fn needed_libraries(&self) -> NeededLibraries<'a> {
+        NeededLibraries::new(self)
+    }
+
+    /// Whether the `PT_GNU_STACK` header asks for an executable stack.
+    ///
+    /// Without such a header, the stack is assumed to be non-executable.
+    pub fn stack_is_executable(&self) -> bool {
+        stack_is_executable(self)
+    }
+
+    /// The stack size requested by the `PT_GNU_STACK` header, in bytes.
+    ///
+    /// Returns `None` if there is no such header, or if it leaves the size up to the host.
+    pub fn stack_size_hint(&self) -> Option<u64> {
+        stack_size_hint(self)
+    }
+
+    /// Classifies the ELF by how to launch it.
+    ///
+    /// - `Library` if there is no entry point.
+    /// - `FixedExec` for fixed-address executables, see `fixed_base`.
+    /// - `DynamicPie` if there is a `PT_INTERP` header, or if any re-location in the `Rel`,
+    ///   `Rela` or PLT tables refers to a symbol.
+    /// - `StaticPie` otherwise, as simply re-locating is all it takes to run it.
+    pub fn launch_kind(&self) -> LaunchKind {
+        launch_kind(self)
+    }
+
+    /// A stable hash of the ELF's re-location structure.
+    ///
+    /// Only the program header layout and the re-location related `DYNAMIC` tags, i.e.
+    /// `DT_REL*`, `DT_RELA*`, `DT_JMPREL`, `DT_PLTREL*` and `DT_RELR*`, are hashed. Two builds
+    /// with the same re-location layout thus share a fingerprint, even if their code differs,
+    /// which makes this a good key for caching decoded re-location tables.
+    pub fn reloc_fingerprint(&self) -> u64 {
+        reloc_fingerprint(self)
+    }
+
+    /// A stable hash of the ELF's loaded content.
+    ///
+    /// Only the file bytes of the `PT_LOAD` segments are hashed, concatenated in program
+    /// header order. Headers, and sections outside of `PT_LOAD` segments, do not count, so
+    /// equivalent relinks share a digest. Use this to tell whether two processes loaded the
+    /// same ELF, not to defend against tampering.
+    pub fn content_digest(&self) -> u64 {
+        content_digest(self)
+    }
+}
+
+
+
+/// A lazily parsed ELF, see `Elf::try_parse_lazy`.
+///
+/// Unlike for an `Elf`, none of the program headers have been bounds-checked yet.
+#[derive(Clone)]
+pub struct LazyElf<'a> {
+    hdrs:  RawProgramHeaders<'a>,
+    secs:  Result<SectionHeaders<'a>, ParseElfError>,
+    raw:   &'a [u8],
+    entry: u64,
+    file:  FileInfo,
+}
+
+impl<'a> LazyElf<'a> {
+    /// Runs all the checks `Elf::try_parse` would have run, producing a loadable `Elf`.
+    pub fn validate(&self) -> Result<Elf<'a>, ParseElfError> {
+        validate_lazy_elf(self)
+    }
+
+    /// Provides an iterator over the ELF's program headers, checking each of them on the fly.
+    ///
+    /// Checking a single program header can't tell whether the entry point is fine, so a
+    /// header-wise `Ok` does not imply that `validate` succeeds.
+    pub fn program_headers(&self) -> LazyProgramHeaders<'a> {
+        LazyProgramHeaders { inner: self.hdrs.clone(), elf: self.raw }
     }
 }
 
 
 
 /// Represents a loaded, but not yet memory-protected and re-located ELF.
-// TODO serialisability, possibly MessagePack, Binn?
 pub struct LoadedElf<'a> {
-    mem:       &'a mut [u8],
-    dyns:      Slice32<ElfDyn>,
-    mem_align: u32,
-    entry:     u32,
-    protect:   SegmentStack,
+    mem:        &'a mut [u8],
+    dyns:       Slice32<ElfDyn>,
+    mem_align:  u32,
+    entry:      u32,
+    protect:    SegmentStack,
+    coalesce:   bool,
+    ifunc:      bool,
+    page_size:  u32,
+    flags:      DynFlags,
+    tls:        Option<TlsSegment>,
+    class:      ElfClass,
+    link_base:  u64,
+    fixed_base: Option<u64>,
+    dyn_info:   Result<DynInfo, RelocElfError>,
+    relocated:  bool,
+    phdrs:      Option<Slice32<u8>>,
 }
 
 impl<'a> LoadedElf<'a> {
     /// Try re-locating and memory-protecting the loaded ELF.
     ///
     /// - `base` is the base address of the re-located ELF's address space. If you run the ELF
-    ///   in the loader's address space, then use the address from `loader_base`.
+    ///   in the loader's address space, then use the address from `loader_base`. Otherwise,
+    ///   all re-locations are still written to the loaded memory, but every absolute address
+    ///   is computed relative to `base`. Thus, a buffer shared with e.g. a sandbox that maps it
+    ///   at `base` needs no second copy. `base` may be anywhere in the address space, e.g.
+    ///   above 4GiB, as only offsets relative to it are limited to 32 bits.
     /// - `prot` is an optional function to be called to restrict access to specific ranges of
     ///   memory. It is possible that overlapping regions of memory request distinct protection
-    ///   levels. In such cases newer protection requests overrule older ones. This argument is
-    ///   optional, as for some systems, like for UEFI, there is no proper way of restricting
-    ///   memory access rights.
-    pub fn try_reloc(mut self, base: *mut u8, prot: Option<ProtectFn>)
-    -> Result<ReadyElf<'a>, (&'a mut [u8], RelocElfError)> {
-        let res   = try_reloc_elf(&mut self, base, prot);
-        let mem   = self.mem;
-        let entry = self.entry;
+    ///   levels. In such cases newer protection requests overrule older ones, unless
+    ///   segments share a page, see `shares_pages`. Enable
+    ///   `coalesce_protection` to rule out any overlaps. This argument is optional, as for
+    ///   some systems, like for UEFI, there is no proper way of restricting memory access
+    ///   rights.
+    /// - `resolve` is an optional function to look up symbols the ELF does not define itself.
+    ///   Without it, any re-location referring to such a symbol fails, unless the symbol is
+    ///   weak.
+    pub fn try_reloc(
+        self,
+        base:    *mut u8,
+        prot:    Option<ProtectFn>,
+        resolve: Option<ResolveFn>,
+    ) -> Result<ReadyElf<'a>, (&'a mut [u8], RelocElfError)> {
+        self.try_reloc_with(base, prot.map(Protector::Each), resolve, None)
+    }
+
+    /// Like `try_reloc`, but protects all memory with a single call to `prot`.
+    ///
+    /// `prot` gets the non-overlapping ranges of `effective_protection_map`, so the
+    /// `coalesce_protection` setting does not matter. If ifunc resolvers are run, there is
+    /// one more call before that, with just the executable ranges.
+    pub fn try_reloc_batched(
+        self,
+        base:    *mut u8,
+        prot:    Option<ProtectAllFn>,
+        resolve: Option<ResolveFn>,
+    ) -> Result<ReadyElf<'a>, (&'a mut [u8], RelocElfError)> {
+        self.try_reloc_with(base, prot.map(Protector::All), resolve, None)
+    }
+
+    /// Like `try_reloc`, but calls `progress` every few thousand re-location entries, e.g. to
+    /// update a progress bar or to feed a watchdog while re-locating huge ELFs.
+    ///
+    /// `progress` gets the number of entries applied so far, and the total number of entries
+    /// of all re-location tables. Without a `progress` callback, this is just `try_reloc`.
+    pub fn try_reloc_with_progress(
+        self,
+        base:     *mut u8,
+        prot:     Option<ProtectFn>,
+        resolve:  Option<ResolveFn>,
+        progress: Option<ProgressFn>,
+    ) -> Result<ReadyElf<'a>, (&'a mut [u8], RelocElfError)> {
+        self.try_reloc_with(base, prot.map(Protector::Each), resolve, progress)
+    }
+
+    fn try_reloc_with(
+        mut self,
+        base:     *mut u8,
+        prot:     Option<Protector>,
+        resolve:  Option<ResolveFn>,
+        progress: Option<ProgressFn>,
+    ) -> Result<ReadyElf<'a>, (&'a mut [u8], RelocElfError)> {
+        let res      = try_reloc_elf(&mut self, base, prot, resolve, progress);
+        let mem      = self.mem;
+        let entry    = self.entry;
+        let protect  = self.protect;
+        let align    = self.mem_align;
+        let tls      = self.tls;
+        let phdrs    = self.phdrs;
+        let pages    = self.page_size;
+        // Re-locating succeeds only with a readable `PT_DYNAMIC` segment.
+        let dyn_info = self.dyn_info.unwrap_or_default();
+
+        // Zero is a fine entry point for ELFs linked above address zero, see `Elf::entry`.
+        let has_entry = (entry != 0) | (self.link_base != 0) | self.fixed_base.is_some();
 
         match res {
-            Ok( _) =>  Ok(ReadyElf { mem, base, entry }),
-            Err(e) => Err((mem, e)),
+            Ok(syms) =>  Ok(ReadyElf {
+                mem, base, entry, has_entry, protect, align, syms, tls, dyn_info, phdrs,
+                page_size: pages,
+            }),
+            Err(e)   => Err((mem, e)),
         }
     }
 
+    /// Checks whether `try_reloc` would succeed for the given base address, without changing
+    /// anything.
+    ///
+    /// All tables and re-location entries are checked just like `try_reloc` does, and
+    /// `resolve` is called just as well. However, nothing is written to the loaded memory, no
+    /// memory is protected, and no ifunc resolvers are called. Thus, this can't tell whether
+    /// the ifunc resolvers themselves succeed, nor whether the `ProtectFn` does.
+    pub fn validate_reloc(&self, base: *mut u8, resolve: Option<ResolveFn>)
+    -> Result<(), RelocElfError> {
+        validate_reloc_elf(self, base, resolve)
+    }
+
+    /// Applies all re-locations for the given base address, but keeps the loaded ELF around,
+    /// e.g. to take a copy of the loaded memory for each of many address spaces. All arguments
+    /// work just like for `try_reloc`, which may follow. However, `prot` is only called to make
+    /// code executable ahead of running ifunc resolvers, if any. Nothing else gets protected.
+    ///
+    /// The `PT_DYNAMIC` segment was already scanned while loading, so each call merely applies
+    /// the re-location entries. `Rela` entries carry their own addends, so each call computes
+    /// every word afresh for `base`, no matter what any earlier call wrote. There is no need to
+    /// copy the unrelocated image again. `Rel` and `Relr` entries, however, keep their addends
+    /// in the very places to re-locate, which the first call overwrites. Thus, any further
+    /// re-location of such ELFs fails with `RelocElfError::ImplicitAddends`. For these, load a
+    /// fresh copy for each base address instead.
+    pub fn reloc_into(
+        &mut self,
+        base:    *mut u8,
+        prot:    Option<ProtectFn>,
+        resolve: Option<ResolveFn>,
+    ) -> Result<(), RelocElfError> {
+        reloc_elf_into(self, base, prot.map(Protector::Each), resolve, None).map(|_| ())
+    }
+
+    /// Copies the loaded memory into `dst`, and re-locates and memory-protects that copy for
+    /// `base`, leaving this loaded ELF untouched, e.g. to map a plugin into many sandboxes at
+    /// distinct addresses without loading it from the ELF buffer time and again.
+    ///
+    /// `dst` must be aligned to `mem_align`, and hold at least `mem_len` bytes. Only the first
+    /// `mem_len` bytes are used. All other arguments work just like for `try_reloc`. As the
+    /// copy is taken of `snapshot`, anything `reloc_into` did to this ELF is copied as well.
+    pub fn copy_and_reloc<'b>(
+        &self,
+        dst:     &'b mut [u8],
+        base:    *mut u8,
+        prot:    Option<ProtectFn>,
+        resolve: Option<ResolveFn>,
+    ) -> Result<ReadyElf<'b>, ElfError> {
+        let copy = copy_into(self, dst)?;
+
+        copy.try_reloc(base, prot, resolve).map_err(|(_, e)| e.into())
+    }
+
+    /// The loaded memory as it is now, i.e. not yet re-located, unless `reloc_into` was used.
+    ///
+    /// This is the template `copy_and_reloc` copies from.
+    pub fn snapshot(&self) -> &[u8] {
+        self.mem
+    }
+
     /// The final re-located ELF's base address within the ELF loader's address space.
     pub fn loader_base(&mut self) -> *mut u8 {
         self.mem.as_mut_ptr()
     }
 
+    /// Splits the loaded ELF into its loaded memory and the metadata needed to re-create it
+    /// with `try_from_parts`.
+    ///
+    /// The `coalesce_protection`, `run_ifunc_resolvers` and `set_page_size` settings are not
+    /// kept.
+    pub fn into_parts(self) -> (&'a mut [u8], LoadedElfMeta) {
+        into_parts(self)
+    }
+
+    /// Re-creates a loaded ELF from a copy of its loaded memory and its metadata, e.g. to load
+    /// an ELF in one process, and to re-locate it in another one.
+    ///
+    /// `mem` must be aligned to `meta.mem_align()`, and it must hold the loaded memory as it
+    /// was when `into_parts` was called. This only checks that all the metadata's ranges lie
+    /// within `mem`, the memory contents are checked by `try_reloc` just as usual.
+    pub fn try_from_parts(mem: &'a mut [u8], meta: LoadedElfMeta)
+    -> Result<Self, LoadElfError> {
+        try_from_parts(mem, meta)
+    }
+
+    /// Whether `try_reloc` may call ifunc resolvers to apply `IRELATIVE` re-locations.
+    ///
+    /// By default, it fails with `RelocElfError::IfuncDisabled` instead. If enabled, all
+    /// executable segments are protected early, before each resolver is called right away,
+    /// in the loader's address space. Thus, the base address given to `try_reloc` must be
+    /// `loader_base`.
+    ///
+    /// # Safety
+    ///
+    /// **This runs code of the ELF while it is still being re-located**, i.e. trusting the ELF
+    /// just as much as calling its entry function would. If `try_reloc` gets no `ProtectFn`,
+    /// the loaded memory must already be executable.
+    pub unsafe fn run_ifunc_resolvers(&mut self, run: bool) {
+        self.ifunc = run;
+    }
+
+    /// Whether `try_reloc` merges protection requests before calling the `ProtectFn`.
+    ///
+    /// By default, the whole memory is made read-only first, followed by one request per
+    /// segment, in order, possibly overlapping. If enabled, `try_reloc` instead issues one
+    /// request per range of `effective_protection_map`, i.e. the fewest non-overlapping
+    /// requests with the very same outcome. This is worth it if protecting memory is costly.
+    ///
+    /// If `shares_pages`, requests are always merged, as in-order requests would not grant
+    /// the shared pages the union of their segments' protection.
+    pub fn coalesce_protection(&mut self, coalesce: bool) {
+        self.coalesce = coalesce;
+    }
+
+    /// The host's page size, which `GNU_RELRO` ranges are rounded to if it is smaller than
+    /// `mem_align`.
+    ///
+    /// `GNU_RELRO` ranges rarely start on a `mem_align` boundary, so unlike all other
+    /// protection requests, they are rounded to whole pages only. By default, these are 4KiB
+    /// pages, which is too small for e.g. AArch64 hosts with 16KiB or 64KiB pages. Values that
+    /// are no power of two are rounded up to one.
+    pub fn set_page_size(&mut self, page_size: u32) {
+        self.page_size = page_size.checked_next_power_of_two().unwrap_or(1 << 31);
+    }
+
+    /// Whether segments share a page, e.g. due to linking with `-z noseparate-code`.
+    ///
+    /// Shared pages get the union of their segments' protection, e.g. read-execute for code
+    /// and read-only data, so a read-only segment may end up executable. Loading already
+    /// failed with `SharedPageConflict` if a read-write and a read-execute segment share a
+    /// page. Strict callers may reject such ELFs altogether.
+    pub fn shares_pages(&self) -> bool {
+        shares_pages(self.protect.as_slice(), self.mem_align, self.mem.len())
+    }
+
     /// Minimum number of bytes to allocate to load this ELF.
     pub fn mem_len(&self) -> usize {
         self.mem.len()
@@ -260,6 +905,64 @@ impl<'a> LoadedElf<'a> {
     pub fn mem_align(&self) -> u32 {
         self.mem_align
     }
+
+    /// The number of bytes the segments actually span, i.e. the highest end of any segment's
+    /// `load_range`, `.bss` included.
+    ///
+    /// This may be less than `mem_len`, if the load buffer was bigger than it had to be. The
+    /// ELF never touches the tail past `used_len`, so callers may trim or discard it.
+    pub fn used_len(&self) -> usize {
+        self.protect.as_slice().iter()
+            .map(|seg| seg.range.to_byte_range().end)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The memory protection each byte range will end up with after `try_reloc`.
+    ///
+    /// This simulates the sequence of protection requests without calling any `ProtectFn`,
+    /// including the initial request to make everything read-only, and the rule that newer
+    /// requests overrule older ones. Pages shared by segments get the union of their
+    /// protection instead, see `shares_pages`.
+    pub fn effective_protection_map(&self) -> ProtectionMap<'_> {
+        ProtectionMap::new(self.protect.as_slice(), self.mem_align, self.page_size, self.mem.len())
+    }
+
+    /// The segment an address, e.g. a faulting one, belongs to, see `ReadyElf::segment_at`.
+    ///
+    /// Until re-locating, there is no other address space than the loader's, so `vaddr` is
+    /// relative to `loader_base`.
+    pub fn segment_at(&self, vaddr: usize) -> Option<LoadedSegment> {
+        let base = self.mem.as_ptr() as usize;
+
+        LoadedSegments::new(self.protect.as_slice(), base, base).segment_at(vaddr)
+    }
+
+    /// The flags of the `DT_FLAGS` and `DT_FLAGS_1` entries, e.g. to choose a binding policy.
+    pub fn dyn_flags(&self) -> DynFlags {
+        self.flags
+    }
+
+    /// The `(d_tag, d_val)` pairs of the loaded `PT_DYNAMIC` segment, up to `DT_NULL`, e.g. to
+    /// tell why some re-location table was not found.
+    ///
+    /// There are none if `try_reloc` would fail with `RelocElfError::BadDynRange` or
+    /// `RelocElfError::BadDynAlignment`.
+    pub fn dynamic_entries(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        let dyns = match self.dyns.fits_in(self.mem.len()) {
+            true  => self.dyns.try_slice(self.mem, ()).unwrap_or(&[]),
+            false => &[],
+        };
+
+        dyns.iter().take_while(|d| d.d_tag != DT_NULL).map(|d| (d.d_tag, d.d_val))
+    }
+
+    /// The thread-local storage template, if the ELF has a `PT_TLS` header.
+    ///
+    /// The image is read from the loaded memory, i.e. before any re-locations are applied.
+    pub fn tls_template(&self) -> Option<TlsTemplate<'_>> {
+        self.tls.map(|tls| tls.template(self.mem))
+    }
 }
 
 /// Type of a memory-protecting callback.
@@ -269,7 +972,10 @@ impl<'a> LoadedElf<'a> {
 /// - `v_base` is the base address within the re-located ELF's address space.
 /// - `mem_len` is the size of the memory region pointed at by the base addresses.
 /// - `range` is the region of memory to protect within the slice of memory
-///   defined by one of the base addresses and `mem_len`.
+///   defined by one of the base addresses and `mem_len`. It starts at a multiple of
+///   `mem_align`, and ends at one as well, or at `mem_len`. `GNU_RELRO` ranges are only
+///   rounded to whole pages instead, see `LoadedElf::set_page_size`, if pages are smaller.
+#[allow(improper_ctypes_definitions)]
 pub type ProtectFn = extern "C" fn(
     prot:    SegmentProtection,
     p_base:  *mut u8,
@@ -278,51 +984,189 @@ pub type ProtectFn = extern "C" fn(
     range:   Range<usize>,
 ) -> Result<(), ()>;
 
+/// Type of a memory-protecting callback that handles all protection requests at once, see
+/// `LoadedElf::try_reloc_batched`.
+///
+/// - `p_base`, `v_base` and `mem_len` are just like for `ProtectFn`.
+/// - `segments` points to `count` non-overlapping, ascending protection requests, which are
+///   only valid for the duration of the call.
+#[allow(improper_ctypes_definitions)]
+pub type ProtectAllFn = extern "C" fn(
+    p_base:   *mut u8,
+    v_base:   *mut u8,
+    mem_len:  usize,
+    segments: *const SegmentDesc,
+    count:    usize,
+) -> Result<(), ()>;
+
+/// Type of a symbol-resolving callback.
+///
+/// - `name` and `name_len` make up the name of the symbol to look up, without any
+///   terminating NUL.
+///
+/// Returns the symbol's address within the re-located ELF's address space, or null if
+/// there is no such symbol.
+pub type ResolveFn = extern "C" fn(name: *const u8, name_len: usize) -> *const ();
+
+/// Type of a progress-reporting callback, see `LoadedElf::try_reloc_with_progress`.
+///
+/// - `done` is the number of re-location entries applied so far.
+/// - `total` is the number of entries of all re-location tables, which `done` ends up at.
+pub type ProgressFn = extern "C" fn(done: usize, total: usize);
+
 /// A memory-protecting callback that does absolutely nothing.
 ///
 /// Useful for systems like UEFI where there either is no way of protecting memory,
 /// or where the system's API does not provide any methods to do such a thing.
+#[allow(improper_ctypes_definitions, clippy::result_unit_err)]
 pub extern "C" fn protect_noop(
     _: SegmentProtection, _: *mut u8, _: *mut u8, _: usize, _: Range<usize>
 ) -> Result<(), ()> {
     Ok(())
 }
 
+/// The page size `GNU_RELRO` ranges are rounded to, unless `LoadedElf::set_page_size` says
+/// otherwise. This is the smallest page size of the supported targets.
+const DEFAULT_PAGE_SIZE: u32 = 0x1000;
+
+/// Capacity of the fixed-size `SegmentStack`. 4 to 6 segments seem typical, but ELFs linked
+/// without a custom linker script, e.g. with `-z separate-code`, easily need more.
+const SEGMENT_STACK_LEN: usize = 16;
+
+/// The segments to memory-protect, in order.
+///
+/// Without the `alloc` feature, this holds at most `SEGMENT_STACK_LEN` segments.
+/// Segments past that limit are still counted, so that `check_capacity` can tell how many
+/// there were in total.
+#[cfg(not(feature = "alloc"))]
 struct SegmentStack {
-    data: [Segment; 8], // TODO more needed? 4 to 6 seems typical
+    data: [Segment; SEGMENT_STACK_LEN],
     len:  u8,
+    seen: u8,
 }
 
+#[cfg(not(feature = "alloc"))]
 impl SegmentStack {
     pub fn new() -> Self {
         Self {
             len:  0,
+            seen: 0,
             data: [Segment {
-                range:   Slice32::new(0, 0),
-                protect: SegmentProtection::RO,
-            }; 8],
+                range:    Slice32::new(0, 0),
+                file_len: 0,
+                protect:  SegmentProtection::RO,
+                kind:     SegmentKind::Load,
+            }; SEGMENT_STACK_LEN],
         }
     }
 
-    pub fn try_push(&mut self, ph: &ProgramHeader<'_>) -> Result<(), LoadElfError> {
-        if (self.len as usize) >= self.data.len() {
-            return Err(LoadElfError::TooManySegments);
+    pub fn push(&mut self, ph: &ProgramHeader<'_>) {
+        self.push_segment(Segment {
+            range:    ph.load_range,
+            // `ProgramHeader::new` and parsing make sure this fits in `load_range`.
+            file_len: ph.copy_from.len() as u32,
+            protect:  ph.protection,
+            kind:     ph.kind,
+        })
+    }
+
+    pub fn push_segment(&mut self, seg: Segment) {
+        self.seen = self.seen.saturating_add(1);
+
+        if let Some(slot) = self.data.get_mut(self.len as usize) {
+            *slot     = seg;
+            self.len += 1;
         }
+    }
 
-        self.data[self.len as usize] = Segment {
-            range:   ph.load_range,
-            protect: ph.protection,
-        };
-        self.len += 1;
+    /// Fails if more segments were pushed than fit, reporting how many that were.
+    pub fn check_capacity(&self) -> Result<(), LoadElfError> {
+        if self.seen > self.len {
+            return Err(LoadElfError::TooManySegments {
+                seen: self.seen,
+                max:  SEGMENT_STACK_LEN as u8,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn as_slice(&self) -> &[Segment] {
+        // `push_segment` never lets `len` exceed `data`.
+        self.data.get(..(self.len as usize)).unwrap_or(&[])
+    }
+}
+
+/// The segments to memory-protect, in order. With the `alloc` feature, there is no limit.
+#[cfg(feature = "alloc")]
+struct SegmentStack {
+    data: alloc::vec::Vec<Segment>,
+}
+
+#[cfg(feature = "alloc")]
+impl SegmentStack {
+    pub fn new() -> Self {
+        Self { data: alloc::vec::Vec::new() }
+    }
+
+    pub fn push(&mut self, ph: &ProgramHeader<'_>) {
+        self.push_segment(Segment {
+            range:    ph.load_range,
+            // `ProgramHeader::new` and parsing make sure this fits in `load_range`.
+            file_len: ph.copy_from.len() as u32,
+            protect:  ph.protection,
+            kind:     ph.kind,
+        })
+    }
+
+    pub fn push_segment(&mut self, seg: Segment) {
+        self.data.push(seg);
+    }
 
+    pub fn check_capacity(&self) -> Result<(), LoadElfError> {
         Ok(())
     }
+
+    pub fn as_slice(&self) -> &[Segment] {
+        &self.data
+    }
 }
 
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Segment {
-    range:   Slice32<u8>,
-    protect: SegmentProtection,
+    range:    Slice32<u8>,
+    /// How many leading bytes of `range` were copied from the ELF buffer.
+    file_len: u32,
+    protect:  SegmentProtection,
+    kind:     SegmentKind,
+}
+
+impl Segment {
+    /// The byte range to hand to a `ProtectFn`, widened to whole multiples of `align`.
+    ///
+    /// `GNU_RELRO` ranges in particular rarely start on a page boundary. Passing them on
+    /// verbatim makes `mprotect` and friends either fail or silently round on their own.
+    /// They are rounded to `page_size`, if smaller than `align`, and their end is rounded
+    /// *down* instead, like `ld.so` does, as the rest of the last page usually holds data
+    /// that must stay writable.
+    fn protect_range(self, align: u32, page_size: u32, mem_len: usize) -> Range<usize> {
+        // Both are powers of two, see `Elf::mem_align`, `LoadedElf::try_from_parts`, and
+        // `LoadedElf::set_page_size`.
+        let mask  = match self.kind {
+            SegmentKind::Relro => (align.min(page_size).max(1) - 1) as usize,
+            _                  => (align.max(1) - 1) as usize,
+        };
+        let range = self.range.to_byte_range();
+        let start = range.start & !mask;
+        let end   = match self.kind {
+            SegmentKind::Relro => (range.end & !mask).max(start),
+            _                  => range.end.checked_add(mask).map(|end| end & !mask)
+                                                             .unwrap_or(mem_len),
+        };
+
+        start.min(mem_len) .. end.min(mem_len)
+    }
 }
 
 
@@ -330,17 +1174,41 @@ struct Segment {
 /// An iterator over the ELF data's program headers.
 #[derive(Clone)]
 pub struct ProgramHeaders<'a> {
-    inner: Iter<'a, ElfProgramHeader>,
+    inner: RawProgramHeaders<'a>,
     elf:   &'a [u8],
 }
 
+impl<'a> ProgramHeaders<'a> {
+    /// Only the program headers of the given `kind`.
+    pub fn of_kind(self, kind: SegmentKind)
+    -> iter::Filter<Self, impl FnMut(&ProgramHeader<'a>) -> bool> {
+        self.filter(move |ph| ph.kind == kind)
+    }
+
+    /// Only the `PT_LOAD` program headers.
+    pub fn loadable(self) -> iter::Filter<Self, impl FnMut(&ProgramHeader<'a>) -> bool> {
+        self.of_kind(SegmentKind::Load)
+    }
+
+    /// Only the `PT_DYNAMIC` program headers. A parsed `Elf` may still have several of them,
+    /// which only loading rejects.
+    pub fn dynamic(self) -> iter::Filter<Self, impl FnMut(&ProgramHeader<'a>) -> bool> {
+        self.of_kind(SegmentKind::Dynamic)
+    }
+
+    /// Only the `PT_GNU_RELRO` program headers.
+    pub fn relro(self) -> iter::Filter<Self, impl FnMut(&ProgramHeader<'a>) -> bool> {
+        self.of_kind(SegmentKind::Relro)
+    }
+}
+
 impl<'a> Iterator for ProgramHeaders<'a> {
     type Item = ProgramHeader<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            match ProgramHeader::from_elf(self.inner.next()?, self.elf) {
-                None     => continue, // a program header we don't give a fuck about
+            match ProgramHeader::from_elf(&self.inner.next()?, self.elf) {
+                None     => continue, // a program header we don't give a fuck about, or broken
                 Some(ph) => return Some(ph),
             }
         }
@@ -349,8 +1217,37 @@ impl<'a> Iterator for ProgramHeaders<'a> {
 
 
 
+/// An iterator over the not yet validated program headers of a `LazyElf`.
+#[derive(Clone)]
+pub struct LazyProgramHeaders<'a> {
+    inner: RawProgramHeaders<'a>,
+    elf:   &'a [u8],
+}
+
+impl<'a> Iterator for LazyProgramHeaders<'a> {
+    type Item = Result<ProgramHeader<'a>, ParseElfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let ph = self.inner.next()?;
+
+            if let Err(e) = check_ph_range(&ph, self.elf) {
+                return Some(Err(e));
+            }
+
+            match ProgramHeader::from_elf(&ph, self.elf) {
+                None     => continue, // a program header we don't give a fuck about
+                Some(ph) => return Some(Ok(ph)),
+            }
+        }
+    }
+}
+
+
+
 /// The kind of memory protection to apply to a loaded segment.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum SegmentProtection {
     /// Segment is read-only.
@@ -370,7 +1267,7 @@ impl SegmentProtection {
             PF_W | PF_RW => SegmentProtection::RW,
             PF_X | PF_RX => SegmentProtection::RX,
 
-            // Attempted RWX.
+            // Attempted RWX. Only `Elf::try_parse_strict` complains.
             _ => SegmentProtection::RX,
         }
     }
@@ -380,6 +1277,7 @@ impl SegmentProtection {
 
 /// Determines what an ELF loader should do.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum SegmentKind {
     /// Copy ELF data into program memory.
@@ -391,6 +1289,9 @@ pub enum SegmentKind {
     /// Relocate and then change the memory protection.
     Relro,
 
+    /// Thread-local storage template.
+    Tls,
+
     /// Some other program header we don't care about.
     Unsupported,
 }
@@ -400,8 +1301,9 @@ impl SegmentKind {
         match kind {
             PT_DYNAMIC   => Some(SegmentKind::Dynamic),
             PT_GNU_RELRO => Some(SegmentKind::Relro  ),
-            PT_GNU_STACK => None, // We don't give a fuck. Stack is always RW, never RWX.
+            PT_GNU_STACK => None, // Not a segment at all, see `Elf::stack_is_executable`.
             PT_LOAD      => Some(SegmentKind::Load   ),
+            PT_TLS       => Some(SegmentKind::Tls    ),
             PT_NULL      => None,
             _            => Some(SegmentKind::Unsupported),
         }
@@ -411,7 +1313,9 @@ impl SegmentKind {
 
 
 /// An ELF program header, which is basically an instruction an ELF loader executes.
-// FIXME How does one handle alignment here? `readelf` reports offsets that don't fit alignment.
+///
+/// `load_range` is exactly what the program header asks for. Memory protection requests are
+/// widened to whole multiples of `Elf::mem_align`, though.
 #[derive(Copy, Clone, Debug)]
 pub struct ProgramHeader<'a> {
     /// What the current header wants us to do.
@@ -423,21 +1327,74 @@ pub struct ProgramHeader<'a> {
     /// A slice into the buffer where the ELF is to be loaded.
     pub load_range: Slice32<u8>,
 
+    /// Where the segment wants to be in physical memory, as told by `p_paddr`.
+    ///
+    /// This loader never looks at it, but early-boot code loading e.g. a kernel into
+    /// identity-mapped memory might. Unlike `load_range`, this is never relative to a link
+    /// base. It is `None` if the range does not fit in the lower 4GiB, which is common for
+    /// executables whose linkers just copy the virtual address.
+    pub phys_range: Option<Slice32<u8>>,
+
     /// Source of the data to copy.
     ///
     /// This is a sub-slice of the original ELF data.
     pub copy_from: &'a [u8],
+
+    raw: Option<ElfProgramHeader>,
 }
 
 impl<'a> ProgramHeader<'a> {
+    /// Builds a program header from scratch, e.g. to test loaders built on top of this crate.
+    ///
+    /// Returns `None` if `copy_from` holds more bytes than `load_range` spans, as loading
+    /// must never copy past the end of `load_range`. `phys_range` is the same as `load_range`.
+    pub fn new(
+        kind:       SegmentKind,
+        protection: SegmentProtection,
+        load_range: Slice32<u8>,
+        copy_from:  &'a [u8],
+    ) -> Option<Self> {
+        if copy_from.len() > (load_range.len as usize) {
+            return None;
+        }
+
+        Some(Self {
+            kind, protection, load_range, copy_from,
+            phys_range: Some(load_range),
+            raw:        None,
+        })
+    }
+
+    /// The raw program header this one was made from, or `None` if it was made with `new`.
+    ///
+    /// ELF32 headers are widened and foreign ones byte-swapped. `p_vaddr` is relative to
+    /// `Elf::link_base`, just like `load_range`.
+    pub fn raw(&self) -> Option<&ElfProgramHeader> {
+        self.raw.as_ref()
+    }
+
+    /// Returns `None` for program headers we don't care about, and for ones whose ranges don't
+    /// fit, even though parsing already checked those. Better safe than `panic!`ing.
     fn from_elf(ph: &ElfProgramHeader, elf: &'a [u8]) -> Option<Self> {
-        Some(ProgramHeader {
-            kind:        SegmentKind      ::from_kind( ph.p_type )?,
-            protection:  SegmentProtection::from_flags(ph.p_flags),
-            load_range:  Slice32::new(ph.p_vaddr as u32, ph.p_memsz as u32),
-            copy_from:   &elf[
-                (ph.p_offset as usize) .. (ph.p_offset as usize).wrapping_add(ph.p_filesz as usize)
-            ],
+        let start = usize::try_from(ph.p_offset).ok()?;
+        let end   = usize::try_from(ph.p_offset.checked_add(ph.p_filesz)?).ok()?;
+
+        // `load_segment` relies on `new` to never copy past the end of `load_range`. Don't
+        // just trust `PhSmallerThanVmem` here, as that one checks the raw header values instead.
+        let len  = u32::try_from(ph.p_memsz).ok()?;
+        let phys = ph.p_paddr.checked_add(ph.p_memsz)
+            .filter(|end| *end <= (u32::MAX as u64))
+            .map(|_| Slice32::new(ph.p_paddr as u32, len));
+
+        Some(Self {
+            phys_range: phys,
+            raw:        Some(*ph),
+            ..Self::new(
+                SegmentKind      ::from_kind( ph.p_type )?,
+                SegmentProtection::from_flags(ph.p_flags),
+                Slice32::new(u32::try_from(ph.p_vaddr).ok()?, len),
+                elf.get(start..end)?,
+            )?
         })
     }
 }
@@ -446,9 +1403,17 @@ impl<'a> ProgramHeader<'a> {
 
 /// A readily loaded and re-located ELF. You can run this as a program now.
 pub struct ReadyElf<'a> {
-    mem:   &'a mut [u8],
-    base:  *const u8,
-    entry: u32,
+    mem:       &'a mut [u8],
+    base:      *const u8,
+    entry:     u32,
+    has_entry: bool,
+    protect:   SegmentStack,
+    align:     u32,
+    page_size: u32,
+    syms:      SymTabs,
+    tls:       Option<TlsSegment>,
+    dyn_info:  DynInfo,
+    phdrs:     Option<Slice32<u8>>,
 }
 
 impl<'a> ReadyElf<'a> {
@@ -463,25 +1428,204 @@ impl<'a> ReadyElf<'a> {
     }
 
     /// Pointer to the entry function, in the ELF loader's address space.
+    ///
+    /// If there is no entry function, this points at the very first loaded byte.
     // FIXME return generic function pointer if variadic generics
     pub fn p_entry(&self) -> *const () {
-        (&self.mem[(self.entry as usize)..]).as_ptr() as *const ()
+        self.mem.as_ptr().wrapping_add(self.entry as usize) as *const ()
     }
 
     /// Pointer to the entry function, in the ready ELF's address space.
+    ///
+    /// If there is no entry function, this points at the very first loaded byte.
     // FIXME return generic function pointer if variadic generics
     pub fn v_entry(&self) -> *const () {
         unsafe { self.base.add(self.entry as usize) as *const () }
     }
+
+    /// Like `p_entry`, but `None` if there is no entry function, e.g. for plugins that only
+    /// export symbols.
+    pub fn try_p_entry(&self) -> Option<*const ()> {
+        Some(self.p_entry()).filter(|_| self.has_entry)
+    }
+
+    /// Like `v_entry`, but `None` if there is no entry function.
+    pub fn try_v_entry(&self) -> Option<*const ()> {
+        Some(self.v_entry()).filter(|_| self.has_entry)
+    }
+
+    /// The entry function, in the ELF loader's address space, as a function pointer of type `F`.
+    ///
+    /// This is `p_entry` followed by a `mem::transmute`, except that it refuses to compile if
+    /// `F` is not pointer-sized.
+    ///
+    /// # Safety
+    ///
+    /// `F` must be a function pointer type with the very signature and ABI of the ELF's entry
+    /// function, e.g. `extern "C" fn() -> i32`. The ELF must have an entry function at all.
+    pub unsafe fn entry_fn<F: Copy>(&self) -> F {
+        let () = PointerSized::<F>::CHECK;
+
+        mem::transmute_copy(&self.p_entry())
+    }
+
+    /// Looks up a defined dynamic symbol by name, in the ELF loader's address space.
+    ///
+    /// This prefers the `DT_GNU_HASH` table, whose bloom filter rejects most absent symbols
+    /// without walking any hash chains, and falls back to the classic `DT_HASH` table.
+    /// Returns `None` if there is no such symbol, or if the ELF has no symbol or hash table.
+    ///
+    /// Of symbols with several GNU versions, this finds the default one, i.e. `name@@version`,
+    /// just like the static linker would.
+    pub fn lookup(&self, name: &str) -> Option<*const ()> {
+        let off = symbol::lookup(self.mem, &self.syms, name, None)?;
+
+        Some(self.mem.as_ptr().wrapping_add(off) as *const ())
+    }
+
+    /// Provides an iterator over all the dynamic symbols, defined or not, like `nm -D` would.
+    ///
+    /// Symbols whose names are out of bounds or not valid UTF-8 are skipped. Without a
+    /// `DT_HASH` or `DT_GNU_HASH` table, the symbol count is unknown, so there are none at all.
+    pub fn symbols(&self) -> SymbolIter<'_> {
+        SymbolIter::new(self.mem, self.syms)
+    }
+
+    /// Like `lookup`, but in the ready ELF's address space.
+    pub fn v_lookup(&self, name: &str) -> Option<*const ()> {
+        let off = symbol::lookup(self.mem, &self.syms, name, None)?;
+
+        Some(self.base.wrapping_add(off) as *const ())
+    }
+
+    /// Like `lookup`, but finds the symbol of exactly the given GNU version, e.g. `foo@V1`,
+    /// even if that is a hidden one. Unversioned symbols match no version at all.
+    ///
+    /// The version is the one given by `DT_VERSYM` and `DT_VERDEF`, see `Symbol::version`.
+    pub fn lookup_versioned(&self, name: &str, version: &str) -> Option<*const ()> {
+        let off = symbol::lookup(self.mem, &self.syms, name, Some(version))?;
+
+        Some(self.mem.as_ptr().wrapping_add(off) as *const ())
+    }
+
+    /// The final memory protection of each byte range, see
+    /// `LoadedElf::effective_protection_map`.
+    ///
+    /// Filter this for `SegmentProtection::RX` to get the executable ranges.
+    pub fn effective_protection_map(&self) -> ProtectionMap<'_> {
+        ProtectionMap::new(self.protect.as_slice(), self.align, self.page_size, self.mem.len())
+    }
+
+    /// Provides an iterator over the memory-protected segments, with their final ranges.
+    pub fn segments(&self) -> LoadedSegments<'_> {
+        LoadedSegments::new(self.protect.as_slice(), self.mem.as_ptr() as usize, self.base as usize)
+    }
+
+    /// The segment a virtual address, e.g. a faulting one, belongs to.
+    ///
+    /// Where segments overlap, like `GNU_RELRO` ones do, the one whose protection request
+    /// came last wins, just like in `effective_protection_map`. `None` for addresses outside
+    /// of all segments, e.g. in the zero-filled gaps between them.
+    pub fn segment_at(&self, vaddr: usize) -> Option<LoadedSegment> {
+        self.segments().segment_at(vaddr)
+    }
+
+    /// The thread-local storage template, see `LoadedElf::tls_template`.
+    ///
+    /// Unlike there, the image already has all re-locations applied.
+    pub fn tls_template(&self) -> Option<TlsTemplate<'_>> {
+        self.tls.map(|tls| tls.template(self.mem))
+    }
+
+    /// The range of the program header table within `p_mem`, if some `PT_LOAD` segment
+    /// loaded it along. This is where an auxiliary vector's `AT_PHDR` points to.
+    pub fn program_header_range(&self) -> Option<Range<usize>> {
+        self.phdrs.map(Slice32::to_byte_range)
+    }
+
+    /// The `DT_PREINIT_ARRAY` function pointers, which are to be called in order before all
+    /// the `init_array` ones. Only executables have any.
+    ///
+    /// Like all the `*_array` tables, the pointers are re-located already, i.e. they point
+    /// into the ready ELF's address space. An ELF without such a table has an empty one.
+    /// Returns `None` if the table lies out of bounds, or if it is misaligned.
+    pub fn preinit_array(&self) -> Option<&[u64]> {
+        self.dyn_info.preinit_array.words(self.mem)
+    }
+
+    /// The `DT_INIT_ARRAY` function pointers, which are to be called in order before the
+    /// entry point, see `preinit_array`.
+    pub fn init_array(&self) -> Option<&[u64]> {
+        self.dyn_info.init_array.words(self.mem)
+    }
+
+    /// The `DT_FINI_ARRAY` function pointers, which are to be called in *reverse* order when
+    /// the ELF is done, see `preinit_array`.
+    pub fn fini_array(&self) -> Option<&[u64]> {
+        self.dyn_info.fini_array.words(self.mem)
+    }
+
+    /// Checks that every `JUMP_SLOT` GOT entry points into an executable range of this ELF.
+    ///
+    /// Targets are expected in the ready ELF's address space, i.e. relative to `v_mem`. On
+    /// failure, returns the offending GOT entry's offset, or the offset of the broken PLT
+    /// re-location table entry if that one can't even be read. Note that targets resolved to
+    /// functions of other objects fail this check, too.
+    pub fn verify_plt(&self) -> Result<(), usize> {
+        verify_plt(self)
+    }
+
+    /// Fills in a `BootHeader` describing this ready ELF's memory image.
+    ///
+    /// Prepend the header to a copy of `p_mem` to get a self-contained image that an OS can
+    /// memory-protect and jump into without re-parsing any ELF data.
+    ///
+    /// With the `alloc` feature, there may be more protection ranges than a header can hold.
+    /// The header is left invalid in that case, see `BootHeader::is_valid`.
+    pub fn to_bootable(&self, header_out: &mut BootHeader) {
+        fill_boot_header(self, header_out)
+    }
+
+    /// Jumps to the ELF's entry point like a kernel starting a process, never to return.
+    ///
+    /// The entry point runs on a fresh stack that is laid out as mandated by the SysV ABI for
+    /// the initial process stack, i.e. `rsp` points at `argc`, followed by `argv`, `envp` and
+    /// an auxiliary vector. The latter has `AT_PAGESZ`, `AT_BASE`, `AT_ENTRY` and `AT_RANDOM`,
+    /// and `AT_PHDR`, `AT_PHENT` and `AT_PHNUM` if the program headers were loaded. `AT_BASE`
+    /// is zero, as there is no interpreter. `rdx` is zero, too, so there is no `rtld_fini`
+    /// function to register with `atexit`.
+    ///
+    /// # Safety
+    ///
+    /// This runs arbitrary code. The ELF must have been re-located to `p_mem().as_ptr()`, and
+    /// its entry point must exit the process, just like any `_start` does.
+    #[cfg(all(feature = "alloc", target_arch = "x86_64"))]
+    pub unsafe fn run_main(&self, args: &[&CStr], env: &[&CStr]) -> ! {
+        run::run_main(self, args, env)
+    }
+}
+
+
+
+/// Fails to compile for types `F` that are not pointer-sized, see `ReadyElf::entry_fn`.
+struct PointerSized<F>(PhantomData<F>);
+
+impl<F> PointerSized<F> {
+    const CHECK: () = assert!(
+        mem::size_of::<F>() == mem::size_of::<*const ()>(),
+        "function pointers must be pointer-sized"
+    );
 }
 
 
 
 /// A slice-ish thing that only uses 32-bit offset and length elements.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(bound = ""))]
 pub struct Slice32<T: Sized + Copy> {
     pub start: u32, // In 1 byte steps.
     pub len:   u32, // In multiples of `size_of::<T>()`.
+    #[cfg_attr(feature = "serde", serde(skip))]
     _wat: PhantomData<T>,
 }
 
@@ -501,15 +1645,23 @@ impl<T: Sized + Copy> Slice32<T> {
         (self.start.wrapping_add(self.len.wrapping_mul(mem::size_of::<T>() as u32)) as usize)
     }
 
+    /// Checks whether this slice lies fully within a buffer of `len` bytes.
+    pub(crate) fn fits_in(self, len: usize) -> bool {
+        (self.len as u64).checked_mul(mem::size_of::<T>() as u64)
+            .and_then(|l| l.checked_add(self.start as u64))
+            .map(|end| end <= (len as u64))
+            .unwrap_or(false)
+    }
+
     /// Tries to grab a sub-slice of `T`s from `mem`.
     ///
     /// Fails if the sub-slice would have bad alignment.
-    pub(crate) fn try_slice<'a, E>(self, mem: &'a [u8], bad_align: E)
-    -> Result<&'a [T], E> {
+    pub(crate) fn try_slice<E>(self, mem: &[u8], bad_align: E)
+    -> Result<&[T], E> {
         // No bounds checking required, will have been done at parsing time.
         let base = unsafe { mem.as_ptr().add(self.start as usize) } as *const T;
 
-        if 0 != ((base as usize) % mem::align_of::<T>()) {
+        if !(base as usize).is_multiple_of(mem::align_of::<T>()) {
             return Err(bad_align);
         }
 
@@ -518,9 +1670,12 @@ impl<T: Sized + Copy> Slice32<T> {
 
     /// A specialisation of `try_slice` that avoids alignment checks.
     ///
-    /// This is safe if `T == u8`, otherwise stay away from it.
+    /// # Safety
+    ///
+    /// This is safe if `T == u8`, otherwise stay away from it. The range must also lie
+    /// within `mem`.
     // FIXME Rather specialise `try_slice` for `u8` and `Result<&'a [u8], !>`, if stable `!`.
-    pub unsafe fn as_slice<'a>(self, mem: &'a [u8]) -> &'a [T] {
+    pub unsafe fn as_slice(self, mem: &[u8]) -> &[T] {
         slice::from_raw_parts(
             mem.as_ptr().add(self.start as usize) as *const T,
             self.len as usize
@@ -528,7 +1683,11 @@ impl<T: Sized + Copy> Slice32<T> {
     }
 
     /// Like `as_slice`, but grabs a mutable reference. Again, no alignment checks.
-    pub unsafe fn as_slice_mut<'a>(self, mem: &'a mut [u8]) -> &'a mut [T] {
+    ///
+    /// # Safety
+    ///
+    /// Same as for `as_slice`.
+    pub unsafe fn as_slice_mut(self, mem: &mut [u8]) -> &mut [T] {
         slice::from_raw_parts_mut(
             mem.as_mut_ptr().add(self.start as usize) as *mut T,
             self.len as usize
@@ -540,7 +1699,9 @@ impl<T: Sized + Copy> Slice32<T> {
     pub fn convert<U: Sized + Copy>(self) -> Slice32<U> {
         Slice32::new(
             self.start,
-            (((self.len as usize) * mem::size_of::<T>()) / mem::size_of::<U>()) as u32
+            ((self.len as usize) * mem::size_of::<T>())
+                .checked_div(mem::size_of::<U>())
+                .unwrap_or(0) as u32
         )
     }
 }
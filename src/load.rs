@@ -1,78 +1,269 @@
 
 use crate::{
-    LoadElfError, Elf, LoadedElf,
-    SegmentKind, SegmentStack,
-    ProgramHeader,
+    ElfError, LoadElfError, Elf, LoadedElf,
+    Segment, SegmentKind, SegmentStack,
+    ProgramHeader, Slice32, DEFAULT_PAGE_SIZE,
 };
+use crate::elf::{ ElfClass, ElfDyn };
+use crate::dynamic::DynFlags;
+use crate::elf::{ PT_NULL, PT_INTERP, PT_NOTE, PT_PHDR, PT_GNU_EH_FRAME, PT_GNU_PROPERTY };
+use crate::reloc::scan_dynamic;
+use crate::protect::check_shared_pages;
+use crate::tls::TlsSegment;
 use core::ptr;
 
 
 
-pub fn try_load_elf<'a>(elf: &Elf<'_>, mem: &'a mut [u8])
+/// Options for `Elf::try_load_with`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct LoadOptions {
+    /// Skip zero-filling the buffer, as it is known to be all zeroes already, e.g. because it
+    /// was freshly mapped with `MAP_ANONYMOUS`.
+    ///
+    /// Only the segments' file contents are copied then, so every byte not covered by them,
+    /// most importantly the zero-filled `.bss` tails past `p_filesz`, must really be zero. If
+    /// not, the ELF sees whatever garbage is in there instead of zero-initialised data.
+    pub assume_zeroed: bool,
+
+    /// Fail with `LoadElfError::UnsupportedSegment` on program headers of unknown types,
+    /// instead of ignoring them, as a crafted ELF might rely on loaders skipping them.
+    ///
+    /// Well-known types that are of no concern to loading are fine nonetheless, i.e.
+    /// `PT_INTERP`, `PT_NOTE`, `PT_PHDR`, `PT_GNU_EH_FRAME`, and `PT_GNU_PROPERTY`.
+    pub strict_unknown_segments: bool,
+}
+
+pub fn try_load_elf<'a>(elf: &Elf<'_>, mem: &'a mut [u8], opts: LoadOptions)
 -> Result<LoadedElf<'a>, LoadElfError> {
-    check_buffer_requirements_and_zerofill(elf, mem)?;
+    check_buffer_requirements(elf, mem)?;
+
+    let layout = layout(elf, mem.len(), opts)?;
+
+    if !opts.assume_zeroed {
+        // Don't you fucking dare, compiler!
+        unsafe { ptr::write_bytes(mem.as_mut_ptr(), 0_u8, mem.len()) };
+    }
+
+    let copied = elf.program_headers()
+        .filter(|ph| matches!(ph.kind, SegmentKind::Load | SegmentKind::Dynamic));
+
+    for ph in copied {
+        load_segment(&ph, mem);
+    }
+
+    Ok(layout.into_loaded(mem))
+}
+
+/// Like `try_load_elf`, but `mem` holds the ELF file itself, already laid out like the loaded
+/// memory, so only the zero-filled tails of segments are written.
+///
+/// The ELF is parsed from a reborrow of `mem`, which is done with before anything is written.
+pub fn try_load_elf_in_place(mem: &mut [u8]) -> Result<LoadedElf<'_>, ElfError> {
+    let layout = {
+        let elf = Elf::try_parse(mem)?;
+
+        check_buffer_requirements(&elf, mem)?;
+        check_in_place(&elf, mem)?;
+
+        layout(&elf, mem.len(), LoadOptions::default())?
+    };
+
+    let loads = layout.segs.as_slice().iter().filter(|seg| seg.kind == SegmentKind::Load);
+
+    for seg in loads {
+        zero_fill_tail(seg, mem);
+    }
+
+    Ok(layout.into_loaded(mem))
+}
+
+
+
+/// Everything loading finds out about an ELF, all before writing to the load buffer.
+struct Layout {
+    segs:       SegmentStack,
+    dyns:       Slice32<ElfDyn>,
+    tls:        Option<TlsSegment>,
+    mem_align:  u32,
+    entry:      u32,
+    flags:      DynFlags,
+    class:      ElfClass,
+    link_base:  u64,
+    fixed_base: Option<u64>,
+    phdrs:      Option<Slice32<u8>>,
+}
+
+impl Layout {
+    fn into_loaded(self, mem: &mut [u8]) -> LoadedElf<'_> {
+        // Only `try_reloc` reports a broken `PT_DYNAMIC` segment, as it always did.
+        let dyn_info = scan_dynamic(mem, self.dyns, self.link_base);
+
+        LoadedElf {
+            mem, dyn_info,
+            dyns:       self.dyns,
+            mem_align:  self.mem_align,
+            entry:      self.entry,
+            protect:    self.segs,
+            coalesce:   false,
+            ifunc:      false,
+            page_size:  DEFAULT_PAGE_SIZE,
+            flags:      self.flags,
+            tls:        self.tls,
+            class:      self.class,
+            link_base:  self.link_base,
+            fixed_base: self.fixed_base,
+            relocated:  false,
+            phdrs:      self.phdrs,
+        }
+    }
+}
+
+/// Checks the program headers, and collects what is needed to protect and re-locate the
+/// loaded memory.
+fn layout(elf: &Elf<'_>, mem_len: usize, opts: LoadOptions) -> Result<Layout, LoadElfError> {
+    if elf.foreign {
+        return Err(LoadElfError::ForeignElf);
+    }
 
     let mut segs = SegmentStack::new();
     let mut dyns = None;
+    let mut tls  = None;
 
     for ph in elf.program_headers() {
         match ph.kind {
-            SegmentKind::Load => {
-                segs.try_push(&ph)?;
-                load_segment(&ph, mem)
-            },
+            SegmentKind::Load    => segs.push(&ph),
             SegmentKind::Dynamic => match dyns.take() {
                 Some(_) => return Err(LoadElfError::MultipleDynamicSegments),
                 None    => {
+                    // The `Dyn` entries are meant to be part of some loaded segment, and not
+                    // to be loaded on their own.
+                    if !is_loadable(&ph, elf) {
+                        return Err(LoadElfError::DynamicNotLoadable);
+                    }
+
                     // TODO make offset relative to load base?
-                    segs.try_push(&ph)?;
-                    load_segment(&ph, mem);
+                    segs.push(&ph);
 
                     dyns = Some(ph.load_range.convert());
                 },
             },
-            SegmentKind::Relro       => segs.try_push(&ph)?,
-            SegmentKind::Unsupported => (),
+            SegmentKind::Relro       => segs.push(&ph),
+            // The TLS template lies within some `LOAD` segment, so there is nothing to copy.
+            SegmentKind::Tls => match tls {
+                Some(_) => return Err(LoadElfError::MultipleTlsSegments),
+                None    => tls = Some(TlsSegment::new(&ph, elf)),
+            },
+            SegmentKind::Unsupported => if opts.strict_unknown_segments {
+                let p_type = ph.raw().map(|raw| raw.p_type).unwrap_or(PT_NULL);
+
+                if !is_harmless(p_type) {
+                    return Err(LoadElfError::UnsupportedSegment { p_type });
+                }
+            },
         }
     }
 
-    Ok(LoadedElf {
-        mem, dyns: dyns.ok_or(LoadElfError::NoDynamicSegments)?,
-        mem_align: elf.mem_align(),
-        entry:     elf.entry,
-        protect:   segs,
+    segs.check_capacity()?;
+    check_shared_pages(segs.as_slice(), elf.mem_align(), mem_len)?;
+
+    // Fixed-address executables need no re-locating, so they need no `PT_DYNAMIC` either.
+    let dyns = match (dyns, elf.fixed_base) {
+        (Some(dyns), _      ) => dyns,
+        (None,       Some(_)) => Slice32::new(0, 0),
+        (None,       None   ) => return Err(LoadElfError::NoDynamicSegments),
+    };
+
+    Ok(Layout {
+        segs, dyns, tls,
+        mem_align:  elf.mem_align(),
+        entry:      elf.entry,
+        flags:      elf.dyn_info.flags,
+        class:      elf.program_headers.inner.format().class,
+        link_base:  elf.link_base,
+        fixed_base: elf.fixed_base,
+        phdrs:      loaded_phdrs(elf),
     })
 }
 
+/// Where the program header table ends up in the loaded memory, if some `PT_LOAD` segment
+/// copies it at all.
+fn loaded_phdrs(elf: &Elf<'_>) -> Option<Slice32<u8>> {
+    let off = elf.file.phoff;
+    let len = elf.program_headers.inner.table_len() as u64;
+
+    elf.program_headers().loadable().find_map(|ph| {
+        let raw = ph.raw()?;
+        let rel = off.checked_sub(raw.p_offset)?;
 
+        // Parsing made sure that the whole of `p_filesz` fits the segment's `load_range`.
+        match rel.checked_add(len)? <= raw.p_filesz {
+            true  => Some(Slice32::new(ph.load_range.start + (rel as u32), len as u32)),
+            false => None,
+        }
+    })
+}
+
+
+
+/// Program header types this loader knowingly ignores, see `strict_unknown_segments`.
+fn is_harmless(p_type: u32) -> bool {
+    matches!(p_type, PT_INTERP | PT_NOTE | PT_PHDR | PT_GNU_EH_FRAME | PT_GNU_PROPERTY)
+}
 
-fn check_buffer_requirements_and_zerofill(elf: &Elf<'_>, mem: &mut [u8])
--> Result<(), LoadElfError> {
-    if mem.len() < (elf.mem_len() as usize) {
+/// Whether some `PT_LOAD` segment covers the whole of `ph`.
+fn is_loadable(ph: &ProgramHeader<'_>, elf: &Elf<'_>) -> bool {
+    let range = ph.load_range.to_byte_range();
+
+    elf.program_headers().loadable()
+        .map(|load| load.load_range.to_byte_range())
+        .any(|load| (load.start <= range.start) & (range.end <= load.end))
+}
+
+fn check_buffer_requirements(elf: &Elf<'_>, mem: &[u8]) -> Result<(), LoadElfError> {
+    if mem.len() < elf.mem_len_usize() {
         return Err(LoadElfError::BadBufferSize);
     }
 
-    // FIXME Store log2 alignment in `elf`?
-    if 0 != ((mem.as_ptr() as usize) % (elf.mem_align() as usize)) {
+    if ((mem.as_ptr() as usize) & (elf.mem_align_usize() - 1)) != 0 {
         return Err(LoadElfError::BadBufferAlignment);
     }
 
-    // Don't you fucking dare, compiler!
-    unsafe { ptr::write_bytes(mem.as_mut_ptr(), 0_u8, mem.len()) };
-
     Ok(())
 }
 
+/// Every loaded segment's file bytes must already be where they would be copied to.
+///
+/// Parsing rejects overlapping segments, so no zero-filled tail clobbers another segment.
+fn check_in_place(elf: &Elf<'_>, mem: &[u8]) -> Result<(), LoadElfError> {
+    let moved = elf.program_headers().loadable().any(|ph| {
+        let at = mem.as_ptr().wrapping_add(ph.load_range.start as usize);
+
+        !ph.copy_from.is_empty() & (ph.copy_from.as_ptr() != at)
+    });
+
+    match moved {
+        true  => Err(LoadElfError::NotInPlaceLoadable),
+        false => Ok(()),
+    }
+}
+
+fn zero_fill_tail(seg: &Segment, mem: &mut [u8]) {
+    // Bounds-checked while parsing, just like for `load_segment`.
+    let dst = unsafe { seg.range.as_slice_mut(mem) };
+
+    if let Some(tail) = dst.get_mut(seg.file_len as usize..) {
+        tail.fill(0);
+    }
+}
+
 fn load_segment(ph: &ProgramHeader, mem: &mut [u8]) {
     // We already bounds-checked `load_range` while parsing, and we already ensured that
     // this invariant holds as well. This prevents the compiler from inserting `panic!`s
     // when generating optimised code, due to slice bounds checks.
     let dst = unsafe { ph.load_range.as_slice_mut(mem) };
 
-    if dst.len() < ph.copy_from.len() {
-        unsafe { ::core::hint::unreachable_unchecked() }
+    match dst.get_mut(..ph.copy_from.len()) {
+        Some(dst) => dst.copy_from_slice(ph.copy_from),
+        None      => unsafe { ::core::hint::unreachable_unchecked() },
     }
-
-    (&mut dst[..ph.copy_from.len()])
-        .copy_from_slice(ph.copy_from);
 }
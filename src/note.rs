@@ -0,0 +1,191 @@
+
+use crate::Elf;
+use crate::elf::{
+    ElfClass, ElfProgramHeader,
+    EM_X86_64, NT_GNU_BUILD_ID, NT_GNU_PROPERTY_TYPE_0, PT_NOTE, PT_GNU_PROPERTY,
+    GNU_PROPERTY_X86_FEATURE_1_AND, GNU_PROPERTY_X86_FEATURE_1_IBT,
+    GNU_PROPERTY_X86_FEATURE_1_SHSTK,
+};
+use core::convert::TryFrom;
+
+
+
+/// The Intel CET features an `x86_64` ELF is marked as compatible with, see
+/// `Elf::cet_features`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct CetFeatures {
+    /// Indirect branch tracking, i.e. all indirect branch targets start with `endbr64`.
+    pub ibt:   bool,
+    /// Shadow stacks, i.e. the ELF does not mess with return addresses.
+    pub shstk: bool,
+}
+
+
+
+pub fn build_id<'a>(elf: &Elf<'a>) -> Option<&'a [u8]> {
+    let raw  = elf.program_headers.elf;
+    let swap = elf.program_headers.inner.format().swap;
+
+    elf.program_headers.inner.clone()
+        .filter(|ph| ph.p_type == PT_NOTE)
+        .filter_map(|ph| Notes::new(raw, &ph, swap))
+        .flatten()
+        .find(|note| (note.kind == NT_GNU_BUILD_ID) & (note.name == b"GNU\0"))
+        .map(|note| note.desc)
+}
+
+pub fn cet_features(elf: &Elf<'_>) -> CetFeatures {
+    let raw    = elf.program_headers.elf;
+    let format = elf.program_headers.inner.format();
+
+    // Other ISAs use the very same property type for features of their own.
+    if elf.file.machine != EM_X86_64 {
+        return CetFeatures::default();
+    }
+
+    // Properties are padded to 8 bytes in ELF64, and to 4 bytes in ELF32.
+    let align = match format.class {
+        ElfClass::Elf64 => 8,
+        _               => 4,
+    };
+
+    let bits = elf.program_headers.inner.clone()
+        .filter(|ph| ph.p_type == PT_GNU_PROPERTY)
+        .filter_map(|ph| Notes::new(raw, &ph, format.swap))
+        .flatten()
+        .filter(|note| (note.kind == NT_GNU_PROPERTY_TYPE_0) & (note.name == b"GNU\0"))
+        .flat_map(|note| Properties { data: note.desc, align, swap: format.swap })
+        .filter(|prop| prop.kind == GNU_PROPERTY_X86_FEATURE_1_AND)
+        .filter_map(|prop| word(prop.data, 0, format.swap))
+        .fold(0, |bits, more| bits | more);
+
+    CetFeatures {
+        ibt:   (bits & GNU_PROPERTY_X86_FEATURE_1_IBT  ) != 0,
+        shstk: (bits & GNU_PROPERTY_X86_FEATURE_1_SHSTK) != 0,
+    }
+}
+
+/// Reads a 32-bit word of the ELF's byte order.
+fn word(data: &[u8], at: usize, swap: bool) -> Option<u32> {
+    let mut w = [0; 4];
+    w.copy_from_slice(data.get(at..at.checked_add(4)?)?);
+
+    match swap {
+        true  => Some(u32::from_ne_bytes(w).swap_bytes()),
+        false => Some(u32::from_ne_bytes(w)),
+    }
+}
+
+
+
+/// A single entry of a `PT_NOTE` segment.
+struct Note<'a> {
+    name: &'a [u8],
+    desc: &'a [u8],
+    kind: u32,
+}
+
+/// An iterator over the entries of a `PT_NOTE` segment. Stops at the first broken one.
+struct Notes<'a> {
+    data:  &'a [u8],
+    align: usize,
+    swap:  bool,
+}
+
+impl<'a> Notes<'a> {
+    fn new(raw: &'a [u8], ph: &ElfProgramHeader, swap: bool) -> Option<Self> {
+        let start = usize::try_from(ph.p_offset).ok()?;
+        let end   = usize::try_from(ph.p_offset.checked_add(ph.p_filesz)?).ok()?;
+
+        // Entries are padded to 4 bytes, except in segments made of 8-byte aligned notes,
+        // like `.note.gnu.property`.
+        let align = match ph.p_align {
+            8 => 8,
+            _ => 4,
+        };
+
+        Some(Self { data: raw.get(start..end)?, align, swap })
+    }
+
+    fn word(&self, at: usize) -> Option<u32> {
+        word(self.data, at, self.swap)
+    }
+
+    /// Rounds up to the next multiple of the entry alignment, failing on overflow.
+    fn pad(&self, len: usize) -> Option<usize> {
+        len.checked_next_multiple_of(self.align)
+    }
+
+    fn try_next(&mut self) -> Option<Note<'a>> {
+        let name_len = self.word(0)? as usize;
+        let desc_len = self.word(4)? as usize;
+        let kind     = self.word(8)?;
+
+        let name_end = 12_usize.checked_add(name_len)?;
+        let desc_at  = self.pad(name_end)?;
+        let desc_end = desc_at.checked_add(desc_len)?;
+
+        let name = self.data.get(12..name_end)?;
+        let desc = self.data.get(desc_at..desc_end)?;
+
+        // The last entry may well lack its padding.
+        self.data = self.data.get(self.pad(desc_end)?..).unwrap_or(&[]);
+
+        Some(Note { name, desc, kind })
+    }
+}
+
+impl<'a> Iterator for Notes<'a> {
+    type Item = Note<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let note = self.try_next();
+
+        if note.is_none() {
+            self.data = &[];
+        }
+
+        note
+    }
+}
+
+
+
+/// A single entry of an `NT_GNU_PROPERTY_TYPE_0` note's descriptor.
+struct Property<'a> {
+    kind: u32,
+    data: &'a [u8],
+}
+
+/// An iterator over the properties of an `NT_GNU_PROPERTY_TYPE_0` note. Stops at the first
+/// broken one.
+struct Properties<'a> {
+    data:  &'a [u8],
+    align: usize,
+    swap:  bool,
+}
+
+impl<'a> Iterator for Properties<'a> {
+    type Item = Property<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let kind     = word(self.data, 0, self.swap);
+        let data_len = word(self.data, 4, self.swap);
+
+        let data_end = data_len.and_then(|len| 8_usize.checked_add(len as usize));
+        let data     = data_end.and_then(|end| self.data.get(8..end));
+        let next     = data_end.and_then(|end| end.checked_next_multiple_of(self.align));
+
+        match (kind, data, next) {
+            (Some(kind), Some(data), Some(next)) => {
+                // The last entry may well lack its padding.
+                self.data = self.data.get(next..).unwrap_or(&[]);
+                Some(Property { kind, data })
+            },
+            _ => {
+                self.data = &[];
+                None
+            },
+        }
+    }
+}
@@ -0,0 +1,172 @@
+
+use crate::{
+    Elf, LoadedElf, ReadyElf, SegmentProtection, LoadOptions,
+    ElfError, LoadElfError, RelocElfError, ProtectFn, ResolveFn,
+};
+use alloc::alloc::{ alloc_zeroed, dealloc, Layout };
+use core::ops::Deref;
+use core::ptr::NonNull;
+use core::slice;
+
+
+
+/// A `LoadedElf` that owns its heap-allocated load buffer, see `Elf::load_boxed`.
+pub struct OwnedLoadedElf {
+    // Declared before `buf`, so that it is dropped before its memory is freed.
+    elf: LoadedElf<'static>,
+    buf: HeapBuffer,
+}
+
+/// A `ReadyElf` that owns its heap-allocated load buffer, see `OwnedLoadedElf::try_reloc`.
+///
+/// Dropping it frees the buffer. If the ELF was memory-protected, the whole buffer is made
+/// read-write again first, so that the allocator can safely reuse it.
+pub struct OwnedReadyElf {
+    elf:  ReadyElf<'static>,
+    _buf: HeapBuffer,
+}
+
+/// Where `Elf::bootstrap` re-locates the ELF to.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BasePolicy {
+    /// Re-locate the ELF to run right where it was loaded, i.e. within this address space.
+    LoaderAddress,
+
+    /// Re-locate the ELF to run at the given base address, e.g. in another address space.
+    Fixed(*mut u8),
+}
+
+/// The load buffer, which is not borrowed by anyone but the `LoadedElf` or `ReadyElf` next
+/// to it.
+struct HeapBuffer {
+    ptr:    NonNull<u8>,
+    layout: Layout,
+    base:   *mut u8,
+    prot:   Option<ProtectFn>,
+}
+
+
+
+pub fn load_boxed(elf: &Elf<'_>) -> Result<OwnedLoadedElf, LoadElfError> {
+    let buf = HeapBuffer::try_new(elf.mem_len_usize(), elf.mem_align_usize())?;
+
+    // The `'static` borrow never leaves `OwnedLoadedElf` or `OwnedReadyElf`, which both drop
+    // the borrow before the buffer. If loading fails, `buf` is freed right here.
+    let mem  = unsafe { slice::from_raw_parts_mut(buf.ptr.as_ptr(), buf.layout.size()) };
+    let opts = LoadOptions { assume_zeroed: true, ..LoadOptions::default() };
+    let elf  = elf.try_load_with(mem, opts)?;
+
+    Ok(OwnedLoadedElf { elf, buf })
+}
+
+pub fn bootstrap(raw: &[u8], base: BasePolicy, prot: Option<ProtectFn>)
+-> Result<OwnedReadyElf, ElfError> {
+    let elf        = Elf::try_parse(raw)?;
+    let mut loaded = load_boxed(&elf)?;
+    let base       = match base {
+        BasePolicy::LoaderAddress => loaded.loader_base(),
+        BasePolicy::Fixed(base)   => base,
+    };
+
+    // Dropping `loaded` on failure frees the buffer.
+    Ok(loaded.try_reloc(base, prot, None)?)
+}
+
+impl OwnedLoadedElf {
+    /// Try re-locating and memory-protecting the loaded ELF, see `LoadedElf::try_reloc`.
+    ///
+    /// On failure, the load buffer is freed, after making it read-write again if `prot` is
+    /// given, as it might already have been protected in parts.
+    pub fn try_reloc(
+        self,
+        base:    *mut u8,
+        prot:    Option<ProtectFn>,
+        resolve: Option<ResolveFn>,
+    ) -> Result<OwnedReadyElf, RelocElfError> {
+        let Self { elf, mut buf } = self;
+
+        buf.base = base;
+        buf.prot = prot;
+
+        match elf.try_reloc(base, prot, resolve) {
+            Ok(elf)     => Ok(OwnedReadyElf { elf, _buf: buf }),
+            Err((_, e)) => Err(e),
+        }
+    }
+
+    /// The final re-located ELF's base address within the ELF loader's address space.
+    pub fn loader_base(&mut self) -> *mut u8 {
+        self.elf.loader_base()
+    }
+
+    /// See `LoadedElf::coalesce_protection`.
+    pub fn coalesce_protection(&mut self, coalesce: bool) {
+        self.elf.coalesce_protection(coalesce)
+    }
+
+    /// See `LoadedElf::set_page_size`.
+    pub fn set_page_size(&mut self, page_size: u32) {
+        self.elf.set_page_size(page_size)
+    }
+
+    /// See `LoadedElf::run_ifunc_resolvers`.
+    ///
+    /// # Safety
+    ///
+    /// See `LoadedElf::run_ifunc_resolvers`.
+    pub unsafe fn run_ifunc_resolvers(&mut self, run: bool) {
+        self.elf.run_ifunc_resolvers(run)
+    }
+}
+
+impl Deref for OwnedLoadedElf {
+    type Target = LoadedElf<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.elf
+    }
+}
+
+// There is no `DerefMut` for either, as swapping two `LoadedElf`s or `ReadyElf`s would leave
+// them pointing into each other's buffers.
+impl Deref for OwnedReadyElf {
+    type Target = ReadyElf<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.elf
+    }
+}
+
+
+
+impl HeapBuffer {
+    fn try_new(len: usize, align: usize) -> Result<Self, LoadElfError> {
+        // Rounding up to whole multiples of `mem_align` keeps memory protection from spilling
+        // over into other allocations. Also, zero-sized allocations are undefined behaviour.
+        // Zeroed memory is initialised memory, and lets loading skip zero-filling it.
+        let align  = align.max(1);
+        let layout = len.max(1).checked_next_multiple_of(align)
+            .and_then(|len| Layout::from_size_align(len, align).ok())
+            .ok_or(LoadElfError::BadBufferAlignment)?;
+        let ptr    = NonNull::new(unsafe { alloc_zeroed(layout) })
+            .ok_or(LoadElfError::OutOfMemory)?;
+
+        Ok(Self { ptr, layout, base: core::ptr::null_mut(), prot: None })
+    }
+}
+
+impl Drop for HeapBuffer {
+    fn drop(&mut self) {
+        let p_base  = self.ptr.as_ptr();
+        let mem_len = self.layout.size();
+
+        if let Some(prot) = self.prot {
+            // If this fails, leaking the memory is the only safe thing left to do.
+            if (prot)(SegmentProtection::RW, p_base, self.base, mem_len, 0 .. mem_len).is_err() {
+                return;
+            }
+        }
+
+        unsafe { dealloc(p_base, self.layout) };
+    }
+}
@@ -1,66 +1,213 @@
 
 use crate::elf::{
-    ElfFileHeader, ElfProgramHeader,
-    EI_CLASS, EI_DATA, ET_DYN,
-    ELFMAG, SELFMAG, ELFCLASS64, ELFDATA2LSB, ELFDATA2MSB,
+    ElfClass, ElfFormat, ElfFileHeader, ElfFileHeader32, ElfProgramHeader,
+    EI_CLASS, EI_DATA, EI_VERSION, EI_OSABI, EV_CURRENT, ET_DYN, ET_EXEC,
+    ELFOSABI_SYSV, ELFOSABI_GNU,
+    ELFMAG, SELFMAG, ELFCLASS32, ELFCLASS64, ELFDATA2LSB, ELFDATA2MSB,
     EM_AARCH64, EM_RISCV, EM_X86_64,
-    PF_X, PT_LOAD,
+    PF_W, PF_X, PT_LOAD, PT_DYNAMIC, PT_GNU_RELRO,
 };
-use crate::{ ParseElfError, Elf, ProgramHeaders };
-use core::slice::{ self, Iter };
+use crate::{ ParseElfError, Elf, FileInfo, LazyElf, ProgramHeaders, SectionHeaders };
+use crate::dynamic::{ DynInfo, FileDyns, parse_dynamic };
 use core::mem;
 
 
 
 pub fn try_parse_elf<'a>(raw: &'a [u8]) -> Result<Elf<'a>, ParseElfError> {
-    let  header                                      = try_load_header(raw)?;
-    let (mem_len, mem_align, entry, program_headers) = try_load_program_headers(header, raw)?;
+    parse_elf(raw, Accept::Native, false)
+}
 
-    Ok(Elf { program_headers, mem_len, mem_align, entry })
+pub fn try_parse_elf_exec<'a>(raw: &'a [u8]) -> Result<Elf<'a>, ParseElfError> {
+    parse_elf(raw, Accept::Native, true)
 }
 
+pub fn try_parse_elf_strict<'a>(raw: &'a [u8]) -> Result<Elf<'a>, ParseElfError> {
+    let elf = parse_elf(raw, Accept::Native, false)?;
+
+    // Other OS/ABIs may well attach their own meaning to e.g. re-location types.
+    if !matches!(elf.file.os_abi, ELFOSABI_SYSV | ELFOSABI_GNU) {
+        return Err(ParseElfError::BadOsAbi);
+    }
 
+    // Just the segments that end up in protection requests. E.g. an executable stack
+    // requested via `PT_GNU_STACK` is none of this loader's business.
+    let wx = elf.program_headers.inner.clone()
+        .filter(|ph| matches!(ph.p_type, PT_LOAD | PT_DYNAMIC | PT_GNU_RELRO))
+        .any(|ph| (ph.p_flags & (PF_W | PF_X)) == (PF_W | PF_X));
 
-fn try_load_header(raw: &[u8]) -> Result<&ElfFileHeader, ParseElfError> {
-    if (raw.len() < mem::size_of::<ElfFileHeader>())
-     | (raw.len() > (u32::max_value() as usize)) {
-        return Err(ParseElfError::BadBufferSize);
+    match wx {
+        true  => Err(ParseElfError::WritableAndExecutable),
+        false => Ok(elf),
     }
+}
 
-    if 0 != ((raw.as_ptr() as usize) % mem::align_of::<ElfFileHeader>()) {
-        return Err(ParseElfError::BadBufferAlignment);
+pub fn try_parse_elf_foreign<'a>(raw: &'a [u8]) -> Result<Elf<'a>, ParseElfError> {
+    parse_elf(raw, Accept::Foreign, false)
+}
+
+pub fn try_parse_elf_for<'a>(raw: &'a [u8], accept: fn(u16) -> bool)
+-> Result<Elf<'a>, ParseElfError> {
+    parse_elf(raw, Accept::Machines(accept), false)
+}
+
+/// Which ELFs `try_load_header` accepts.
+#[derive(Copy, Clone)]
+enum Accept {
+    /// Only ELFs of native byte order and ISA, which may be loaded.
+    Native,
+    /// ELFs of any byte order and ISA, which may only be inspected.
+    Foreign,
+    /// ELFs of native byte order and any ISA the function accepts. Only native ones may be
+    /// loaded.
+    Machines(fn(u16) -> bool),
+}
+
+/// In `exec` mode, fixed-address `ET_EXEC` executables are fine, too.
+fn parse_elf(raw: &[u8], accept: Accept, exec: bool) -> Result<Elf<'_>, ParseElfError> {
+    let (header, format) = try_load_header(raw, accept, exec)?;
+    let foreign          = match accept {
+        Accept::Native      => false,
+        Accept::Foreign     => true,
+        Accept::Machines(_) => check_isa(header.e_machine).is_err(),
+    };
+    let (mem_len, align_log2, entry, program_headers) =
+        try_load_program_headers(&header, format, raw)?;
+    // Loading doesn't need any section headers, so a broken table doesn't fail parsing.
+    let section_headers = SectionHeaders::try_new(&header, format, raw);
+    let link_base       = program_headers.inner.base;
+    let fixed_base      = match header.e_type {
+        ET_EXEC => Some(link_base),
+        _       => None,
+    };
+
+    Ok(with_dyn_info(Elf {
+        program_headers, section_headers,
+        mem_len, align_log2, entry, link_base, fixed_base, foreign,
+        file:     file_info(&header),
+        dyn_info: DynInfo::default(),
+    }))
+}
+
+pub fn try_parse_elf_lazy<'a>(raw: &'a [u8]) -> Result<LazyElf<'a>, ParseElfError> {
+    let (header, format) = try_load_header(raw, Accept::Native, false)?;
+    let hdrs             = try_slice_program_headers(&header, format, raw)?;
+    let secs             = SectionHeaders::try_new(&header, format, raw);
+
+    Ok(LazyElf { hdrs, secs, raw, entry: header.e_entry, file: file_info(&header) })
+}
+
+pub fn validate_lazy_elf<'a>(elf: &LazyElf<'a>) -> Result<Elf<'a>, ParseElfError> {
+    let entry = match elf.entry {
+        0   => None,
+        ent => Some(ent.wrapping_sub(elf.hdrs.base)),
+    };
+    let machine               = elf.file.machine;
+    let (mem_len, align_log2) = check_ph_ranges(elf.hdrs.clone(), elf.raw, entry, machine)?;
+
+    Ok(with_dyn_info(Elf {
+        program_headers: ProgramHeaders { inner: elf.hdrs.clone(), elf: elf.raw },
+        section_headers: elf.secs.clone(),
+        mem_len, align_log2,
+        entry:      entry.unwrap_or(0) as u32,
+        link_base:  elf.hdrs.base,
+        fixed_base: None,
+        foreign:    false,
+        file:       elf.file,
+        dyn_info:   DynInfo::default(),
+    }))
+}
+
+/// Scans the file's `PT_DYNAMIC` entries, so that nothing needs to scan them again.
+fn with_dyn_info(mut elf: Elf<'_>) -> Elf<'_> {
+    elf.dyn_info = parse_dynamic(FileDyns::new(&elf));
+    elf
+}
+
+fn file_info(header: &ElfFileHeader) -> FileInfo {
+    FileInfo {
+        kind:    header.e_type,
+        machine: header.e_machine,
+        flags:   header.e_flags,
+        os_abi:  header.e_ident[EI_OSABI],
+        phoff:   header.e_phoff,
     }
+}
 
-    let header: &ElfFileHeader = unsafe { mem::transmute(raw.as_ptr()) };
 
-    if &header.e_ident[..SELFMAG] != &ELFMAG[..] {
+
+/// Reads the file header, widened to ELF64 if it is an ELF32 one.
+///
+/// Depending on `accept`, the ELF may be of foreign byte order or ISA.
+fn try_load_header(raw: &[u8], accept: Accept, exec: bool)
+-> Result<(ElfFileHeader, ElfFormat), ParseElfError> {
+    // The ELF32 header is the smaller one, and enough to tell the class.
+    // The buffer itself may well be larger than 4GiB, e.g. due to huge debug sections. Only
+    // the program headers and the data they copy have to lie within the first 4GiB.
+    // Headers are only ever copied out of the buffer, so it needs no particular alignment,
+    // e.g. if it is a slice of some larger file, or an `include_bytes!` that isn't aligned.
+    if raw.len() < mem::size_of::<ElfFileHeader32>() {
+        return Err(ParseElfError::BadBufferSize);
+    }
+
+    if !raw.starts_with(&ELFMAG[..SELFMAG]) {
         return Err(ParseElfError::BufferNotElf);
     }
 
-    if (header.e_ehsize as usize) != mem::size_of::<ElfFileHeader>() {
-        return Err(ParseElfError::BadHeaderSize);
+    // `raw` is longer than any identification, so the fallback is just there to spare us a
+    // bounds check.
+    let ident = |idx: usize| raw.get(idx).copied().unwrap_or(0);
+
+    let class = match ident(EI_CLASS) {
+        ELFCLASS64                            => ElfClass::Elf64,
+        ELFCLASS32 if cfg!(feature = "elf32") => ElfClass::Elf32,
+        _                                     => return Err(ParseElfError::NotElf64),
+    };
+
+    let foreign = matches!(accept, Accept::Foreign);
+    let swap    = match (is_native_endian(ident(EI_DATA))?, foreign) {
+        (native, true ) => !native,
+        (true,   false) => false,
+        (false,  false) => return Err(ParseElfError::BadEndian),
+    };
+
+    if ident(EI_VERSION) != EV_CURRENT {
+        return Err(ParseElfError::BadVersion);
     }
 
-    // FIXME maybe allow ELF32 one day
-    if header.e_ident[EI_CLASS] != ELFCLASS64 {
-        return Err(ParseElfError::NotElf64);
+    let format = ElfFormat { class, swap };
+    let header = format.read_header(raw).ok_or(ParseElfError::BadBufferSize)?;
+
+    if header.e_version != u32::from(EV_CURRENT) {
+        return Err(ParseElfError::BadVersion);
     }
 
-    check_is_native_endian(header.e_ident[EI_DATA ])?;
+    if (header.e_ehsize as usize) != class.header_len() {
+        return Err(ParseElfError::BadHeaderSize);
+    }
 
-    if header.e_type != ET_DYN {
-        return Err(ParseElfError::NotPic);
+    match header.e_type {
+        ET_DYN          => (),
+        ET_EXEC if exec => (),
+        ET_EXEC         => return Err(ParseElfError::NotPic),
+        _               => return Err(ParseElfError::NotExecutable),
     }
 
-    check_isa(header.e_machine)?; // TODO ? header.e_flags
+    match accept {
+        Accept::Native           => check_isa(header.e_machine)?, // TODO ? header.e_flags
+        Accept::Foreign          => (),
+        Accept::Machines(accept) => if !accept(header.e_machine) {
+            return Err(ParseElfError::BadIsa);
+        },
+    }
 
-    Ok(header)
+    Ok((header, format))
 }
 
-fn check_is_native_endian(tag: u8) -> Result<(), ParseElfError> {
+/// Whether the byte order tag matches the target's. Fails for invalid tags.
+fn is_native_endian(tag: u8) -> Result<bool, ParseElfError> {
     match tag {
-        ELFDATA2LSB if cfg!(target_endian = "little") => Ok(()),
-        ELFDATA2MSB if cfg!(target_endian = "big"   ) => Ok(()),
+        ELFDATA2LSB => Ok(cfg!(target_endian = "little")),
+        ELFDATA2MSB => Ok(cfg!(target_endian = "big"   )),
 
         _ => Err(ParseElfError::BadEndian),
     }
@@ -69,7 +216,7 @@ fn check_is_native_endian(tag: u8) -> Result<(), ParseElfError> {
 fn check_isa(tag: u16) -> Result<(), ParseElfError> {
     let wat = match tag {
         EM_AARCH64 => cfg!(target_arch = "aarch64"),
-        EM_RISCV   => false, // FIXME wait for `rustc` to target RV64
+        EM_RISCV   => cfg!(target_arch = "riscv64"),
         EM_X86_64  => cfg!(target_arch = "x86_64"),
         // FIXME more archs?
 
@@ -82,88 +229,205 @@ fn check_isa(tag: u16) -> Result<(), ParseElfError> {
 
 
 
-fn try_load_program_headers<'a>(hdr: &'a ElfFileHeader, raw: &'a [u8])
--> Result<(u32, u32, u32, ProgramHeaders<'a>), ParseElfError> {
-    if (hdr.e_phentsize as usize) != mem::size_of::<ElfProgramHeader>() {
+fn try_load_program_headers<'a>(hdr: &ElfFileHeader, format: ElfFormat, raw: &'a [u8])
+-> Result<(u32, u8, u32, ProgramHeaders<'a>), ParseElfError> {
+    let hdrs = try_slice_program_headers(hdr, format, raw)?;
+
+    // Like the program headers, the entry point is relative to the link-time base, if any.
+    // This may well make it zero for fixed-address executables.
+    let entry = match hdr.e_entry {
+        0   => None,
+        ent => Some(ent.wrapping_sub(hdrs.base)),
+    };
+
+    // Bounds-check here, so we can blindly slice the ELF buffer later.
+    let (mem_len, align_log2) = check_ph_ranges(hdrs.clone(), raw, entry, hdr.e_machine)?;
+    let entry                 = entry.unwrap_or(0);
+
+    Ok((mem_len, align_log2, entry as u32, ProgramHeaders {
+        inner: hdrs,
+        elf:   raw,
+    }))
+}
+
+fn try_slice_program_headers<'a>(hdr: &ElfFileHeader, format: ElfFormat, raw: &'a [u8])
+-> Result<RawProgramHeaders<'a>, ParseElfError> {
+    let class = format.class;
+
+    if (hdr.e_phentsize as usize) != class.ph_len() {
         return Err(ParseElfError::BadProgramHeaderSize);
     }
 
     let hoff = hdr.e_phoff;
-    let ptr  = unsafe { raw.as_ptr().add(hoff as usize) as *const ElfProgramHeader };
-    let len  = hdr.e_phnum as usize;
-    let l    = raw.len() as u64;
-
-    if (mem::size_of::<ElfProgramHeader>() as u64).checked_mul(len as u64)
-            .and_then(|x| x.checked_add(hoff))
-            .map(|x| x >= l)
-            .unwrap_or(true) {
+    let len  = (class.ph_len() as u64) * (hdr.e_phnum as u64);
+
+    if len.checked_add(hoff)
+          .map(|x| (x > (raw.len() as u64)) | (x > (u32::MAX as u64)))
+          .unwrap_or(true) {
         return Err(ParseElfError::ProgramHeaderOverflow);
     }
 
-    if 0 != ((ptr as usize) % mem::align_of::<ElfProgramHeader>()) {
+    let table = raw.get((hoff as usize)..((hoff + len) as usize))
+        .ok_or(ParseElfError::ProgramHeaderOverflow)?;
+
+    // Program headers are only ever read unaligned, but a table misaligned within the file
+    // still hints at a broken ELF buffer.
+    if !hoff.is_multiple_of(class.ph_align() as u64) {
         return Err(ParseElfError::BadBufferAlignment);
     }
 
-    let hdrs: &[ElfProgramHeader] = unsafe { slice::from_raw_parts(ptr, len) };
+    let mut hdrs = RawProgramHeaders { table, format, base: 0 };
 
-    // Bounds-check here, so we can blindly slice the ELF buffer later.
-    let (mem_len, mem_align) = check_ph_ranges(hdrs.iter(), raw, hdr.e_entry)?;
+    hdrs.base = link_base(hdrs.clone());
 
-    Ok((mem_len, mem_align, hdr.e_entry as u32, ProgramHeaders {
-        inner: hdrs.iter(),
-        elf:   raw,
-    }))
+    Ok(hdrs)
 }
 
-fn check_ph_ranges<'a>(hdrs: Iter<'a, ElfProgramHeader>, raw: &'a [u8], ent: u64)
--> Result<(u32, u32), ParseElfError> {
-    let mut end_offset   = 0;
-    let mut max_align    = 1;
-    let mut entry_in_exe = false;
+/// The lowest `PT_LOAD` address, rounded down to the highest segment alignment.
+///
+/// Fixed-address executables usually live far above address zero, and so may shared objects
+/// linked at a preferred address, so their segments are treated as relative to this base.
+/// Only the 4GiB above the base are supported. Most position-independent ELFs start at zero.
+fn link_base(hdrs: RawProgramHeaders<'_>) -> u64 {
+    // Alignments are only checked later on. Rounding down to anything else than a power of
+    // two is still in bounds, and `BadAlignment` follows anyway.
+    let align = hdrs.clone().map(|ph| ph.p_align).max().unwrap_or(1).max(1);
+    let low   = hdrs.filter(|ph| ph.p_type == PT_LOAD).map(|ph| ph.p_vaddr).min().unwrap_or(0);
+
+    low & !(align - 1)
+}
 
-    // FIXME Bail out on too high header count?
-    for ph in hdrs {
-        // `p_offset` and `p_filesz` implicitly checked against a 4GiB limit,
-        // as `raw.len()` has already checked to be at most that.
-        if ph.p_offset.checked_add(ph.p_filesz)
-                      .map(|x| x >= (raw.len() as u64))
-                      .unwrap_or(true) {
-            return Err(ParseElfError::BadPhRange);
-        }
+/// An iterator over all the raw program headers, widened to ELF64 and byte-swapped to
+/// native byte order if need be.
+///
+/// `p_vaddr` is made relative to the link-time base, which is zero for most PIEs.
+#[derive(Clone)]
+pub struct RawProgramHeaders<'a> {
+    table:  &'a [u8],
+    format: ElfFormat,
+    base:   u64,
+}
 
-        if (ph.p_vaddr.checked_add(ph.p_memsz)
-                      .map(|x| x > (u32::max_value() as u64))
-                      .unwrap_or(true))
-         | (ph.p_memsz > (u32::max_value() as u64)) {
-            return Err(ParseElfError::BadVmemRange);
-        }
+impl<'a> RawProgramHeaders<'a> {
+    pub fn format(&self) -> ElfFormat {
+        self.format
+    }
+
+    /// The size of the remaining table, in bytes.
+    pub fn table_len(&self) -> usize {
+        self.table.len()
+    }
+}
+
+impl<'a> Iterator for RawProgramHeaders<'a> {
+    type Item = ElfProgramHeader;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut ph = self.format.read_ph(self.table)?;
 
-        if ph.p_memsz < ph.p_filesz {
-            return Err(ParseElfError::PhSmallerThanVmem);
+        self.table = self.table.get(self.format.class.ph_len()..).unwrap_or(&[]);
+
+        // Headers below the base, like an all-zero `PT_GNU_STACK`, are left as they are.
+        if ph.p_vaddr >= self.base {
+            ph.p_vaddr -= self.base;
         }
 
-        if ent != 0 {
-            if ((ph.p_type, ph.p_flags & PF_X) == (PT_LOAD, PF_X))
-            & ((ent >= ph.p_vaddr) & (ent < ph.p_vaddr.wrapping_add(ph.p_memsz))) {
-                // In case there are - for whatever reason - valid ELF files with many
-                // executable segments, delaying the error return allows us to check
-                // the entry address against all of them.
-                entry_in_exe = true;
-            }
+        Some(ph)
+    }
+}
+
+/// Returns the memory length, and the log2 of the highest segment alignment.
+fn check_ph_ranges(hdrs: RawProgramHeaders<'_>, raw: &[u8], ent: Option<u64>, machine: u16)
+-> Result<(u32, u8), ParseElfError> {
+    let mut end_offset   = 0;
+    let mut max_align    = 0;
+    let mut entry_in_exe = false;
+    let (has_ent, ent)   = (ent.is_some(), ent.unwrap_or(0));
+
+    // Instructions are 4 bytes each on AArch64, but of any length on x86_64.
+    let ent_aligned = (machine != EM_AARCH64) || ent.is_multiple_of(4);
+
+    // FIXME Bail out on too high header count?
+    for ph in hdrs.clone() {
+        check_ph_range(&ph, raw)?;
+
+        // Code in the zero-filled tail past `p_filesz` is no code at all.
+        if has_ent
+        & ((ph.p_type, ph.p_flags & PF_X) == (PT_LOAD, PF_X))
+        & ((ent >= ph.p_vaddr) & (ent < ph.p_vaddr.wrapping_add(ph.p_filesz))) {
+            // In case there are - for whatever reason - valid ELF files with many
+            // executable segments, delaying the error return allows us to check
+            // the entry address against all of them.
+            entry_in_exe = true;
         }
 
+        // `check_ph_range` made sure this is a power of two that fits a `u32`.
         let end   = (ph.p_vaddr.wrapping_add(ph.p_memsz)) as u32;
-        let align = if ph.p_align <= (u32::max_value() as u64) { ph.p_align as u32 }
-                    else { return Err(ParseElfError::ExcessiveAlignment); };
+        let align = ph.p_align.max(1).trailing_zeros() as u8;
 
         if end   > end_offset { end_offset = end;   }
         if align > max_align  { max_align  = align; }
     }
 
-    // FIXME For shared objects, it seems to be the case that `ent==0` means no entry. Check this.
-    if (ent != 0) & (!entry_in_exe) {
+    // An `e_entry` of zero means there is no entry function, as for symbol-only shared
+    // objects. Those may not even have executable segments, so there is nothing to check.
+    if has_ent & (!entry_in_exe | !ent_aligned) {
         return Err(ParseElfError::BadEntry);
     }
 
+    check_load_overlaps(hdrs)?;
+
     Ok((end_offset, max_align))
 }
+
+/// Makes sure no two `PT_LOAD` segments claim the same bytes, lest one of them overwrites
+/// the other while loading. Other segments, e.g. `PT_GNU_RELRO`, overlap loads by design.
+///
+/// There are only so many segments, so quadratic time is fine.
+fn check_load_overlaps(hdrs: RawProgramHeaders<'_>) -> Result<(), ParseElfError> {
+    // `check_ph_range` made sure these fit a `u32`, so they don't wrap around.
+    let loads = hdrs
+        .filter(|ph| (ph.p_type == PT_LOAD) & (ph.p_memsz != 0))
+        .map(|ph| ph.p_vaddr .. (ph.p_vaddr + ph.p_memsz));
+
+    for (i, a) in loads.clone().enumerate() {
+        if loads.clone().skip(i + 1).any(|b| (a.start < b.end) & (b.start < a.end)) {
+            return Err(ParseElfError::OverlappingSegments);
+        }
+    }
+
+    Ok(())
+}
+
+/// All the checks that only concern a single program header.
+pub fn check_ph_range(ph: &ElfProgramHeader, raw: &[u8]) -> Result<(), ParseElfError> {
+    // Whatever gets copied has to lie within the first 4GiB of the buffer, no matter how large
+    // the whole buffer is.
+    if ph.p_offset.checked_add(ph.p_filesz)
+                  .map(|x| (x > (raw.len() as u64)) | (x > (u32::MAX as u64)))
+                  .unwrap_or(true) {
+        return Err(ParseElfError::BadPhRange);
+    }
+
+    if (ph.p_vaddr.checked_add(ph.p_memsz)
+                  .map(|x| x > (u32::MAX as u64))
+                  .unwrap_or(true))
+     | (ph.p_memsz > (u32::MAX as u64)) {
+        return Err(ParseElfError::BadVmemRange);
+    }
+
+    if ph.p_memsz < ph.p_filesz {
+        return Err(ParseElfError::PhSmallerThanVmem);
+    }
+
+    if ph.p_align > (u32::MAX as u64) {
+        return Err(ParseElfError::ExcessiveAlignment);
+    }
+
+    // Both zero and one mean no alignment at all.
+    if (ph.p_align > 1) & !ph.p_align.is_power_of_two() {
+        return Err(ParseElfError::BadAlignment);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,202 @@
+
+use crate::{ LoadedElf, LoadElfError, SegmentStack, Slice32, DEFAULT_PAGE_SIZE };
+use crate::elf::{ ElfClass, ElfDyn };
+use crate::dynamic::DynFlags;
+use crate::tls::TlsSegment;
+use crate::protect::check_shared_pages;
+use crate::reloc::scan_dynamic;
+use core::mem;
+
+
+
+/// Everything a `LoadedElf` knows about its loaded memory, but the memory itself.
+///
+/// Together with a copy of the loaded memory, this suffices to re-create a `LoadedElf` with
+/// `LoadedElf::try_from_parts`, e.g. in another process. With the `serde` feature, this can be
+/// serialised.
+// The `SegmentStack` looks the same with or without the `alloc` feature when serialised.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LoadedElfMeta {
+    dyns:       Slice32<ElfDyn>,
+    mem_align:  u32,
+    entry:      u32,
+    protect:    SegmentStack,
+    flags:      DynFlags,
+    tls:        Option<TlsSegment>,
+    class:      ElfClass,
+    // Metadata of older versions knows nothing of ELFs linked above address zero.
+    #[cfg_attr(feature = "serde", serde(default))]
+    link_base:  u64,
+    fixed_base: Option<u64>,
+    // Neither does it know whether `Rel` and `Relr` addends were already overwritten.
+    #[cfg_attr(feature = "serde", serde(default))]
+    relocated:  bool,
+    // Nor where the program header table was loaded to.
+    #[cfg_attr(feature = "serde", serde(default))]
+    phdrs:      Option<Slice32<u8>>,
+}
+
+impl LoadedElfMeta {
+    /// Minimum alignment, in bytes, of the buffer to hand to `LoadedElf::try_from_parts`.
+    pub fn mem_align(&self) -> u32 {
+        self.mem_align
+    }
+}
+
+
+
+pub fn into_parts(elf: LoadedElf<'_>) -> (&mut [u8], LoadedElfMeta) {
+    let meta = LoadedElfMeta {
+        dyns:       elf.dyns,
+        mem_align:  elf.mem_align,
+        entry:      elf.entry,
+        protect:    elf.protect,
+        flags:      elf.flags,
+        tls:        elf.tls,
+        class:      elf.class,
+        link_base:  elf.link_base,
+        fixed_base: elf.fixed_base,
+        relocated:  elf.relocated,
+        phdrs:      elf.phdrs,
+    };
+
+    (elf.mem, meta)
+}
+
+pub fn try_from_parts(mem: &mut [u8], meta: LoadedElfMeta)
+-> Result<LoadedElf<'_>, LoadElfError> {
+    if !meta.mem_align.is_power_of_two() {
+        return Err(LoadElfError::BadMetadata);
+    }
+
+    if ((mem.as_ptr() as usize) & ((meta.mem_align - 1) as usize)) != 0 {
+        return Err(LoadElfError::BadBufferAlignment);
+    }
+
+    // `try_reloc` trusts all of these ranges just as much as the ones of freshly loaded ELFs.
+    let len  = mem.len();
+    let fits = meta.dyns.fits_in(len)
+             & meta.protect.as_slice().iter().all(|seg| seg.range.fits_in(len))
+             & meta.protect.as_slice().iter().all(|seg| seg.file_len <= seg.range.len)
+             & meta.tls.map(|tls| tls.image.fits_in(len)).unwrap_or(true)
+             & meta.phdrs.map(|phdrs| phdrs.fits_in(len)).unwrap_or(true)
+             & ((meta.entry as usize) <= len);
+
+    if !fits {
+        return Err(LoadElfError::BadBufferSize);
+    }
+
+    if !(meta.dyns.start as usize).is_multiple_of(mem::align_of::<ElfDyn>()) {
+        return Err(LoadElfError::BadMetadata);
+    }
+
+    // Parsing made sure of these, but nothing stops forged metadata from dividing by zero.
+    if meta.tls.map(|tls| !tls.align.is_power_of_two() | (tls.image.len > tls.mem_len))
+               .unwrap_or(false) {
+        return Err(LoadElfError::BadMetadata);
+    }
+
+    check_shared_pages(meta.protect.as_slice(), meta.mem_align, len)?;
+
+    let dyn_info = scan_dynamic(mem, meta.dyns, meta.link_base);
+
+    Ok(LoadedElf {
+        mem,
+        dyns:       meta.dyns,
+        mem_align:  meta.mem_align,
+        entry:      meta.entry,
+        protect:    meta.protect,
+        coalesce:   false,
+        ifunc:      false,
+        page_size:  DEFAULT_PAGE_SIZE,
+        flags:      meta.flags,
+        tls:        meta.tls,
+        class:      meta.class,
+        link_base:  meta.link_base,
+        fixed_base: meta.fixed_base,
+        dyn_info,
+        relocated:  meta.relocated,
+        phdrs:      meta.phdrs,
+    })
+}
+
+
+/// Copies the loaded memory into `dst`, and re-creates the loaded ELF there, settings and all.
+pub fn copy_into<'b>(elf: &LoadedElf<'_>, dst: &'b mut [u8])
+-> Result<LoadedElf<'b>, LoadElfError> {
+    let len = elf.mem.len();
+
+    if ((dst.as_ptr() as usize) & ((elf.mem_align - 1) as usize)) != 0 {
+        return Err(LoadElfError::BadBufferAlignment);
+    }
+
+    let mem = dst.get_mut(..len).ok_or(LoadElfError::BadBufferSize)?;
+    mem.copy_from_slice(elf.mem);
+
+    let mut protect = SegmentStack::new();
+
+    for seg in elf.protect.as_slice() {
+        protect.push_segment(*seg);
+    }
+
+    Ok(LoadedElf {
+        mem, protect,
+        dyns:       elf.dyns,
+        mem_align:  elf.mem_align,
+        entry:      elf.entry,
+        coalesce:   elf.coalesce,
+        ifunc:      elf.ifunc,
+        page_size:  elf.page_size,
+        flags:      elf.flags,
+        tls:        elf.tls,
+        class:      elf.class,
+        link_base:  elf.link_base,
+        fixed_base: elf.fixed_base,
+        dyn_info:   elf.dyn_info,
+        // Whatever `reloc_into` overwrote is in the copy just as well.
+        relocated:  elf.relocated,
+        phdrs:      elf.phdrs,
+    })
+}
+
+
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SegmentStack {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self.as_slice())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SegmentStack {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        use crate::Segment;
+        use serde::de::{ Error, SeqAccess, Visitor };
+        use core::fmt;
+
+        struct SegmentsVisitor;
+
+        impl<'de> Visitor<'de> for SegmentsVisitor {
+            type Value = SegmentStack;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sequence of segments")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut segs = SegmentStack::new();
+
+                while let Some(seg) = seq.next_element::<Segment>()? {
+                    segs.push_segment(seg);
+                }
+
+                segs.check_capacity().map_err(A::Error::custom)?;
+
+                Ok(segs)
+            }
+        }
+
+        d.deserialize_seq(SegmentsVisitor)
+    }
+}
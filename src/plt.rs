@@ -0,0 +1,67 @@
+
+use crate::{ ReadyElf, SegmentProtection };
+use crate::dynamic::DynTable;
+use crate::elf::{
+    ElfRel, ElfRela,
+    DT_REL,
+    R_X86_64_JUMP_SLOT, R_AARCH64_JUMP_SLOT, R_RISCV_JUMP_SLOT,
+    r_type,
+};
+use crate::symbol::read;
+use core::mem;
+
+
+
+pub fn verify_plt(elf: &ReadyElf<'_>) -> Result<(), usize> {
+    let mem     = elf.p_mem();
+    let lb      = elf.syms.link_base;
+    let info    = elf.dyn_info;
+    let ent_len = match info.plt_rel {
+        Some(DT_REL) => mem::size_of::<ElfRel >() as u64,
+        _            => mem::size_of::<ElfRela>() as u64,
+    };
+
+    let DynTable { off: table, len: table_len } = info.jmprel;
+
+    if table == 0 {
+        return Ok(());
+    }
+
+    let mut at  = table;
+    let     end = table.checked_add(table_len).ok_or(table as usize)?;
+
+    while at < end {
+        // The first two fields of `Rel` and `Rela` are the same.
+        let r_offset = read::<u64>(mem, at    ).ok_or(at as usize)?.wrapping_sub(lb);
+        let r_info   = read::<u64>(mem, at + 8).ok_or(at as usize)?;
+
+        if is_jump_slot(r_info) {
+            let target = read::<u64>(mem, r_offset).ok_or(r_offset as usize)?;
+
+            if !is_executable(elf, target) {
+                return Err(r_offset as usize);
+            }
+        }
+
+        at += ent_len;
+    }
+
+    Ok(())
+}
+
+fn is_jump_slot(r_info: u64) -> bool {
+    let ty = r_type(r_info);
+
+    (cfg!(target_arch = "x86_64" ) && (ty == R_X86_64_JUMP_SLOT ))
+  | (cfg!(target_arch = "aarch64") && (ty == R_AARCH64_JUMP_SLOT))
+  | (cfg!(target_arch = "riscv64") && (ty == R_RISCV_JUMP_SLOT  ))
+}
+
+/// Whether the given address, in the ready ELF's address space, points into RX memory.
+fn is_executable(elf: &ReadyElf<'_>, addr: u64) -> bool {
+    let off = match addr.checked_sub(elf.base as u64) { Some(o) => o, None => return false };
+
+    elf.effective_protection_map()
+       .filter(|(_, prot)| *prot == SegmentProtection::RX)
+       .any(|(range, _)| (off >= (range.start as u64)) & (off < (range.end as u64)))
+}
@@ -0,0 +1,198 @@
+
+use crate::{ Segment, SegmentKind, SegmentProtection, LoadElfError };
+use core::ops::Range;
+use core::slice;
+
+
+
+/// An iterator over the final memory protection of a loaded ELF's memory, as if all
+/// protection requests had been applied in order.
+///
+/// Yields non-overlapping, ascending ranges that cover the whole loaded memory. Adjacent
+/// ranges of equal protection are merged.
+#[derive(Clone)]
+pub struct ProtectionMap<'a> {
+    segs:      &'a [Segment],
+    align:     u32,
+    page_size: u32,
+    mem_len:   usize,
+    at:        usize,
+}
+
+impl<'a> ProtectionMap<'a> {
+    pub(crate) fn new(segs: &'a [Segment], align: u32, page_size: u32, mem_len: usize)
+    -> Self {
+        Self { segs, align, page_size, mem_len, at: 0 }
+    }
+
+    /// The closest segment boundary after `offset`, or `mem_len` if there is none.
+    ///
+    /// Searching anew for every range keeps this free of any fixed-size buffers, no matter
+    /// how many segments there are.
+    fn next_point(&self, offset: usize) -> usize {
+        self.segs.iter()
+            .flat_map(|seg| {
+                let r = seg.protect_range(self.align, self.page_size, self.mem_len);
+                [r.start, r.end]
+            })
+            .filter(|point| *point > offset)
+            .min()
+            .unwrap_or(self.mem_len)
+    }
+
+    /// Segments sharing a page get the union of their protection, but `GNU_RELRO` overrules
+    /// all older requests. Everything not covered by any segment is read-only.
+    fn protection_at(&self, offset: usize) -> SegmentProtection {
+        self.segs.iter()
+            .filter(|seg| {
+                seg.protect_range(self.align, self.page_size, self.mem_len).contains(&offset)
+            })
+            .fold(SegmentProtection::RO, |prot, seg| match seg.kind {
+                SegmentKind::Relro => seg.protect,
+                // `check_shared_pages` rules out `RW` and `RX` sharing a page.
+                _                  => union(prot, seg.protect).unwrap_or(SegmentProtection::RX),
+            })
+    }
+}
+
+impl<'a> Iterator for ProtectionMap<'a> {
+    type Item = (Range<usize>, SegmentProtection);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.at >= self.mem_len {
+            return None;
+        }
+
+        let start   = self.at;
+        let prot    = self.protection_at(start);
+        let mut end = self.next_point(start);
+
+        while (end < self.mem_len) && (self.protection_at(end) == prot) {
+            end = self.next_point(end);
+        }
+
+        self.at = end;
+
+        Some((start .. end, prot))
+    }
+}
+
+
+
+/// Whether any two segments share a page, i.e. overlap only after widening them to whole
+/// multiples of `align`. `GNU_RELRO` segments are meant to overlap, so they don't count.
+pub fn shares_pages(segs: &[Segment], align: u32, mem_len: usize) -> bool {
+    page_overlaps(segs, align, mem_len)
+        .any(|(a, b)| !overlap(&a.range.to_byte_range(), &b.range.to_byte_range()))
+}
+
+/// Fails if a read-write and a read-execute segment overlap or share a page, as there is no
+/// protection that would suit both.
+pub fn check_shared_pages(segs: &[Segment], align: u32, mem_len: usize)
+-> Result<(), LoadElfError> {
+    match page_overlaps(segs, align, mem_len).all(|(a, b)| union(a.protect, b.protect).is_some()) {
+        true  => Ok(()),
+        false => Err(LoadElfError::SharedPageConflict),
+    }
+}
+
+/// All pairs of segments, but `GNU_RELRO` ones, with at least one page in common.
+fn page_overlaps(segs: &[Segment], align: u32, mem_len: usize)
+-> impl Iterator<Item = (&Segment, &Segment)> {
+    segs.iter().enumerate()
+        .flat_map(move |(i, a)| segs.iter().skip(i + 1).map(move |b| (a, b)))
+        .filter(|(a, b)| (a.kind != SegmentKind::Relro) & (b.kind != SegmentKind::Relro))
+        // Without `GNU_RELRO` ranges, the page size does not matter.
+        .filter(move |(a, b)| {
+            let a = a.protect_range(align, align, mem_len);
+            let b = b.protect_range(align, align, mem_len);
+
+            overlap(&a, &b)
+        })
+}
+
+fn overlap(a: &Range<usize>, b: &Range<usize>) -> bool {
+    (a.start < b.end) & (b.start < a.end)
+}
+
+/// The protection that grants everything either one does, if there is one without `W^X`
+/// violations.
+fn union(a: SegmentProtection, b: SegmentProtection) -> Option<SegmentProtection> {
+    use self::SegmentProtection::*;
+
+    match (a, b) {
+        (RO, p) | (p, RO) => Some(p),
+        (RW, RW)          => Some(RW),
+        (RX, RX)          => Some(RX),
+        _                 => None,
+    }
+}
+
+
+
+/// A segment of a ready ELF, with its memory ranges in both address spaces.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct LoadedSegment {
+    /// The segment's memory range, in the ready ELF's address space.
+    pub v_range: Range<usize>,
+
+    /// The segment's memory range, in the ELF loader's address space.
+    pub p_range: Range<usize>,
+
+    /// The memory protection requested for the segment.
+    pub protection: SegmentProtection,
+
+    /// What kind of program header the segment stems from.
+    pub kind: SegmentKind,
+
+    /// How many leading bytes of the segment were copied from the ELF buffer.
+    pub file_len: usize,
+
+    /// How many trailing bytes of the segment were zero-filled instead, e.g. for `.bss`.
+    pub zero_fill_len: usize,
+}
+
+/// An iterator over the segments of a ready ELF, in the order their protection was requested.
+///
+/// Unlike `ProtectionMap`, this yields the segments as they are, i.e. possibly overlapping.
+#[derive(Clone)]
+pub struct LoadedSegments<'a> {
+    segs:   slice::Iter<'a, Segment>,
+    p_base: usize,
+    v_base: usize,
+}
+
+impl<'a> LoadedSegments<'a> {
+    pub(crate) fn new(segs: &'a [Segment], p_base: usize, v_base: usize) -> Self {
+        Self { segs: segs.iter(), p_base, v_base }
+    }
+
+    /// The last segment whose `v_range` contains `vaddr`, as later protection requests win.
+    pub(crate) fn segment_at(self, vaddr: usize) -> Option<LoadedSegment> {
+        self.filter(|seg| seg.v_range.contains(&vaddr)).last()
+    }
+}
+
+impl<'a> Iterator for LoadedSegments<'a> {
+    type Item = LoadedSegment;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let seg   = self.segs.next()?;
+        let range = seg.range.to_byte_range();
+
+        let (p_base, v_base) = (self.p_base, self.v_base);
+
+        Some(LoadedSegment {
+            v_range:       v_base.wrapping_add(range.start) .. v_base.wrapping_add(range.end),
+            p_range:       (p_base + range.start) .. (p_base + range.end),
+            protection:    seg.protect,
+            kind:          seg.kind,
+            file_len:      seg.file_len as usize,
+            zero_fill_len: seg.range.len.saturating_sub(seg.file_len) as usize,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.segs.size_hint()
+    }
+}
@@ -1,125 +1,526 @@
 
-use crate::{ LoadedElf, RelocElfError, ProtectFn, SegmentProtection };
+use crate::{
+    LoadedElf, Segment, ProtectionMap, RelocElfError, ProtectFn, ResolveFn, ProgressFn,
+    SegmentProtection, Slice32,
+};
 use crate::elf::{
-    ElfDyn, ElfRel, ElfRela,
-    DT_REL, DT_RELSZ, DT_RELENT, DT_RELA, DT_RELASZ, DT_RELAENT,
-    R_X86_64_NONE, R_X86_64_COPY, R_X86_64_RELATIVE,
-    r_type,
+    ElfClass, ElfDyn, ElfRel, ElfRela, ElfSym,
+    DT_REL, DT_RELA,
+    SHN_ABS, SHN_UNDEF, STN_UNDEF,
+    R_X86_64_NONE, R_X86_64_64, R_X86_64_PC32, R_X86_64_32, R_X86_64_32S, R_X86_64_COPY,
+    R_X86_64_GLOB_DAT, R_X86_64_JUMP_SLOT, R_X86_64_RELATIVE,
+    R_X86_64_DTPMOD64, R_X86_64_DTPOFF64, R_X86_64_TPOFF64, R_X86_64_IRELATIVE,
+    R_AARCH64_NONE, R_AARCH64_ABS64, R_AARCH64_COPY,
+    R_AARCH64_GLOB_DAT, R_AARCH64_JUMP_SLOT, R_AARCH64_RELATIVE,
+    R_RISCV_NONE, R_RISCV_64, R_RISCV_RELATIVE, R_RISCV_COPY, R_RISCV_JUMP_SLOT,
+    STB_WEAK,
+    r_sym, r_type, st_bind,
 };
-use core::{ mem, slice };
+use crate::symbol::SymTabs;
+use crate::dynamic::{ DynInfo, DynTable, parse_dynamic };
+use crate::tls::TlsSegment;
+use crate::batch::{ Protector, request_batch };
+use crate::protect::shares_pages;
+use core::{ mem, ptr, slice };
+use core::ops::Range;
+
+
+
+pub fn try_reloc_elf(
+    elf:      &mut LoadedElf<'_>,
+    base:     *mut u8,
+    prot:     Option<Protector>,
+    resolve:  Option<ResolveFn>,
+    progress: Option<ProgressFn>,
+) -> Result<SymTabs, RelocElfError> {
+    let tabs = reloc_elf_into(elf, base, prot, resolve, progress)?;
+
+    protect_segments(elf, base, prot)?;
+
+    Ok(tabs)
+}
 
+/// Applies all re-locations for `base`, but protects nothing, so that this may be done again.
+pub fn reloc_elf_into(
+    elf:      &mut LoadedElf<'_>,
+    base:     *mut u8,
+    prot:     Option<Protector>,
+    resolve:  Option<ResolveFn>,
+    progress: Option<ProgressFn>,
+) -> Result<SymTabs, RelocElfError> {
+    let mem_base = elf.mem.as_mut_ptr();
+    let res      = reloc_elf(elf, mem_base, base, prot, resolve, progress, false);
+
+    // Even a failed attempt may have overwritten some implicit addends.
+    elf.relocated = true;
+
+    res
+}
+
+/// Scans the loaded `PT_DYNAMIC` segment. Loading does this once, so that re-locating, maybe
+/// many times over, needs not.
+pub fn scan_dynamic(mem: &[u8], dyns: Slice32<ElfDyn>, link_base: u64)
+-> Result<DynInfo, RelocElfError> {
+    // Parsing already bounds-checks all segments against `mem_len`, but `try_slice` does not
+    // check bounds at all, so better be safe than sorry.
+    if !dyns.fits_in(mem.len()) {
+        return Err(RelocElfError::BadDynRange);
+    }
 
+    let dyns = dyns.try_slice(mem, RelocElfError::BadDynAlignment)?;
 
-pub fn try_reloc_elf(elf: &mut LoadedElf<'_>, base: *mut u8, prot: Option<ProtectFn>)
+    Ok(parse_dynamic(dyns.iter().copied()).rebase(link_base))
+}
+
+/// Goes through all the motions of `try_reloc_elf`, without writing to memory, calling any
+/// ifunc resolvers, or protecting anything.
+pub fn validate_reloc_elf(elf: &LoadedElf<'_>, base: *mut u8, resolve: Option<ResolveFn>)
 -> Result<(), RelocElfError> {
-    let base_off = base_to_offset(elf.mem_align(), base)?;
+    // Nothing ever writes through this pointer on a dry run.
+    let mem_base = elf.mem.as_ptr() as *mut u8;
+
+    reloc_elf(elf, mem_base, base, None, resolve, None, true).map(|_| ())
+}
+
+fn reloc_elf(
+    elf:      &LoadedElf<'_>,
+    mem_base: *mut u8,
+    base:     *mut u8,
+    prot:     Option<Protector>,
+    resolve:  Option<ResolveFn>,
+    progress: Option<ProgressFn>,
+    dry:      bool,
+) -> Result<SymTabs, RelocElfError> {
+    // ELF32 data is only ever parsed and loaded, see the `elf32` feature.
+    if elf.class != ElfClass::Elf64 {
+        return Err(RelocElfError::UnsupportedElfClass);
+    }
 
-    relocate_segments(elf, base_off)?;
+    let base_off = base_to_offset(elf.mem_align(), base)?;
 
-    protect_segments(elf, base, prot)
+    match elf.fixed_base {
+        None        => relocate_segments(elf, mem_base, base_off, prot, resolve, progress, dry),
+        Some(fixed) => check_fixed_base(elf, fixed, base_off),
+    }
 }
 
-fn protect_segments(elf: &mut LoadedElf<'_>, v_base: *mut u8, prot: Option<ProtectFn>)
+fn protect_segments(elf: &mut LoadedElf<'_>, v_base: *mut u8, prot: Option<Protector>)
 -> Result<(), RelocElfError> {
-    if let Some(prot) = prot {
-        let p_base  = elf.mem.as_mut_ptr();
-        let mem_len = elf.mem.len();
+    let p_base  = elf.mem.as_mut_ptr();
+    let mem_len = elf.mem.len();
+
+    if let Some(Protector::All(prot)) = prot {
+        // Batched requests must not overlap, or else their order would matter.
+        let map = ProtectionMap::new(elf.protect.as_slice(), elf.mem_align, elf.page_size, mem_len);
+
+        return request_batch(prot, p_base, v_base, mem_len, map);
+    }
+
+    if let Some(Protector::Each(prot)) = prot {
+        // Requests in order would let the last segment sharing a page win, not the union.
+        if elf.coalesce | shares_pages(elf.protect.as_slice(), elf.mem_align, mem_len) {
+            // The map already covers all the memory, including what no segment asks for.
+            let map = ProtectionMap::new(
+                elf.protect.as_slice(), elf.mem_align, elf.page_size, mem_len
+            );
+
+            for (range, protect) in map {
+                request_protection(prot, protect, p_base, v_base, mem_len, range)?;
+            }
+
+            return Ok(());
+        }
 
         // Initial protection request to make everything read-only. This way no unused memory
         // is left with undefined, at worst executable, rights.
-        (prot)(
-            SegmentProtection::RO,
-            p_base, v_base, mem_len,
-            0_usize .. elf.mem.len()
-        ).map_err(|_| RelocElfError::MemProtectFailed)?;
-
-        for seg in &elf.protect.data[..(elf.protect.len as usize)] {
-            (prot)(
-                seg.protect,
-                p_base, v_base, mem_len,
-                seg.range.to_byte_range()
-            ).map_err(|_| RelocElfError::MemProtectFailed)?;
+        request_protection(prot, SegmentProtection::RO, p_base, v_base, mem_len, 0 .. mem_len)?;
+
+        for seg in elf.protect.as_slice() {
+            let range = seg.protect_range(elf.mem_align, elf.page_size, mem_len);
+
+            // `GNU_RELRO` ranges smaller than a page are left alone.
+            if range.is_empty() {
+                continue;
+            }
+
+            request_protection(prot, seg.protect, p_base, v_base, mem_len, range)?;
         }
     }
 
     Ok(())
 }
 
+/// Calls `prot` once, and tells which request failed, if it does.
+pub fn request_protection(
+    prot:    ProtectFn,
+    protect: SegmentProtection,
+    p_base:  *mut u8,
+    v_base:  *mut u8,
+    mem_len: usize,
+    range:   Range<usize>,
+) -> Result<(), RelocElfError> {
+    let (start, end) = (range.start, range.end);
+
+    (prot)(protect, p_base, v_base, mem_len, range)
+        .map_err(|_| RelocElfError::MemProtectFailed { start, end, protect })
+}
+
 fn base_to_offset(align: u32, base: *mut u8) -> Result<usize, RelocElfError> {
     let off = base as usize;
 
-    match off % (align as usize) {
+    // `align` is a power of two, see `Elf::mem_align` and `LoadedElf::try_from_parts`.
+    match off & ((align - 1) as usize) {
         0 =>  Ok(off),
         _ => Err(RelocElfError::BadBaseAddressAlignment),
     }
 }
 
-fn relocate_segments(elf: &mut LoadedElf<'_>, off: usize)
--> Result<(), RelocElfError> {
+/// Fixed-address executables without any dynamic linking info are good to go as they are,
+/// as long as they are placed at their link-time base.
+fn check_fixed_base(elf: &LoadedElf<'_>, fixed: u64, off: usize)
+-> Result<SymTabs, RelocElfError> {
+    if elf.dyns.len != 0 {
+        return Err(RelocElfError::DynamicFixedBase);
+    }
+
+    match (off as u64) == fixed {
+        true  => Ok(SymTabs::default()),
+        false => Err(RelocElfError::BadFixedBase),
+    }
+}
+
+/// Returns the checked symbol-related tables, for later symbol look-ups.
+///
+/// On a dry run, nothing is written through `mem_base`.
+fn relocate_segments(
+    elf:      &LoadedElf<'_>,
+    mem_base: *mut u8,
+    off:      usize,
+    prot:     Option<Protector>,
+    resolve:  Option<ResolveFn>,
+    progress: Option<ProgressFn>,
+    dry:      bool,
+) -> Result<SymTabs, RelocElfError> {
     use self::RelocElfError::*;
 
-    let mem_base      = elf.mem.as_mut_ptr();
-    let mem_len       = elf.mem.len();
-    let dyns          = elf.dyns.try_slice(elf.mem, BadDynAlignment)?;
-    let (rels, relas) = find_rels_and_relas(elf.mem, dyns)?;
+    let mem_len = elf.mem.len();
+    let info    = elf.dyn_info?;
+    let tables  = find_rel_tables(elf.mem, &info)?;
 
-    // FIXME Does the ELF spec say something about "either, or"? Where even is the ELF spec?!
-    for rel  in rels  { apply_rel( rel , mem_base, mem_len, off)?; }
-    for rela in relas { apply_rela(rela, mem_base, mem_len, off)?; }
+    // `Rel` and `Relr` entries keep their addends in the very places the first re-location
+    // overwrote, so only `Rela` entries can be applied again.
+    let implicit = !tables.rels.is_empty() | !tables.plt_rels.is_empty() | !tables.relr.is_empty();
 
-    Ok(())
+    if elf.relocated & implicit {
+        return Err(ImplicitAddends);
+    }
+
+    let tabs   = check_sym_str_tables(mem_len, info.tabs)?;
+    let tls    = elf.tls;
+    let segs   = if elf.flags.text_rel() { &[][..] } else { elf.protect.as_slice() };
+    let lb     = elf.link_base;
+    // `B`, i.e. what to add to link-time addresses, which are relative to the link base.
+    let base   = off.wrapping_sub(lb as usize);
+    let ctx    = RelocCtx {
+        mem_base, mem_len, base, link_base: lb, tabs, tls, resolve, dry, segs,
+    };
+
+    // `DT_RELCOUNT` and `DT_RELACOUNT` promise that this many leading entries are `RELATIVE`
+    // ones. The promise is trusted, so these are just as cheap as `Relr` entries.
+    let (rel_prefix,  rels ) = tables.rels .split_at(tables.rel_count );
+    let (rela_prefix, relas) = tables.relas.split_at(tables.rela_count);
+
+    let mut progress = Progress { report: progress, done: 0, total: tables.len() };
+    let mut place    = 0;
+
+    // `Relr` entries are `RELATIVE` ones in disguise, which don't depend on anything else.
+    progress.each(tables.relr, |relr | apply_relr(relr, &mut place, &ctx))?;
+    progress.each(rel_prefix,  |rels | apply_relative_rels( rels,  &ctx))?;
+    progress.each(rela_prefix, |relas| apply_relative_relas(relas, &ctx))?;
+
+    // The gABI allows for both `Rel` and `Rela` tables, and linkers do mix them, e.g. with
+    // a `Rel` PLT table next to `Rela` data re-locations. Every entry re-locates a place of
+    // its own, so which table goes first does not matter.
+    for table in [rels, tables.plt_rels] {
+        progress.each(table, |rels| rels.iter().try_for_each(|rel| apply_rel(rel, &ctx)))?;
+    }
+
+    // `COPY` and `IRELATIVE` entries count as done right here, even though they come later.
+    for table in [relas, tables.plt_relas] {
+        progress.each(table, |relas| relas.iter()
+            .filter(|r| !is_copy(r) & !is_irelative(r))
+            .try_for_each(|rela| apply_rela(rela, &ctx)))?;
+    }
+
+    let relas = relas.iter().chain(tables.plt_relas);
+
+    // `IRELATIVE` resolvers may well rely on everything else being re-located already.
+    let mut irelas = relas.clone().filter(|r| is_irelative(r)).peekable();
+
+    if irelas.peek().is_some() {
+        if !elf.ifunc { return Err(IfuncDisabled); }
+
+        let segs = elf.protect.as_slice();
+
+        make_code_executable(segs, elf.mem_align, off as *mut u8, prot, &ctx)?;
+
+        for rela in irelas { apply_irelative(rela, segs, &ctx)?; }
+    }
+
+    // `COPY` re-locations read from symbols other re-locations may have to set up first, so
+    // they always come last.
+    for rela in relas.filter(|r| is_copy(r)) { apply_copy(rela, &ctx)?; }
+
+    Ok(tabs)
 }
 
-fn find_rels_and_relas<'a>(mem: &'a [u8], dyns: &'a [ElfDyn])
--> Result<(&'a [ElfRel], &'a [ElfRela]), RelocElfError> {
-    // FIXME move to load?
-    let mut  rel_table_off = 0_u64;
-    let mut  rel_table_len = 0_u64;
-
-    let mut rela_table_off = 0_u64;
-    let mut rela_table_len = 0_u64;
-
-    for d in dyns {
-        match d.d_tag {
-            DT_REL     =>  rel_table_off = d.d_val,
-            DT_RELSZ   =>  rel_table_len = d.d_val,
-            DT_RELENT  => if (mem::size_of::<ElfRel >() as u64) != d.d_val {
-                return Err(RelocElfError::BadRelSize );
-            },
-            DT_RELA    => rela_table_off = d.d_val,
-            DT_RELASZ  => rela_table_len = d.d_val,
-            DT_RELAENT => if (mem::size_of::<ElfRela>() as u64) != d.d_val {
-                return Err(RelocElfError::BadRelaSize);
-            },
-            _ => (), // Other `DT_DYNAMIC` entries are of no interest to us.
+/// Everything a single re-location needs to know about the loaded ELF.
+struct RelocCtx<'s> {
+    mem_base:  *mut u8,
+    mem_len:   usize,
+    base:      usize,
+    // Link-time addresses minus this are offsets into the loaded memory.
+    link_base: u64,
+    tabs:      SymTabs,
+    tls:       Option<TlsSegment>,
+    resolve:   Option<ResolveFn>,
+    dry:       bool,
+    // The segments whose executable ones must not be re-located, i.e. none if the ELF
+    // allows text re-locations.
+    segs:      &'s [Segment],
+}
+
+impl RelocCtx<'_> {
+    /// Writes the re-located value to a bounds-checked place, unless this is a dry run.
+    fn write(&self, r: *mut u64, val: u64) {
+        if !self.dry {
+            unsafe { r.write_unaligned(val) }
         }
     }
 
-    slice_rel_rela(mem, rel_table_off, rel_table_len, rela_table_off, rela_table_len)
+    /// Like `write`, but for 32-bit re-locations, which only ever touch the first four bytes.
+    fn write32(&self, r: *mut u64, val: u32) {
+        if !self.dry {
+            unsafe { (r as *mut u32).write_unaligned(val) }
+        }
+    }
+
+    /// The value `S` of the symbol with the given index.
+    ///
+    /// Symbols this ELF does not define are looked up via the resolver, except for weak ones,
+    /// which may stay undefined with a value of zero. The null symbol resolves to the base `B`,
+    /// as in `ld.so`, i.e. like a local symbol at the start of this ELF. E.g. `R_X86_64_64`
+    /// then yields `B + A`, just like `RELATIVE`, and `R_X86_64_PC32` yields `B + A - P`.
+    fn symbol_value(&self, idx: u32) -> Result<u64, RelocElfError> {
+        if idx == STN_UNDEF {
+            return Ok(self.base as u64);
+        }
+
+        let sym = self.symbol(idx)?;
+
+        match sym.st_shndx {
+            SHN_UNDEF => self.resolve(&sym),
+            SHN_ABS   => Ok(sym.st_value),
+            _         => Ok((self.base as u64).wrapping_add(sym.st_value)),
+        }
+    }
+
+    /// The offset of a TLS symbol within this ELF's TLS block.
+    ///
+    /// There is no way to look up TLS symbols of other modules, so undefined ones fail.
+    fn tls_symbol_offset(&self, idx: u32) -> Result<u64, RelocElfError> {
+        if idx == 0 {
+            return Ok(0);
+        }
+
+        match self.symbol(idx)? {
+            sym if sym.st_shndx == SHN_UNDEF => Err(RelocElfError::UnresolvedSymbol),
+            sym                              => Ok(sym.st_value),
+        }
+    }
+
+    /// A bounds-checked read of the symbol with the given index.
+    fn symbol(&self, idx: u32) -> Result<ElfSym, RelocElfError> {
+        let sym_len = mem::size_of::<ElfSym>() as u64;
+        let sym_end = (idx as u64).checked_mul(sym_len)
+            .and_then(|off| off.checked_add(self.tabs.symtab))
+            .and_then(|off| off.checked_add(sym_len));
+
+        let sym_off = match sym_end {
+            Some(end) if (self.tabs.symtab != 0) & (end <= (self.mem_len as u64)) => end - sym_len,
+            _ => return Err(RelocElfError::BadSymbolIndex),
+        };
+
+        Ok(unsafe { (self.mem_base.add(sym_off as usize) as *const ElfSym).read_unaligned() })
+    }
+
+    fn resolve(&self, sym: &ElfSym) -> Result<u64, RelocElfError> {
+        let addr = match (self.resolve, self.symbol_name(sym)) {
+            (Some(resolve), Some(name)) => (resolve)(name.as_ptr(), name.len()),
+            _                           => ptr::null(),
+        };
+
+        match (addr.is_null(), st_bind(sym.st_info)) {
+            (false, _      ) => Ok(addr as u64),
+            (true, STB_WEAK) => Ok(0),
+            (true, _       ) => Err(RelocElfError::UnresolvedSymbol),
+        }
+    }
+
+    /// The symbol's name, without the terminating NUL.
+    fn symbol_name(&self, sym: &ElfSym) -> Option<&[u8]> {
+        // `check_sym_str_tables` made sure the string table lies within `mem`.
+        let strs = unsafe { slice::from_raw_parts(
+            self.mem_base.add(self.tabs.strtab as usize),
+            self.tabs.strsz as usize
+        )};
+
+        let name = strs.get((sym.st_name as usize)..)?;
+        let len  = name.iter().position(|c| *c == 0)?;
+
+        name.get(..len)
+    }
 }
 
-fn slice_rel_rela(
-    mem: &[u8],
-    rel_off: u64, rel_len: u64,
-    rela_off: u64, rela_len: u64
-)
--> Result<(&[ElfRel], &[ElfRela]), RelocElfError> {
-    let  rel_mem = slice_rel(mem,  rel_off,  rel_len)?;
-    let rela_mem = slice_rel(mem, rela_off, rela_len)?;
+/// How many re-location entries to apply between two calls to a `ProgressFn`.
+const PROGRESS_STEP: usize = 4096;
+
+/// Reports progress every `PROGRESS_STEP` entries, if there is anyone to report to.
+struct Progress {
+    report: Option<ProgressFn>,
+    done:   usize,
+    total:  usize,
+}
+
+impl Progress {
+    /// Applies all `entries`, in chunks small enough to report progress after each.
+    fn each<T, F>(&mut self, entries: &[T], mut apply: F) -> Result<(), RelocElfError>
+    where F: FnMut(&[T]) -> Result<(), RelocElfError> {
+        // Without a callback, there is no need to chop anything up.
+        let report = match self.report {
+            Some(report) => report,
+            None         => return apply(entries),
+        };
+
+        for chunk in entries.chunks(PROGRESS_STEP) {
+            apply(chunk)?;
+
+            self.done += chunk.len();
+            (report)(self.done, self.total);
+        }
+
+        Ok(())
+    }
+}
+
+/// All the re-location tables a `PT_DYNAMIC` segment may point at.
+struct RelTables<'a> {
+    rels:      &'a [ElfRel],
+    relas:     &'a [ElfRela],
+    plt_rels:  &'a [ElfRel],
+    plt_relas: &'a [ElfRela],
+    relr:      &'a [u64],
+
+    // Both are checked to not exceed their table's length.
+    rel_count:  usize,
+    rela_count: usize,
+}
+
+impl RelTables<'_> {
+    /// The total number of entries, of all kinds.
+    fn len(&self) -> usize {
+        self.rels.len() + self.relas.len() + self.plt_rels.len() + self.plt_relas.len()
+            + self.relr.len()
+    }
+}
+
+fn find_rel_tables<'a>(mem: &'a [u8], info: &DynInfo)
+-> Result<RelTables<'a>, RelocElfError> {
+    use self::RelocElfError::*;
+
+    // Entry sizes may well be missing, but if they are there, they must be right.
+    let bad_ent = |ent: Option<u64>, len: usize| ent.is_some_and(|e| e != (len as u64));
+
+    if bad_ent(info.rel_ent,  mem::size_of::<ElfRel >()) { return Err(BadRelSize ); }
+    if bad_ent(info.rela_ent, mem::size_of::<ElfRela>()) { return Err(BadRelaSize); }
+    if bad_ent(info.relr_ent, mem::size_of::<u64    >()) { return Err(BadRelrSize); }
+
+    let DynTable { off: plt_table_off, len: plt_table_len } = info.jmprel;
+
+    // The PLT table holds either `Rel` or `Rela` entries, as told by `DT_PLTREL`.
+    let (plt_rel_off, plt_rela_off, plt_ent_len) = match info.plt_rel.unwrap_or(DT_RELA) {
+        DT_REL  => (plt_table_off, 0, mem::size_of::<ElfRel >()),
+        DT_RELA => (0, plt_table_off, mem::size_of::<ElfRela>()),
+        _       => return Err(RelocElfError::BadPltSize),
+    };
+
+    if !plt_table_len.is_multiple_of(plt_ent_len as u64) {
+        return Err(RelocElfError::BadPltSize);
+    }
+
+    let rels  = slice_rel::<ElfRel >(mem, info.rel .off, info.rel .len)?;
+    let relas = slice_rel::<ElfRela>(mem, info.rela.off, info.rela.len)?;
+
+    if (info.rel_count > (rels.len() as u64)) | (info.rela_count > (relas.len() as u64)) {
+        return Err(RelocElfError::BadRelCount);
+    }
+
+    Ok(RelTables {
+        rels, relas,
+        rel_count:  info.rel_count  as usize,
+        rela_count: info.rela_count as usize,
+        plt_rels:  slice_rel(mem,    plt_rel_off,  plt_table_len)
+                       .map_err(|_| RelocElfError::BadPltRange)?,
+        plt_relas: slice_rel(mem,   plt_rela_off,  plt_table_len)
+                       .map_err(|_| RelocElfError::BadPltRange)?,
+        relr:      slice_rel(mem, info.relr.off, info.relr.len)
+                       .map_err(|_| RelocElfError::BadRelrRange)?,
+    })
+}
+
+/// Symbol look-ups trust these tables to be in bounds, so better check them once, up front.
+///
+/// Returns the tables' offsets, which are zero if there are none.
+fn check_sym_str_tables(mem_len: usize, mut tabs: SymTabs) -> Result<SymTabs, RelocElfError> {
+    let mem_len = mem_len as u64;
+
+    let strtab_end = tabs.strtab.checked_add(tabs.strsz);
+
+    if (tabs.strtab != 0) && strtab_end.map(|end| end > mem_len).unwrap_or(true) {
+        return Err(RelocElfError::BadStrTabRange);
+    }
+
+    if tabs.symtab != 0 {
+        let sym_len = mem::size_of::<ElfSym>() as u64;
+
+        // There is no size tag for the symbol table, but it holds at least the null symbol.
+        if tabs.symtab.checked_add(sym_len).map(|end| end > mem_len).unwrap_or(true) {
+            return Err(RelocElfError::BadSymTabRange);
+        }
+
+        if !tabs.symtab.is_multiple_of(mem::align_of::<ElfSym>() as u64) {
+            return Err(RelocElfError::BadSymTabRange);
+        }
+    }
 
-    Ok((rel_mem, rela_mem))
+    // A string table without a size is just an empty one.
+    if tabs.strtab == 0 {
+        tabs.strsz = 0;
+    }
+
+    Ok(tabs)
 }
 
 fn slice_rel<T: Sized>(mem: &[u8], off: u64, len: u64) -> Result<&[T], RelocElfError> {
     if off == 0 { return Ok(&[]); }
 
-    if off.checked_add(len).map(|end| end >= (mem.len() as u64)).unwrap_or(true) {
+    if off.checked_add(len).map(|end| end > (mem.len() as u64)).unwrap_or(true) {
         return Err(RelocElfError::BadRelRelaTableRange);
     }
 
-    let addr = (&mem[(off as usize)..]).as_ptr() as *const T;
+    let addr = mem.as_ptr().wrapping_add(off as usize) as *const T;
 
-    if 0 != ((addr as usize) % mem::align_of::<T>()) {
+    if !(addr as usize).is_multiple_of(mem::align_of::<T>()) {
         return Err(RelocElfError::BadRelRelaTableAlignment);
     }
 
@@ -141,35 +542,351 @@ fn slice_rel<T: Sized>(mem: &[u8], off: u64, len: u64) -> Result<&[T], RelocElfE
 // - Z:        ?
 // - indirect: ?
 
-fn apply_rel(rel: &ElfRel, mem_base: *mut u8, mem_len: usize, base: usize)
--> Result<(), RelocElfError> {
-    // Pretty much TODO here.
-    let _ = (rel, mem_base, mem_len, base); // shut up, linter
-    Err(RelocElfError::UnsupportedRelArch)
+/// Decodes a `Relr` table, adding the base address to each place it lists.
+///
+/// An even entry is the offset of a place to re-locate. Each odd entry following it is a
+/// bitmap, whose bits 1 to 63 mark which of the next 63 words to re-locate. `place` carries
+/// the next place over from one chunk of the table to the next.
+fn apply_relr(relr: &[u64], place: &mut u64, ctx: &RelocCtx) -> Result<(), RelocElfError> {
+    let b    = ctx.base as u64;
+    let word = mem::size_of::<u64>() as u64;
+
+    let relocate = |off: u64| -> Result<(), RelocElfError> {
+        let r = reloc_target(off, 8, ctx, RelocElfError::BadRelrRange)?;
+        ctx.write(r, unsafe { r.read_unaligned() }.wrapping_add(b));
+        Ok(())
+    };
+
+    for &entry in relr {
+        if (entry & 1) == 0 {
+            relocate(entry)?;
+            *place = entry.wrapping_add(word);
+            continue;
+        }
+
+        for bit in 1..64 {
+            if (entry >> bit) & 1 != 0 {
+                relocate(place.wrapping_add((bit - 1) * word))?;
+            }
+        }
+
+        *place = place.wrapping_add(63 * word);
+    }
+
+    Ok(())
+}
+
+/// Applies `Rel` entries known to be `RELATIVE` ones, without looking at their type.
+fn apply_relative_rels(rels: &[ElfRel], ctx: &RelocCtx) -> Result<(), RelocElfError> {
+    // Keep failing with `UnsupportedRelArch` where `apply_rel` would.
+    if !cfg!(target_arch = "x86_64") {
+        return rels.iter().try_for_each(|rel| apply_rel(rel, ctx));
+    }
+
+    let b = ctx.base as u64;
+
+    for rel in rels {
+        let r = reloc_target(rel.r_offset, 8, ctx, RelocElfError::BadRelOffset)?;
+        ctx.write(r, unsafe { r.read_unaligned() }.wrapping_add(b));
+    }
+
+    Ok(())
+}
+
+/// Applies `Rela` entries known to be `RELATIVE` ones, without looking at their type.
+fn apply_relative_relas(relas: &[ElfRela], ctx: &RelocCtx) -> Result<(), RelocElfError> {
+    // Keep failing with `UnsupportedRelaArch` where `apply_rela` would.
+    if !cfg!(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "riscv64")) {
+        return relas.iter().try_for_each(|rela| apply_rela(rela, ctx));
+    }
+
+    let b = ctx.base as u64;
+
+    for rela in relas {
+        let r = reloc_target(rela.r_offset, 8, ctx, RelocElfError::BadRelaOffset)?;
+        ctx.write(r, b.wrapping_add(rela.r_addend as u64));
+    }
+
+    Ok(())
+}
+
+fn apply_rel(rel: &ElfRel, ctx: &RelocCtx) -> Result<(), RelocElfError> {
+    let reloc_this = reloc_target(rel.r_offset, 8, ctx, RelocElfError::BadRelOffset)?;
+
+    if cfg!(target_arch = "x86_64") { apply_rel_x86_64(reloc_this, rel, ctx) }
+    else { Err(RelocElfError::UnsupportedRelArch) }
+}
+
+fn apply_rela(rela: &ElfRela, ctx: &RelocCtx) -> Result<(), RelocElfError> {
+    let width      = place_width(rela.r_info);
+    let reloc_this = reloc_target(rela.r_offset, width, ctx, RelocElfError::BadRelaOffset)?;
+
+    if      cfg!(target_arch = "x86_64" ) { apply_rela_x86_64( reloc_this, rela, ctx) }
+    else if cfg!(target_arch = "aarch64") { apply_rela_aarch64(reloc_this, rela, ctx) }
+    else if cfg!(target_arch = "riscv64") { apply_rela_riscv64(reloc_this, rela, ctx) }
+    else { Err(RelocElfError::UnsupportedRelaArch) }
+}
+
+/// Bounds-checks the place to re-locate, which must fit `width` bytes, and fails with `oob`
+/// if it does not. Places within executable segments are text re-locations.
+fn reloc_target(addr: u64, width: u64, ctx: &RelocCtx, oob: RelocElfError)
+-> Result<*mut u64, RelocElfError> {
+    let off = addr.checked_sub(ctx.link_base).ok_or(oob)?;
+    let end = off.checked_add(width)
+        .filter(|end| *end <= (ctx.mem_len as u64))
+        .ok_or(oob)? as usize;
+
+    let text = ctx.segs.iter()
+        .filter(|seg| seg.protect == SegmentProtection::RX)
+        .map(|seg| seg.range.to_byte_range())
+        .any(|range| ((off as usize) < range.end) & (end > range.start));
+
+    if text {
+        return Err(RelocElfError::TextRelocation);
+    }
+
+    Ok(ctx.mem_base.wrapping_add(off as usize) as *mut u64)
+}
+
+/// How many bytes a `Rela` entry's place spans. Only the small code model's re-locations
+/// write less than a whole `u64`.
+fn place_width(info: u64) -> u64 {
+    match r_type(info) {
+        R_X86_64_PC32 | R_X86_64_32 | R_X86_64_32S if cfg!(target_arch = "x86_64") => 4,
+        _ => 8,
+    }
+}
+
+fn is_irelative(rela: &ElfRela) -> bool {
+    cfg!(target_arch = "x86_64") && (r_type(rela.r_info) == R_X86_64_IRELATIVE)
+}
+
+/// Applies the final protection of all executable segments ahead of time, so that `IRELATIVE`
+/// resolvers can run. Everything else stays writable until `protect_segments`.
+fn make_code_executable(
+    segs:   &[Segment],
+    align:  u32,
+    v_base: *mut u8,
+    prot:   Option<Protector>,
+    ctx:    &RelocCtx,
+) -> Result<(), RelocElfError> {
+    // Without `GNU_RELRO` ranges, the page size does not matter.
+    let code = segs.iter().filter(|seg| seg.protect == SegmentProtection::RX);
+
+    if let Some(Protector::All(prot)) = prot {
+        let requests = code.map(|seg| (seg.protect_range(align, align, ctx.mem_len), seg.protect));
+
+        return request_batch(prot, ctx.mem_base, v_base, ctx.mem_len, requests);
+    }
+
+    if let Some(Protector::Each(prot)) = prot {
+        for seg in code {
+            let range = seg.protect_range(align, align, ctx.mem_len);
+
+            request_protection(
+                prot, SegmentProtection::RX,
+                ctx.mem_base, v_base, ctx.mem_len,
+                range
+            )?;
+        }
+    }
+
+    Ok(())
 }
 
-fn apply_rela(rela: &ElfRela, mem_base: *mut u8, mem_len: usize, base: usize)
+/// Calls the resolver at `B + A`, and stores the address it returns.
+fn apply_irelative(rela: &ElfRela, segs: &[Segment], ctx: &RelocCtx)
 -> Result<(), RelocElfError> {
-    if rela.r_offset >= (mem_len as u64) {
+    use self::RelocElfError::*;
+
+    // Resolvers run right here, so they can only return addresses of this address space.
+    if ctx.base.wrapping_add(ctx.link_base as usize) != (ctx.mem_base as usize) {
+        return Err(IfuncBaseMismatch);
+    }
+
+    let r     = reloc_target(rela.r_offset, 8, ctx, BadRelaOffset)?;
+    let place = rela.r_offset.wrapping_sub(ctx.link_base);
+    let off   = (rela.r_addend as u64).wrapping_sub(ctx.link_base);
+
+    // Code just got executable, so writing to it would fault.
+    let in_code = |at: u64| segs.iter()
+        .filter(|seg| seg.protect == SegmentProtection::RX)
+        .any(|seg| seg.range.to_byte_range().contains(&(at as usize)));
+
+    if in_code(place) | !in_code(off) {
+        return Err(BadRelaOffset);
+    }
+
+    // A dry run must not run any of the ELF's code.
+    if ctx.dry {
+        return Ok(());
+    }
+
+    let resolver: extern "C" fn() -> u64 = unsafe {
+        mem::transmute(ctx.mem_base.wrapping_add(off as usize))
+    };
+
+    ctx.write(r, (resolver)());
+
+    Ok(())
+}
+
+fn is_copy(rela: &ElfRela) -> bool {
+    let ty = r_type(rela.r_info);
+
+    (cfg!(target_arch = "x86_64" ) && (ty == R_X86_64_COPY ))
+  | (cfg!(target_arch = "aarch64") && (ty == R_AARCH64_COPY))
+  | (cfg!(target_arch = "riscv64") && (ty == R_RISCV_COPY  ))
+}
+
+/// Copies `st_size` bytes of a symbol some other object defines to the place, as is done for
+/// global variables an executable uses but doesn't define.
+///
+/// As the point is to copy from *another* object, the symbol is always looked up via the
+/// resolver, whose address must thus be readable by the ELF loader.
+fn apply_copy(rela: &ElfRela, ctx: &RelocCtx) -> Result<(), RelocElfError> {
+    let sym = ctx.symbol(r_sym(rela.r_info))?;
+    let dst = reloc_target(rela.r_offset, sym.st_size, ctx, RelocElfError::BadRelaOffset)?;
+
+    let src = match (ctx.resolve, ctx.symbol_name(&sym)) {
+        (Some(resolve), Some(name)) => (resolve)(name.as_ptr(), name.len()) as *const u8,
+        _                           => ptr::null(),
+    };
+
+    // Weak or not, there is nothing to copy from a symbol that isn't there.
+    if src.is_null() {
+        return Err(RelocElfError::UnresolvedSymbol);
+    }
+
+    let len = sym.st_size as usize;
+
+    if (src as usize).checked_add(len).is_none() {
         return Err(RelocElfError::BadRelaOffset);
     }
 
-    let reloc_this = mem_base.wrapping_add(rela.r_offset as usize) as *mut u64;
-    let reloc_ty   = r_type(rela.r_info);
-    let a          = rela.r_addend as u64;
-    let b          = base as u64;
+    if !ctx.dry {
+        unsafe { ptr::copy(src, dst as *mut u8, len) }
+    }
 
-    if cfg!(target_arch = "x86_64") { apply_rela_x86_64(reloc_this, reloc_ty, a, b) }
-    else { Err(RelocElfError::UnsupportedRelaArch) }
+    Ok(())
+}
+
+fn apply_rel_x86_64(r: *mut u64, rel: &ElfRel, ctx: &RelocCtx) -> Result<(), RelocElfError> {
+    // `Rel` entries have no addend of their own, it's stored in the place to re-locate.
+    let a = unsafe { r.read_unaligned() };
+
+    apply_x86_64(r, rel.r_info, a, ctx, RelocElfError::UnsupportedRelType)
+}
+
+fn apply_rela_x86_64(r: *mut u64, rela: &ElfRela, ctx: &RelocCtx) -> Result<(), RelocElfError> {
+    let a = rela.r_addend as u64;
+
+    // The small code model's 32-bit re-locations, which must not silently truncate.
+    let small = |val: u64, fits: bool| match fits {
+        true  => { ctx.write32(r, val as u32); Ok(()) },
+        false => Err(RelocElfError::RelocationOverflow),
+    };
+
+    match r_type(rela.r_info) {
+        | R_X86_64_PC32 => {
+            let p   = (ctx.base as u64).wrapping_add(rela.r_offset);
+            let val = ctx.symbol_value(r_sym(rela.r_info))?.wrapping_add(a).wrapping_sub(p);
+            small(val, fits_i32(val))
+        },
+        | R_X86_64_32 => {
+            let val = ctx.symbol_value(r_sym(rela.r_info))?.wrapping_add(a);
+            small(val, val <= u64::from(u32::MAX))
+        },
+        | R_X86_64_32S => {
+            let val = ctx.symbol_value(r_sym(rela.r_info))?.wrapping_add(a);
+            small(val, fits_i32(val))
+        },
+        _ => apply_x86_64(r, rela.r_info, a, ctx, RelocElfError::UnsupportedRelaType),
+    }
 }
 
-#[cfg(target_arch = "x86_64")]
-fn apply_rela_x86_64(r: *mut u64, ty: u32, a: u64, b: u64) -> Result<(), RelocElfError> {
-    match ty {
-        | R_X86_64_COPY
+/// Whether the two's complement `val` survives being truncated to an `i32`.
+fn fits_i32(val: u64) -> bool {
+    (val as i64) == i64::from(val as i32)
+}
+
+/// Applies a re-location given its addend, no matter where that one came from.
+fn apply_x86_64(r: *mut u64, info: u64, a: u64, ctx: &RelocCtx, unsupported: RelocElfError)
+-> Result<(), RelocElfError> {
+    let b = ctx.base as u64;
+
+    match r_type(info) {
         | R_X86_64_NONE => (),
 
-        | R_X86_64_RELATIVE => unsafe { r.write_unaligned(a.wrapping_add(b)) },
+        | R_X86_64_RELATIVE => ctx.write(r, a.wrapping_add(b)),
+
+        | R_X86_64_64
+        | R_X86_64_GLOB_DAT
+        | R_X86_64_JUMP_SLOT => {
+            let s = ctx.symbol_value(r_sym(info))?;
+            ctx.write(r, s.wrapping_add(a))
+        },
+
+        // This ELF is the one and only module with a TLS block, hence module ID 1.
+        | R_X86_64_DTPMOD64 => ctx.write(r, 1),
+
+        | R_X86_64_DTPOFF64 => {
+            let s = ctx.tls_symbol_offset(r_sym(info))?;
+            ctx.write(r, s.wrapping_add(a))
+        },
+
+        // The TLS block sits right below the thread pointer, as if this ELF was the main
+        // executable, leaving any other static TLS to whoever sets up the threads.
+        | R_X86_64_TPOFF64 => {
+            let tls = ctx.tls.ok_or(RelocElfError::NoTlsSegment)?;
+            let s   = ctx.tls_symbol_offset(r_sym(info))?;
+            ctx.write(r, s.wrapping_add(a).wrapping_sub(tls.block_len()))
+        },
+
+        _ => return Err(unsupported),
+    }
+
+    Ok(())
+}
+
+fn apply_rela_aarch64(r: *mut u64, rela: &ElfRela, ctx: &RelocCtx) -> Result<(), RelocElfError> {
+    let a = rela.r_addend as u64;
+    let b = ctx.base as u64;
+
+    match r_type(rela.r_info) {
+        | R_AARCH64_NONE => (),
+
+        // `Delta(S) + A`, where `Delta(S)` is just the base address for the null symbol.
+        | R_AARCH64_RELATIVE => ctx.write(r, a.wrapping_add(b)),
+
+        | R_AARCH64_ABS64
+        | R_AARCH64_GLOB_DAT
+        | R_AARCH64_JUMP_SLOT => {
+            let s = ctx.symbol_value(r_sym(rela.r_info))?;
+            ctx.write(r, s.wrapping_add(a))
+        },
+
+        _ => return Err(RelocElfError::UnsupportedRelaType),
+    }
+
+    Ok(())
+}
+
+fn apply_rela_riscv64(r: *mut u64, rela: &ElfRela, ctx: &RelocCtx) -> Result<(), RelocElfError> {
+    let a = rela.r_addend as u64;
+    let b = ctx.base as u64;
+
+    match r_type(rela.r_info) {
+        | R_RISCV_NONE => (),
+
+        // `B + A`, just like on the other architectures.
+        | R_RISCV_RELATIVE => ctx.write(r, a.wrapping_add(b)),
+
+        | R_RISCV_64
+        | R_RISCV_JUMP_SLOT => {
+            let s = ctx.symbol_value(r_sym(rela.r_info))?;
+            ctx.write(r, s.wrapping_add(a))
+        },
 
         _ => return Err(RelocElfError::UnsupportedRelaType),
     }
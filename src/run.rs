@@ -0,0 +1,133 @@
+use crate::ReadyElf;
+use crate::elf::ElfProgramHeader;
+use alloc::vec::Vec;
+use core::ffi::CStr;
+use core::arch::asm;
+use core::arch::x86_64::_rdtsc;
+use core::mem;
+
+
+
+/// Size of the stack the entry function runs on, in bytes.
+const STACK_LEN: usize = 1 << 20;
+
+const AT_NULL:   u64 =  0;
+const AT_PHDR:   u64 =  3;
+const AT_PHENT:  u64 =  4;
+const AT_PHNUM:  u64 =  5;
+const AT_PAGESZ: u64 =  6;
+const AT_BASE:   u64 =  7;
+const AT_ENTRY:  u64 =  9;
+const AT_RANDOM: u64 = 25;
+
+/// Size of the `AT_RANDOM` bytes, in `u64` steps.
+const RANDOM_LEN: usize = 2;
+
+
+
+pub unsafe fn run_main(elf: &ReadyElf<'_>, args: &[&CStr], env: &[&CStr]) -> ! {
+    let base  = elf.p_mem().as_ptr() as u64;
+    let entry = elf.p_entry()        as u64;
+    let phent = mem::size_of::<ElfProgramHeader>() as u64;
+
+    // There is no interpreter, so `AT_BASE` is zero.
+    let mut aux = Vec::with_capacity(6);
+    aux.extend([(AT_PAGESZ, elf.page_size as u64), (AT_BASE, 0), (AT_ENTRY, entry)]);
+
+    if let Some(phdrs) = elf.program_header_range() {
+        let phdr  = base + (phdrs.start as u64);
+        let phnum = (phdrs.len() as u64) / phent;
+
+        aux.extend([(AT_PHDR, phdr), (AT_PHENT, phent), (AT_PHNUM, phnum)]);
+    }
+
+    // This function never returns, so the stack is never freed.
+    let mut stack = Vec::new();
+    let sp        = build_process_stack(&mut stack, &aux, args, env);
+
+    asm!(
+        "mov rsp, {sp}",
+        "xor ebp, ebp",
+        "jmp {entry}",
+        sp    = in(reg) sp,
+        entry = in(reg) entry,
+        // There is no `rtld_fini` for the entry function to register with `atexit`.
+        in("rdx") 0,
+        options(noreturn),
+    )
+}
+
+/// Lays out `argc`, `argv`, `envp`, `auxv`, and all the strings they point to at the top of
+/// a fresh stack, as mandated by the SysV ABI for the initial process stack. `aux` is
+/// completed by `AT_RANDOM` and `AT_NULL`.
+///
+/// Returns a 16-byte aligned pointer to `argc`.
+fn build_process_stack(
+    stack: &mut Vec<u64>,
+    aux:   &[(u64, u64)],
+    args:  &[&CStr],
+    env:   &[&CStr],
+) -> *mut u64 {
+    let words   = |s: &&CStr| s.to_bytes_with_nul().len().div_ceil(8);
+    let str_len = args.iter().chain(env).map(words).sum::<usize>() + RANDOM_LEN;
+    let vec_len = 1 + (args.len() + 1) + (env.len() + 1) + ((aux.len() + 2) * 2);
+
+    // One spare word to fix up the alignment of `argc`.
+    stack.resize((STACK_LEN / 8) + vec_len + str_len + 1, 0);
+
+    let addr    = stack.as_ptr() as u64;
+    let mut sp  = stack.len() - vec_len - str_len - 1;
+
+    if !(addr + (sp as u64 * 8)).is_multiple_of(16) { sp -= 1; }
+
+    // Vectors and strings are gathered apart, and then copied to the top of the stack.
+    let strs_at   = sp + vec_len;
+    let mut vecs  = Vec::with_capacity(vec_len);
+    let mut strs  = Vec::with_capacity(str_len);
+
+    vecs.push(args.len() as u64);
+
+    for list in [args, env] {
+        for s in list {
+            vecs.push(addr + ((strs_at + strs.len()) as u64 * 8));
+
+            strs.extend(s.to_bytes_with_nul().chunks(8).map(|chunk| {
+                let mut w = [0_u8; 8];
+                w.iter_mut().zip(chunk).for_each(|(w, c)| *w = *c);
+                u64::from_ne_bytes(w)
+            }));
+        }
+
+        vecs.push(0);
+    }
+
+    let random = addr + ((strs_at + strs.len()) as u64 * 8);
+    strs.extend(random_words(addr));
+
+    for &(key, val) in aux.iter().chain(&[(AT_RANDOM, random), (AT_NULL, 0)]) {
+        vecs.push(key);
+        vecs.push(val);
+    }
+
+    stack.iter_mut().skip(sp)
+        .zip(vecs.into_iter().chain(strs))
+        .for_each(|(slot, word)| *slot = word);
+
+    unsafe { stack.as_mut_ptr().add(sp) }
+}
+
+/// The `AT_RANDOM` bytes, which e.g. seed stack protectors. They are unpredictable enough for
+/// that, but by no means of cryptographic quality.
+fn random_words(seed: u64) -> [u64; RANDOM_LEN] {
+    // SplitMix64, seeded with the time stamp counter.
+    let mut x    = seed ^ unsafe { _rdtsc() };
+    let mut next = || {
+        x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let z = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        let z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+
+    [next(), next()]
+}
@@ -0,0 +1,138 @@
+
+use crate::elf::{ ElfFileHeader, ElfFormat, SHT_NOBITS };
+use crate::ParseElfError;
+use core::convert::TryFrom;
+use core::str;
+
+
+
+/// An iterator over the ELF data's section headers.
+///
+/// Section headers play no role in loading an ELF, but tell tools where to find things like
+/// `.text` or `.symtab`. Extended section numbering, i.e. more than `0xFF00` sections, is not
+/// supported.
+#[derive(Clone)]
+pub struct SectionHeaders<'a> {
+    table:    &'a [u8],
+    format:   ElfFormat,
+    raw:      &'a [u8],
+    shstrtab: &'a [u8],
+}
+
+impl<'a> SectionHeaders<'a> {
+    /// No section headers at all, for ELFs whose section header table is broken.
+    pub(crate) fn empty(format: ElfFormat) -> Self {
+        Self { table: &[], format, raw: &[], shstrtab: &[] }
+    }
+
+    /// Bounds-checks the section header table, and finds the section name string table.
+    pub(crate) fn try_new(hdr: &ElfFileHeader, format: ElfFormat, raw: &'a [u8])
+    -> Result<Self, ParseElfError> {
+        let sh_len = format.class.sh_len();
+
+        // Stripped ELFs may well have no section headers at all.
+        if hdr.e_shnum == 0 {
+            return Ok(Self { table: &[], format, raw, shstrtab: &[] });
+        }
+
+        if (hdr.e_shentsize as usize) != sh_len {
+            return Err(ParseElfError::BadSectionHeaderSize);
+        }
+
+        let hoff = hdr.e_shoff;
+        let len  = (sh_len as u64) * (hdr.e_shnum as u64);
+
+        // Unlike program headers, section headers usually end right at the end of the file.
+        if len.checked_add(hoff)
+              .map(|x| x > (raw.len() as u64))
+              .unwrap_or(true) {
+            return Err(ParseElfError::SectionHeaderOverflow);
+        }
+
+        let table = raw.get((hoff as usize)..((hoff + len) as usize))
+            .ok_or(ParseElfError::SectionHeaderOverflow)?;
+
+        // A broken name string table just leaves all sections nameless.
+        let shstrtab = table.get(((hdr.e_shstrndx as usize) * sh_len)..)
+            .and_then(|sh| format.read_sh(sh))
+            .and_then(|sh| file_bytes(raw, sh.sh_offset, sh.sh_size))
+            .unwrap_or(&[]);
+
+        Ok(Self { table, format, raw, shstrtab })
+    }
+}
+
+impl<'a> Iterator for SectionHeaders<'a> {
+    type Item = SectionHeader<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sh = self.format.read_sh(self.table)?;
+
+        self.table = self.table.get(self.format.class.sh_len()..).unwrap_or(&[]);
+
+        Some(SectionHeader {
+            name:   str_at(self.shstrtab, sh.sh_name),
+            kind:   sh.sh_type,
+            flags:  sh.sh_flags,
+            addr:   sh.sh_addr,
+            offset: sh.sh_offset,
+            size:   sh.sh_size,
+            data:   match sh.sh_type {
+                SHT_NOBITS => None,
+                _          => file_bytes(self.raw, sh.sh_offset, sh.sh_size),
+            },
+        })
+    }
+}
+
+
+
+/// An ELF section header.
+#[derive(Copy, Clone, Debug)]
+pub struct SectionHeader<'a> {
+    /// The section's name, like `.text`, if it can be found in the name string table.
+    pub name: Option<&'a str>,
+
+    /// The section's type, i.e. `sh_type`, like `SHT_SYMTAB`.
+    pub kind: u32,
+
+    /// The section's `SHF_*` flags.
+    pub flags: u64,
+
+    /// The section's virtual address, if it is loaded at all.
+    pub addr: u64,
+
+    /// The section's offset into the ELF data.
+    pub offset: u64,
+
+    /// The section's size, in bytes.
+    pub size: u64,
+
+    data: Option<&'a [u8]>,
+}
+
+impl<'a> SectionHeader<'a> {
+    /// The section's contents within the ELF data.
+    ///
+    /// Returns `None` for sections that occupy no file space at all, like `.bss`, and for
+    /// sections that lie out of the ELF data's bounds.
+    pub fn data(&self) -> Option<&'a [u8]> {
+        self.data
+    }
+}
+
+
+
+fn file_bytes(raw: &[u8], off: u64, len: u64) -> Option<&[u8]> {
+    let start = usize::try_from(off).ok()?;
+    let end   = usize::try_from(off.checked_add(len)?).ok()?;
+
+    raw.get(start..end)
+}
+
+fn str_at(strs: &[u8], off: u32) -> Option<&str> {
+    let name = strs.get((off as usize)..)?;
+    let len  = name.iter().position(|c| *c == 0)?;
+
+    str::from_utf8(name.get(..len)?).ok()
+}
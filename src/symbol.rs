@@ -0,0 +1,448 @@
+
+use crate::elf::{
+    ElfSym,
+    SHN_ABS, SHN_UNDEF,
+    STB_LOCAL, STB_GLOBAL, STB_WEAK, STB_GNU_UNIQUE,
+    STT_NOTYPE, STT_OBJECT, STT_FUNC, STT_SECTION, STT_FILE, STT_COMMON, STT_TLS, STT_GNU_IFUNC,
+    st_bind, st_type,
+};
+use crate::version::{ is_hidden, version_name };
+use core::{ mem, ptr, str };
+
+
+
+/// Where to find the symbol-related tables within the loaded ELF's memory.
+///
+/// Offsets are zero for absent tables.
+#[derive(Copy, Clone, Default)]
+pub struct SymTabs {
+    pub symtab:   u64,
+    pub strtab:   u64,
+    pub strsz:    u64,
+    pub gnu_hash: u64,
+    pub hash:     u64,
+    pub versym:     u64,
+    pub verdef:     u64,
+    pub verdefnum:  u64,
+    pub verneed:    u64,
+    pub verneednum: u64,
+    /// What `st_value`s are relative to, see `Elf::link_base`.
+    pub link_base:  u64,
+}
+
+/// Looks up a defined symbol by name, returning its offset within the loaded ELF's memory.
+///
+/// Prefers the `DT_GNU_HASH` table, and falls back to the classic `DT_HASH` table if there is
+/// no usable `DT_GNU_HASH` table. Every single read is bounds-checked, as none of the tables
+/// are trusted.
+///
+/// Without a `version`, this finds the default version of a symbol, i.e. skips hidden ones.
+/// With a `version`, this finds the symbol of exactly that version, hidden or not.
+pub fn lookup(mem: &[u8], tabs: &SymTabs, name: &str, version: Option<&str>)
+-> Option<usize> {
+    if (tabs.symtab == 0) | (tabs.strtab == 0) {
+        return None;
+    }
+
+    // Table offsets within `mem` keep all the offset arithmetic below from overflowing.
+    let in_mem = |off: u64| off < (mem.len() as u64);
+
+    if !in_mem(tabs.symtab) {
+        return None;
+    }
+
+    // A `DT_GNU_HASH` table out of bounds is as good as none at all, which leaves `DT_HASH`.
+    let has = |off: u64| (off != 0) & in_mem(off);
+
+    let accept = |idx: u64| match version {
+        None      => !is_hidden(mem, tabs, idx),
+        Some(ver) => version_name(mem, tabs, idx) == Some(ver),
+    };
+
+    let sym = match (has(tabs.gnu_hash), has(tabs.hash)) {
+        (true,  _    ) => gnu_hash_lookup( mem, tabs, name.as_bytes(), accept)?,
+        (false, true ) => sysv_hash_lookup(mem, tabs, name.as_bytes(), accept)?,
+        (false, false) => return None,
+    };
+
+    if sym.st_shndx == SHN_UNDEF {
+        return None;
+    }
+
+    let off = sym.st_value.wrapping_sub(tabs.link_base);
+
+    if off >= (mem.len() as u64) {
+        return None;
+    }
+
+    Some(off as usize)
+}
+
+/// Walks the hash chain of `name`, up to the first symbol of that name that `accept`s its index.
+fn gnu_hash_lookup<F>(mem: &[u8], tabs: &SymTabs, name: &[u8], accept: F) -> Option<ElfSym>
+where F: Fn(u64) -> bool {
+    let hdr         = tabs.gnu_hash;
+    let nbuckets    = read::<u32>(mem, hdr     )? as u64;
+    let symoffset   = read::<u32>(mem, hdr +  4)? as u64;
+    let bloom_size  = read::<u32>(mem, hdr +  8)? as u64;
+    let bloom_shift = read::<u32>(mem, hdr + 12)?;
+
+    if (nbuckets == 0) | (bloom_size == 0) {
+        return None;
+    }
+
+    let bloom   = hdr + 16;
+    let buckets = bloom   + (bloom_size * 8);
+    let chains  = buckets + (nbuckets   * 4);
+    let hash    = gnu_hash(name);
+
+    // The bloom filter says "no" for most symbols that aren't there, saving us from walking
+    // any hash chains at all. Two bits derived from the hash must both be set.
+    let word = read::<u64>(mem, bloom + (((hash / 64) as u64 % bloom_size) * 8))?;
+    let mask = (1_u64 << (hash % 64)) | (1_u64 << (hash.wrapping_shr(bloom_shift) % 64));
+
+    if (word & mask) != mask {
+        return None;
+    }
+
+    let mut idx = read::<u32>(mem, buckets + ((hash as u64 % nbuckets) * 4))? as u64;
+
+    if idx < symoffset {
+        return None; // Empty bucket, or just broken.
+    }
+
+    loop {
+        let chain_hash = read::<u32>(mem, chains + ((idx - symoffset) * 4))?;
+
+        if (chain_hash | 1) == (hash | 1) {
+            let sym = read::<ElfSym>(mem, tabs.symtab + (idx * mem::size_of::<ElfSym>() as u64))?;
+
+            if sym_name_eq(mem, tabs, &sym, name) && accept(idx) {
+                return Some(sym);
+            }
+        }
+
+        // The lowest bit marks the end of a chain.
+        if (chain_hash & 1) != 0 {
+            return None;
+        }
+
+        idx += 1;
+    }
+}
+
+fn sysv_hash_lookup<F>(mem: &[u8], tabs: &SymTabs, name: &[u8], accept: F) -> Option<ElfSym>
+where F: Fn(u64) -> bool {
+    let hdr     = tabs.hash;
+    let nbucket = read::<u32>(mem, hdr    )? as u64;
+    let nchain  = read::<u32>(mem, hdr + 4)? as u64;
+
+    if nbucket == 0 {
+        return None;
+    }
+
+    let buckets = hdr + 8;
+    let chains  = buckets + (nbucket * 4);
+    let mut idx = read::<u32>(mem, buckets + ((sysv_hash(name) as u64 % nbucket) * 4))? as u64;
+
+    // A chain can't be longer than there are symbols, which also stops us from walking in
+    // circles through a crafted chain.
+    for _ in 0..nchain {
+        if (idx == 0) | (idx >= nchain) {
+            return None; // Index 0 is the null symbol, which terminates every chain.
+        }
+
+        let sym = read::<ElfSym>(mem, tabs.symtab + (idx * mem::size_of::<ElfSym>() as u64))?;
+
+        if sym_name_eq(mem, tabs, &sym, name) && accept(idx) {
+            return Some(sym);
+        }
+
+        idx = read::<u32>(mem, chains + (idx * 4))? as u64;
+    }
+
+    None
+}
+
+/// The number of entries of the dynamic symbol table, which is not stored anywhere directly.
+///
+/// `DT_HASH` has one chain entry per symbol. `DT_GNU_HASH` only covers the symbols from
+/// `symoffset` on, so the end of the table is the end of the chain of the highest bucket.
+fn symbol_count(mem: &[u8], tabs: &SymTabs) -> Option<u64> {
+    let in_mem = |off: u64| (off != 0) & (off < (mem.len() as u64));
+
+    if in_mem(tabs.hash) {
+        return Some(read::<u32>(mem, tabs.hash + 4)? as u64);
+    }
+
+    if !in_mem(tabs.gnu_hash) {
+        return None;
+    }
+
+    let hdr        = tabs.gnu_hash;
+    let nbuckets   = read::<u32>(mem, hdr    )? as u64;
+    let symoffset  = read::<u32>(mem, hdr + 4)? as u64;
+    let bloom_size = read::<u32>(mem, hdr + 8)? as u64;
+
+    let buckets = hdr + 16 + (bloom_size * 8);
+    let chains  = buckets + (nbuckets * 4);
+
+    let mut last = 0_u64;
+    for i in 0..nbuckets {
+        last = last.max(read::<u32>(mem, buckets + (i * 4))? as u64);
+    }
+
+    if last < symoffset {
+        return Some(symoffset); // All buckets are empty.
+    }
+
+    // Each chain entry is 4 bytes of `mem`, so this loop is bounded by the memory size.
+    while (read::<u32>(mem, chains + ((last - symoffset) * 4))? & 1) == 0 {
+        last += 1;
+    }
+
+    Some(last + 1)
+}
+
+fn sysv_hash(name: &[u8]) -> u32 {
+    name.iter().fold(0_u32, |h, c| {
+        let h = (h << 4).wrapping_add(*c as u32);
+        (h ^ ((h & 0xF000_0000) >> 24)) & 0x0FFF_FFFF
+    })
+}
+
+fn gnu_hash(name: &[u8]) -> u32 {
+    name.iter().fold(5381_u32, |h, c| h.wrapping_mul(33).wrapping_add(*c as u32))
+}
+
+fn sym_name_eq(mem: &[u8], tabs: &SymTabs, sym: &ElfSym, name: &[u8]) -> bool {
+    let strs = match str_table(mem, tabs) { Some(s) => s, None => return false };
+
+    match strs.get((sym.st_name as usize)..) {
+        Some(s) => s.starts_with(name) && (s.get(name.len()) == Some(&0)),
+        None    => false,
+    }
+}
+
+/// The string table, clamped to the loaded ELF's memory.
+fn str_table<'a>(mem: &'a [u8], tabs: &SymTabs) -> Option<&'a [u8]> {
+    let end = tabs.strtab.checked_add(tabs.strsz)?.min(mem.len() as u64);
+
+    mem.get((tabs.strtab as usize)..(end as usize))
+}
+
+/// A bounds-checked read of a `T` at some offset into `mem`, without alignment requirements.
+pub fn read<T: Copy>(mem: &[u8], off: u64) -> Option<T> {
+    let end = off.checked_add(mem::size_of::<T>() as u64)?;
+
+    if end > (mem.len() as u64) {
+        return None;
+    }
+
+    Some(unsafe { ptr::read_unaligned(mem.as_ptr().add(off as usize) as *const T) })
+}
+
+
+
+/// An iterator over the dynamic symbols of a `ReadyElf`, see `ReadyElf::symbols`.
+#[derive(Clone)]
+pub struct SymbolIter<'a> {
+    mem:   &'a [u8],
+    tabs:  SymTabs,
+    idx:   u64,
+    count: u64,
+}
+
+impl<'a> SymbolIter<'a> {
+    pub(crate) fn new(mem: &'a [u8], tabs: SymTabs) -> Self {
+        let count = match (tabs.symtab != 0) & (tabs.strtab != 0) {
+            true  => symbol_count(mem, &tabs).unwrap_or(0),
+            false => 0,
+        };
+
+        // Index 0 is the null symbol.
+        Self { mem, tabs, idx: 1, count }
+    }
+}
+
+impl<'a> Iterator for SymbolIter<'a> {
+    type Item = Symbol<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.idx < self.count {
+            let sym = self.idx.checked_mul(mem::size_of::<ElfSym>() as u64)
+                .and_then(|off| off.checked_add(self.tabs.symtab))
+                .and_then(|off| read::<ElfSym>(self.mem, off));
+
+            let idx   = self.idx;
+            self.idx += 1;
+
+            // A symbol table running out of memory ends right there.
+            let sym = match sym {
+                Some(sym) => sym,
+                None      => { self.count = 0; return None; },
+            };
+
+            let name = match sym_name(self.mem, &self.tabs, &sym) {
+                Some(name) => name,
+                None       => continue,
+            };
+
+            let value = match sym.st_shndx {
+                SHN_UNDEF => 0,
+                SHN_ABS   => sym.st_value as usize,
+                _         => {
+                    let off = sym.st_value.wrapping_sub(self.tabs.link_base);
+                    (self.mem.as_ptr() as usize).wrapping_add(off as usize)
+                },
+            };
+
+            return Some(Symbol {
+                name, value,
+                size:    sym.st_size,
+                kind:    SymKind   ::from_info(sym.st_info),
+                binding: SymBinding::from_info(sym.st_info),
+                defined: sym.st_shndx != SHN_UNDEF,
+                version: version_name(self.mem, &self.tabs, idx),
+                hidden:  is_hidden(self.mem, &self.tabs, idx),
+                raw:     sym,
+            });
+        }
+
+        None
+    }
+}
+
+fn sym_name<'a>(mem: &'a [u8], tabs: &SymTabs, sym: &ElfSym) -> Option<&'a str> {
+    str_at(mem, tabs, sym.st_name)
+}
+
+/// Reads a NUL-terminated UTF-8 string at some offset into the string table.
+pub fn str_at<'a>(mem: &'a [u8], tabs: &SymTabs, off: u32) -> Option<&'a str> {
+    let name = str_table(mem, tabs)?.get((off as usize)..)?;
+    let len  = name.iter().position(|c| *c == 0)?;
+
+    str::from_utf8(name.get(..len)?).ok()
+}
+
+
+
+/// A dynamic symbol of a `ReadyElf`.
+#[derive(Copy, Clone, Debug)]
+pub struct Symbol<'a> {
+    /// The symbol's name, which may be empty, e.g. for section symbols.
+    pub name: &'a str,
+
+    /// The symbol's address in the ELF loader's address space, just like `ReadyElf::lookup`
+    /// returns it. Absolute symbols keep their value as is, undefined ones have a value of zero.
+    pub value: usize,
+
+    /// The size of the object or function, in bytes, or zero if unknown.
+    pub size: u64,
+
+    /// What kind of thing the symbol refers to.
+    pub kind: SymKind,
+
+    /// The symbol's visibility to other objects.
+    pub binding: SymBinding,
+
+    /// Whether this ELF defines the symbol, rather than importing it.
+    pub defined: bool,
+
+    /// The symbol's GNU version, as found via `DT_VERSYM`, e.g. `GLIBC_2.2.5`. For imported
+    /// symbols, this is the version required. `None` for unversioned symbols.
+    pub version: Option<&'a str>,
+
+    /// Whether `version` is hidden, i.e. `name@version` rather than the default
+    /// `name@@version`. `ReadyElf::lookup` skips such symbols.
+    pub hidden: bool,
+
+    raw: ElfSym,
+}
+
+impl Symbol<'_> {
+    /// The symbol table entry this symbol was read from, e.g. for its `st_other` visibility.
+    pub fn raw(&self) -> &ElfSym {
+        &self.raw
+    }
+}
+
+/// The type of a symbol, i.e. `STT_*`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[repr(u8)]
+pub enum SymKind {
+    /// No type given.
+    NoType,
+
+    /// A variable, array, etc.
+    Object,
+
+    /// A function or some other code.
+    Func,
+
+    /// A section, used for re-locations.
+    Section,
+
+    /// The source file's name.
+    File,
+
+    /// An uninitialised common block.
+    Common,
+
+    /// A thread-local variable, whose value is an offset into the TLS block.
+    Tls,
+
+    /// A function whose value is the ifunc resolver picking the actual implementation.
+    GnuIfunc,
+
+    /// Some other, e.g. OS- or processor-specific, type.
+    Other,
+}
+
+impl SymKind {
+    fn from_info(info: u8) -> Self {
+        match st_type(info) {
+            STT_NOTYPE    => SymKind::NoType,
+            STT_OBJECT    => SymKind::Object,
+            STT_FUNC      => SymKind::Func,
+            STT_SECTION   => SymKind::Section,
+            STT_FILE      => SymKind::File,
+            STT_COMMON    => SymKind::Common,
+            STT_TLS       => SymKind::Tls,
+            STT_GNU_IFUNC => SymKind::GnuIfunc,
+            _             => SymKind::Other,
+        }
+    }
+}
+
+/// The binding of a symbol, i.e. `STB_*`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[repr(u8)]
+pub enum SymBinding {
+    /// Not visible outside of this ELF.
+    Local,
+
+    /// Visible to all objects.
+    Global,
+
+    /// Like `Global`, but may be overridden, or stay undefined.
+    Weak,
+
+    /// Like `Global`, but there is only ever one definition within a process.
+    GnuUnique,
+
+    /// Some other, e.g. OS- or processor-specific, binding.
+    Other,
+}
+
+impl SymBinding {
+    fn from_info(info: u8) -> Self {
+        match st_bind(info) {
+            STB_LOCAL      => SymBinding::Local,
+            STB_GLOBAL     => SymBinding::Global,
+            STB_WEAK       => SymBinding::Weak,
+            STB_GNU_UNIQUE => SymBinding::GnuUnique,
+            _              => SymBinding::Other,
+        }
+    }
+}
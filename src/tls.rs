@@ -0,0 +1,69 @@
+
+use crate::{ Elf, ProgramHeader, Slice32 };
+use crate::elf::PT_TLS;
+
+
+
+/// The thread-local storage template of a loaded ELF, as described by its `PT_TLS` header.
+///
+/// Every thread's TLS block starts out as a copy of `image`, followed by `zero_fill` zero
+/// bytes. Setting up TLS blocks and the thread pointer is up to the caller.
+#[derive(Copy, Clone, Debug)]
+pub struct TlsTemplate<'a> {
+    /// Initialised TLS data, i.e. `.tdata`.
+    pub image: &'a [u8],
+
+    /// Number of zero bytes following `image`, i.e. the size of `.tbss`.
+    pub zero_fill: usize,
+
+    /// Required alignment of each TLS block, in bytes.
+    pub align: usize,
+}
+
+/// Where to find the TLS template within the loaded ELF's memory.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TlsSegment {
+    pub image:   Slice32<u8>,
+    pub mem_len: u32,
+    pub align:   u32,
+}
+
+impl TlsSegment {
+    pub fn new(ph: &ProgramHeader<'_>, elf: &Elf<'_>) -> Self {
+        // `ProgramHeader` doesn't keep the alignment, so dig it out of the raw header again.
+        // There is just one `PT_TLS` header, or loading fails before this is of any use.
+        let align = elf.program_headers.inner.clone()
+            .find(|raw| raw.p_type == PT_TLS)
+            .map(|raw| raw.p_align as u32)
+            .unwrap_or(1);
+
+        Self {
+            image:   Slice32::new(ph.load_range.start, ph.copy_from.len() as u32),
+            mem_len: ph.load_range.len,
+            align:   align.max(1),
+        }
+    }
+
+    /// The TLS block's size, rounded up to its alignment.
+    ///
+    /// With x86-64's TLS layout, this is how far below the thread pointer the block starts.
+    pub fn block_len(&self) -> u64 {
+        // `align` is a power of two, so masking does the job without risking a division by
+        // zero, which `new` and `try_from_parts` rule out anyway.
+        let mask = (self.align as u64).wrapping_sub(1);
+
+        ((self.mem_len as u64) + mask) & !mask
+    }
+
+    pub fn template<'a>(&self, mem: &'a [u8]) -> TlsTemplate<'a> {
+        // Parsing checked the `PT_TLS` header to lie within `mem`, like all the others.
+        let image = self.image.to_byte_range();
+
+        TlsTemplate {
+            image:     mem.get(image).unwrap_or(&[]),
+            zero_fill: self.mem_len.saturating_sub(self.image.len) as usize,
+            align:     self.align as usize,
+        }
+    }
+}
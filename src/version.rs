@@ -0,0 +1,99 @@
+
+use crate::elf::{
+    ElfVerdef, ElfVerdaux, ElfVerneed, ElfVernaux,
+    VER_NDX_GLOBAL, VERSYM_HIDDEN, VERSYM_VERSION,
+};
+use crate::symbol::{ SymTabs, read, str_at };
+
+
+
+/// The `DT_VERSYM` entry of the symbol at index `idx`, if there is a `DT_VERSYM` table.
+fn versym(mem: &[u8], tabs: &SymTabs, idx: u64) -> Option<u16> {
+    if tabs.versym == 0 {
+        return None;
+    }
+
+    read::<u16>(mem, idx.checked_mul(2)?.checked_add(tabs.versym)?)
+}
+
+/// Whether the symbol at index `idx` is a hidden version, i.e. not the default one.
+pub fn is_hidden(mem: &[u8], tabs: &SymTabs, idx: u64) -> bool {
+    versym(mem, tabs, idx).map(|v| (v & VERSYM_HIDDEN) != 0).unwrap_or(false)
+}
+
+/// The name of the version of the symbol at index `idx`, be it a defined or required one.
+///
+/// Local and global symbols have no version. All version tables are walked with bounds-checked
+/// reads, and at most `DT_VERDEFNUM` or `DT_VERNEEDNUM` entries are visited, so broken tables
+/// just make for unversioned symbols.
+pub fn version_name<'a>(mem: &'a [u8], tabs: &SymTabs, idx: u64) -> Option<&'a str> {
+    let ndx = versym(mem, tabs, idx)? & VERSYM_VERSION;
+
+    if ndx <= VER_NDX_GLOBAL {
+        return None;
+    }
+
+    verdef_name(mem, tabs, ndx).or_else(|| verneed_name(mem, tabs, ndx))
+}
+
+fn verdef_name<'a>(mem: &'a [u8], tabs: &SymTabs, ndx: u16) -> Option<&'a str> {
+    let mut off = tabs.verdef;
+
+    if off == 0 {
+        return None;
+    }
+
+    for _ in 0..tabs.verdefnum {
+        let def = read::<ElfVerdef>(mem, off)?;
+
+        // The first auxiliary entry names the version, any others name its parents.
+        if def.vd_ndx == ndx {
+            let aux = read::<ElfVerdaux>(mem, off.checked_add(def.vd_aux as u64)?)?;
+
+            return str_at(mem, tabs, aux.vda_name);
+        }
+
+        if def.vd_next == 0 {
+            return None;
+        }
+
+        off = off.checked_add(def.vd_next as u64)?;
+    }
+
+    None
+}
+
+fn verneed_name<'a>(mem: &'a [u8], tabs: &SymTabs, ndx: u16) -> Option<&'a str> {
+    let mut off = tabs.verneed;
+
+    if off == 0 {
+        return None;
+    }
+
+    for _ in 0..tabs.verneednum {
+        let need    = read::<ElfVerneed>(mem, off)?;
+        let mut aux = off.checked_add(need.vn_aux as u64)?;
+
+        for _ in 0..need.vn_cnt {
+            let vna = read::<ElfVernaux>(mem, aux)?;
+
+            if vna.vna_other == ndx {
+                return str_at(mem, tabs, vna.vna_name);
+            }
+
+            if vna.vna_next == 0 {
+                break;
+            }
+
+            aux = aux.checked_add(vna.vna_next as u64)?;
+        }
+
+        if need.vn_next == 0 {
+            return None;
+        }
+
+        off = off.checked_add(need.vn_next as u64)?;
+    }
+
+    None
+}
@@ -1,6 +1,7 @@
+#![allow(clippy::drop_non_drop, clippy::bool_assert_comparison, clippy::unusual_byte_groupings)]
+
 
 use elf_loader::*;
-use std::mem;
 
 
 
@@ -8,7 +9,7 @@ mod os;
 
 
 
-// FIXME force alignment, using a custom section if necessary.
+// Parsing copies headers out of the buffer, so it needs no alignment.
 static ELF: &[u8] = include_bytes!("./bss_rodata_data.elf");
 
 
@@ -31,10 +32,10 @@ fn simple_elf_works() {
     drop(buf);
 
     let base  = loaded_elf.loader_base();
-    let ready = loaded_elf.try_reloc(base, Some(os::protection_fn))
+    let ready = loaded_elf.try_reloc(base, Some(os::protection_fn), None)
                           .expect("Re-locating `bss_rodata_data.elf` failed");
 
-    let main: fn(&mut u32)->bool = unsafe { mem::transmute(ready.p_entry()) };
+    let main: fn(&mut u32)->bool = unsafe { ready.entry_fn() };
 
     let mut inout = 0xDEADBEEF;
     assert_eq!((main)(&mut inout), true);
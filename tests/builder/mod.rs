@@ -0,0 +1,406 @@
+
+#![allow(dead_code)]
+
+//! A tiny ELF64 writer for crafting (possibly broken) test input without a linker.
+
+
+
+pub const PT_NULL:      u32 = 0;
+pub const PT_LOAD:      u32 = 1;
+pub const PT_DYNAMIC:   u32 = 2;
+pub const PT_INTERP:    u32 = 3;
+pub const PT_TLS:       u32 = 7;
+pub const PT_GNU_STACK: u32 = 0x6474E551;
+pub const PT_GNU_RELRO: u32 = 0x6474E552;
+
+pub const PF_X:   u32 = 0b001;
+pub const PF_W:   u32 = 0b010;
+pub const PF_R:   u32 = 0b100;
+pub const PF_RW:  u32 = 0b110;
+pub const PF_RX:  u32 = 0b101;
+pub const PF_RWX: u32 = 0b111;
+
+pub const ET_DYN:    u16 =  3;
+pub const EM_X86_64: u16 = 62;
+
+pub const DT_NULL:            u64 =  0;
+pub const DT_NEEDED:          u64 =  1;
+pub const DT_PLTRELSZ:        u64 =  2;
+pub const DT_HASH:            u64 =  4;
+pub const DT_STRTAB:          u64 =  5;
+pub const DT_SYMTAB:          u64 =  6;
+pub const DT_RELA:            u64 =  7;
+pub const DT_RELASZ:          u64 =  8;
+pub const DT_RELAENT:         u64 =  9;
+pub const DT_STRSZ:           u64 = 10;
+pub const DT_SONAME:          u64 = 14;
+pub const DT_REL:             u64 = 17;
+pub const DT_RELSZ:           u64 = 18;
+pub const DT_RELENT:          u64 = 19;
+pub const DT_PLTREL:          u64 = 20;
+pub const DT_TEXTREL:         u64 = 22;
+pub const DT_JMPREL:          u64 = 23;
+pub const DT_INIT_ARRAY:      u64 = 25;
+pub const DT_FINI_ARRAY:      u64 = 26;
+pub const DT_INIT_ARRAYSZ:    u64 = 27;
+pub const DT_FINI_ARRAYSZ:    u64 = 28;
+pub const DT_FLAGS:           u64 = 30;
+pub const DT_PREINIT_ARRAY:   u64 = 32;
+pub const DT_PREINIT_ARRAYSZ: u64 = 33;
+pub const DT_RELRSZ:          u64 = 35;
+pub const DT_RELR:            u64 = 36;
+pub const DT_RELRENT:         u64 = 37;
+pub const DT_GNU_HASH:        u64 = 0x6FFFFEF5;
+pub const DT_RELACOUNT:       u64 = 0x6FFFFFF9;
+pub const DT_RELCOUNT:        u64 = 0x6FFFFFFA;
+pub const DT_FLAGS_1:         u64 = 0x6FFFFFFB;
+pub const DT_VERSYM:          u64 = 0x6FFFFFF0;
+pub const DT_VERDEF:          u64 = 0x6FFFFFFC;
+pub const DT_VERDEFNUM:       u64 = 0x6FFFFFFD;
+pub const DT_VERNEED:         u64 = 0x6FFFFFFE;
+pub const DT_VERNEEDNUM:      u64 = 0x6FFFFFFF;
+
+pub const R_X86_64_64:        u64 = 1;
+pub const R_X86_64_PC32:      u64 = 2;
+pub const R_X86_64_COPY:      u64 = 5;
+pub const R_X86_64_GLOB_DAT:  u64 = 6;
+pub const R_X86_64_JUMP_SLOT: u64 = 7;
+pub const R_X86_64_RELATIVE:  u64 = 8;
+pub const R_X86_64_32:        u64 = 10;
+pub const R_X86_64_32S:       u64 = 11;
+pub const R_X86_64_DTPMOD64:  u64 = 16;
+pub const R_X86_64_DTPOFF64:  u64 = 17;
+pub const R_X86_64_TPOFF64:   u64 = 18;
+
+pub const ST_INFO_GLOBAL_FUNC: u8 = 0x12;
+pub const ST_INFO_GLOBAL_OBJ:  u8 = 0x11;
+pub const ST_INFO_WEAK_FUNC:   u8 = 0x22;
+pub const ST_INFO_GLOBAL_TLS:  u8 = 0x16;
+
+/// File offset of the first blob. Everything before that is reserved for headers.
+const BLOBS_START: usize = 0x1000;
+
+const EHDR_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+
+
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Phdr {
+    pub p_type:   u32,
+    pub p_flags:  u32,
+    pub p_offset: u64,
+    pub p_vaddr:  u64,
+    pub p_paddr:  u64,
+    pub p_filesz: u64,
+    pub p_memsz:  u64,
+    pub p_align:  u64,
+}
+
+pub struct ElfBuilder {
+    pub e_type:    u16,
+    pub e_machine: u16,
+    pub e_entry:   u64,
+    pub e_flags:   u32,
+    pub phdrs:     Vec<Phdr>,
+    blobs:         Vec<u8>,
+}
+
+impl ElfBuilder {
+    pub fn new() -> Self {
+        Self {
+            e_type:    ET_DYN,
+            e_machine: EM_X86_64,
+            e_entry:   0,
+            e_flags:   0,
+            phdrs:     Vec::new(),
+            blobs:     Vec::new(),
+        }
+    }
+
+    pub fn entry(&mut self, entry: u64) -> &mut Self {
+        self.e_entry = entry;
+        self
+    }
+
+    /// Appends raw bytes to the file and returns their file offset.
+    pub fn blob(&mut self, data: &[u8]) -> u64 {
+        while !self.blobs.len().is_multiple_of(16) { self.blobs.push(0); }
+
+        let off = BLOBS_START + self.blobs.len();
+        self.blobs.extend_from_slice(data);
+        off as u64
+    }
+
+    pub fn phdr(&mut self, ph: Phdr) -> &mut Self {
+        self.phdrs.push(ph);
+        self
+    }
+
+    /// Adds a program header that maps `data` at `vaddr`, with `memsz` bytes in memory.
+    pub fn segment(&mut self, p_type: u32, p_flags: u32, vaddr: u64, memsz: u64, data: &[u8])
+    -> &mut Self {
+        let off = self.blob(data);
+
+        self.phdr(Phdr {
+            p_type, p_flags,
+            p_offset: off,
+            p_vaddr:  vaddr,
+            p_paddr:  vaddr,
+            p_filesz: data.len() as u64,
+            p_memsz:  memsz,
+            p_align:  0x1000,
+        })
+    }
+
+    /// Like `segment`, but with a `PT_DYNAMIC` header covering the very same bytes.
+    pub fn dynamic(&mut self, vaddr: u64, dyns: &[(u64, u64)]) -> &mut Self {
+        let data = dyn_table(dyns);
+        let off  = self.blob(&data);
+        let len  = data.len() as u64;
+
+        self.phdr(Phdr {
+            p_type: PT_LOAD, p_flags: PF_RW,
+            p_offset: off, p_vaddr: vaddr, p_paddr: vaddr,
+            p_filesz: len, p_memsz: len,
+            p_align: 0x1000,
+        });
+        self.phdr(Phdr {
+            p_type: PT_DYNAMIC, p_flags: PF_RW,
+            p_offset: off, p_vaddr: vaddr, p_paddr: vaddr,
+            p_filesz: len, p_memsz: len,
+            p_align: 8,
+        })
+    }
+
+    /// Writes the ELF. A few padding bytes follow the last blob.
+    pub fn build(&self) -> Vec<u8> {
+        let mut out = self.build_unpadded();
+        out.extend_from_slice(&[0; 16]);
+        out
+    }
+
+    /// Writes the ELF, ending right at the last byte of the last blob.
+    pub fn build_unpadded(&self) -> Vec<u8> {
+        assert!(EHDR_SIZE + (self.phdrs.len() * PHDR_SIZE) <= BLOBS_START);
+
+        let mut out = Vec::with_capacity(BLOBS_START + self.blobs.len());
+
+        out.extend_from_slice(&[0x7F, b'E', b'L', b'F', 2, 1, 1, 0]);
+        out.extend_from_slice(&[0; 8]);
+        out.extend_from_slice(&self.e_type   .to_le_bytes());
+        out.extend_from_slice(&self.e_machine.to_le_bytes());
+        out.extend_from_slice(&1_u32         .to_le_bytes()); // e_version
+        out.extend_from_slice(&self.e_entry  .to_le_bytes());
+        out.extend_from_slice(&(EHDR_SIZE as u64).to_le_bytes()); // e_phoff
+        out.extend_from_slice(&0_u64         .to_le_bytes()); // e_shoff
+        out.extend_from_slice(&self.e_flags  .to_le_bytes());
+        out.extend_from_slice(&(EHDR_SIZE as u16).to_le_bytes());
+        out.extend_from_slice(&(PHDR_SIZE as u16).to_le_bytes());
+        out.extend_from_slice(&(self.phdrs.len() as u16).to_le_bytes());
+        out.extend_from_slice(&64_u16        .to_le_bytes()); // e_shentsize
+        out.extend_from_slice(&0_u16         .to_le_bytes()); // e_shnum
+        out.extend_from_slice(&0_u16         .to_le_bytes()); // e_shstrndx
+
+        for ph in &self.phdrs {
+            out.extend_from_slice(&ph.p_type  .to_le_bytes());
+            out.extend_from_slice(&ph.p_flags .to_le_bytes());
+            out.extend_from_slice(&ph.p_offset.to_le_bytes());
+            out.extend_from_slice(&ph.p_vaddr .to_le_bytes());
+            out.extend_from_slice(&ph.p_paddr .to_le_bytes());
+            out.extend_from_slice(&ph.p_filesz.to_le_bytes());
+            out.extend_from_slice(&ph.p_memsz .to_le_bytes());
+            out.extend_from_slice(&ph.p_align .to_le_bytes());
+        }
+
+        out.resize(BLOBS_START, 0);
+        out.extend_from_slice(&self.blobs);
+        out
+    }
+}
+
+
+
+/// A crafted re-locatable ELF with code at `0x1000`, and a page of data at `0x2000` holding
+///
+/// - the `Rel` table at `0x2000`, the `Rela` table at `0x2100` and the PLT table at `0x2200`,
+/// - the `Relr` table at `0x2300`, the symbol table at `0x2400` and strings at `0x2600`,
+/// - and `words` from `0x2800` on, which is where re-locations are meant to write to.
+///
+/// Empty tables get no `DYNAMIC` entries, and `dyns` are appended to the others.
+#[derive(Clone, Default)]
+pub struct RelocElf<'a> {
+    /// The code at `0x1000`, or `ret_imm32(42)` if empty.
+    pub code:      &'a [u8],
+    pub rels:      &'a [(u64, u64)],
+    pub relas:     &'a [(u64, u64, i64)],
+    pub plt_rels:  &'a [(u64, u64)],
+    pub plt_relas: &'a [(u64, u64, i64)],
+    pub relr:      &'a [u64],
+    /// See `sym_table` and `sized_sym_table`.
+    pub symtab:    Vec<u8>,
+    pub strtab:    &'a [u8],
+    pub words:     &'a [u64],
+    pub dyns:      &'a [(u64, u64)],
+    /// Lists the `DYNAMIC` entries in reverse order, which must not make any difference.
+    pub reversed:  bool,
+}
+
+impl RelocElf<'_> {
+    /// An `ElfBuilder` with all the segments, to add some more to or to tweak the header of.
+    pub fn builder(&self) -> ElfBuilder {
+        let plt = match self.plt_rels.is_empty() {
+            true  => rela_table(self.plt_relas),
+            false => rel_table(self.plt_rels),
+        };
+
+        let mut data = Vec::new();
+        let mut put  = |at: usize, bytes: &[u8]| {
+            assert!(data.len() <= (at - 0x2000), "the table before {:#x} is too large", at);
+            data.resize(at - 0x2000, 0);
+            data.extend_from_slice(bytes);
+        };
+
+        put(0x2000, &rel_table(self.rels));
+        put(0x2100, &rela_table(self.relas));
+        put(0x2200, &plt);
+        put(0x2300, &u64_table(self.relr));
+        put(0x2400, &self.symtab);
+        put(0x2600, self.strtab);
+        put(0x2800, &u64_table(self.words));
+
+        let mut dyns = Vec::new();
+
+        if !self.rels.is_empty() {
+            dyns.extend([
+                (DT_REL,  0x2000), (DT_RELSZ,  16 * self.rels .len() as u64), (DT_RELENT,  16),
+            ]);
+        }
+        if !self.relas.is_empty() {
+            dyns.extend([
+                (DT_RELA, 0x2100), (DT_RELASZ, 24 * self.relas.len() as u64), (DT_RELAENT, 24),
+            ]);
+        }
+        if !plt.is_empty() {
+            let kind = if self.plt_rels.is_empty() { DT_RELA } else { DT_REL };
+            dyns.extend([
+                (DT_JMPREL, 0x2200), (DT_PLTRELSZ, plt.len() as u64), (DT_PLTREL, kind),
+            ]);
+        }
+        if !self.relr.is_empty() {
+            dyns.extend([
+                (DT_RELR, 0x2300), (DT_RELRSZ, 8 * self.relr.len() as u64), (DT_RELRENT, 8),
+            ]);
+        }
+        if !self.symtab.is_empty() {
+            dyns.push((DT_SYMTAB, 0x2400));
+        }
+        if !self.strtab.is_empty() {
+            dyns.extend([(DT_STRTAB, 0x2600), (DT_STRSZ, self.strtab.len() as u64)]);
+        }
+
+        dyns.extend_from_slice(self.dyns);
+
+        if self.reversed { dyns.reverse(); }
+
+        let code = match self.code.is_empty() {
+            true  => ret_imm32(42),
+            false => self.code.to_vec(),
+        };
+
+        let mut b = ElfBuilder::new();
+
+        b.dynamic(0x0000, &dyns);
+        b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &code);
+        b.segment(PT_LOAD, PF_RW, 0x2000, 0x1000, &data);
+        b
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        self.builder().build()
+    }
+}
+
+
+
+/// Serialises `Dyn` entries, appending the terminating `DT_NULL`.
+pub fn dyn_table(dyns: &[(u64, u64)]) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    for &(tag, val) in dyns.iter().chain(Some(&(DT_NULL, 0))) {
+        out.extend_from_slice(&tag.to_le_bytes());
+        out.extend_from_slice(&val.to_le_bytes());
+    }
+
+    out
+}
+
+/// Serialises `Rel` entries of the form `(r_offset, r_info)`.
+pub fn rel_table(rels: &[(u64, u64)]) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    for &(off, info) in rels {
+        out.extend_from_slice(&off .to_le_bytes());
+        out.extend_from_slice(&info.to_le_bytes());
+    }
+
+    out
+}
+
+/// Serialises `Rela` entries of the form `(r_offset, r_info, r_addend)`.
+pub fn rela_table(relas: &[(u64, u64, i64)]) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    for &(off, info, addend) in relas {
+        out.extend_from_slice(&off   .to_le_bytes());
+        out.extend_from_slice(&info  .to_le_bytes());
+        out.extend_from_slice(&addend.to_le_bytes());
+    }
+
+    out
+}
+
+/// Serialises `Sym` entries of the form `(st_name, st_info, st_shndx, st_value)`, after the
+/// null symbol.
+pub fn sym_table(syms: &[(u32, u8, u16, u64)]) -> Vec<u8> {
+    let sized: Vec<_> = syms.iter()
+        .map(|&(name, info, shndx, value)| (name, info, shndx, value, 0))
+        .collect();
+
+    sized_sym_table(&sized)
+}
+
+/// Like `sym_table`, but with an `st_size` for each symbol.
+pub fn sized_sym_table(syms: &[(u32, u8, u16, u64, u64)]) -> Vec<u8> {
+    let mut out = vec![0; 24];
+
+    for &(name, info, shndx, value, size) in syms {
+        out.extend_from_slice(&name .to_le_bytes());
+        out.extend_from_slice(&[info, 0]);
+        out.extend_from_slice(&shndx.to_le_bytes());
+        out.extend_from_slice(&value.to_le_bytes());
+        out.extend_from_slice(&size .to_le_bytes());
+    }
+
+    out
+}
+
+/// Serialises little-endian `u64`s, e.g. `Relr` entries.
+pub fn u64_table(words: &[u64]) -> Vec<u8> {
+    words.iter().flat_map(|w| w.to_le_bytes()).collect()
+}
+
+/// Reads the little-endian `u64` at `off`.
+pub fn u64_at(mem: &[u8], off: usize) -> u64 {
+    let mut w = [0; 8];
+    w.copy_from_slice(&mem[off..(off + 8)]);
+    u64::from_le_bytes(w)
+}
+
+/// `mov eax, imm32; ret`
+pub fn ret_imm32(imm: u32) -> Vec<u8> {
+    let mut out = vec![0xB8];
+    out.extend_from_slice(&imm.to_le_bytes());
+    out.push(0xC3);
+    out
+}
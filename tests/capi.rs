@@ -0,0 +1,91 @@
+#![cfg(feature = "capi")]
+#![allow(clippy::zero_prefixed_literal)]
+
+use elf_loader::*;
+use elf_loader::capi::*;
+use elf_loader::SegmentProtection::RX;
+use std::ptr;
+
+
+
+mod os;
+
+
+
+static ELF: &[u8] = include_bytes!("./simple.elf");
+
+
+
+#[allow(improper_ctypes_definitions)]
+type RelocSig = unsafe extern "C" fn(
+    *mut LoadedElf<'static>, *mut u8, Option<ProtectFn>, Option<ResolveFn>,
+    *mut *mut ReadyElf<'static>,
+) -> u16;
+
+#[test]
+fn signatures_stay_put() {
+    let _: unsafe extern "C" fn(*const u8, usize, *mut *mut Elf<'static>) -> u16 = elf_parse;
+    let _: unsafe extern "C" fn(*const Elf<'static>) -> u32                     = elf_mem_len;
+    let _: unsafe extern "C" fn(*const Elf<'static>) -> u32                     = elf_mem_align;
+    let _: RelocSig                                                            = elf_reloc;
+    let _: unsafe extern "C" fn(*const ReadyElf<'static>) -> *const ()          = elf_entry;
+
+    let _: unsafe extern "C" fn(
+        *const Elf<'static>, *mut u8, usize, *mut *mut LoadedElf<'static>,
+    ) -> u16 = elf_load;
+}
+
+#[test]
+fn error_codes_round_trip() {
+    let mut seen = 0;
+
+    for code in 0 ..= u16::MAX {
+        if let Some(e) = ElfError::from_code(code) {
+            assert_eq!(e.code(), code, "{}", e);
+            seen += 1;
+        }
+    }
+
+    // All sub-errors but the hidden `_Reserved` ones, and none of them is `ELF_OK`.
+    assert_eq!(seen, 23 + 13 + 32);
+    assert_eq!(ElfError::from_code(ELF_OK), None);
+
+    let e = ElfError::Reloc(RelocElfError::MemProtectFailed { start: 1, end: 2, protect: RX });
+    assert_eq!(e.code(), 0x30D);
+    assert_eq!(ElfError::from_code(0x30D).map(|e| e.as_str()), Some(e.as_str()));
+}
+
+#[test]
+fn simple_elf_runs_through_the_c_api() {
+    let mut elf    = ptr::null_mut();
+    let mut loaded = ptr::null_mut();
+    let mut ready  = ptr::null_mut();
+    let     buf    = Vec::from(ELF);
+
+    unsafe {
+        assert_eq!(elf_parse(buf.as_ptr(), buf.len(), &mut elf), ELF_OK);
+
+        let mem = os::alloc_aligned(elf_mem_len(elf) as usize, elf_mem_align(elf) as usize);
+
+        assert_eq!(elf_load(elf, mem.as_mut_ptr(), mem.len(), &mut loaded), ELF_OK);
+        assert_eq!(elf_reloc(loaded, ptr::null_mut(), Some(os::protection_fn), None, &mut ready),
+                   ELF_OK);
+
+        let main: extern "C" fn() -> i32 = std::mem::transmute(elf_entry(ready));
+        assert_eq!(main(), 0815);
+
+        elf_ready_free(ready);
+        elf_free(elf);
+    }
+}
+
+#[test]
+fn failures_return_error_codes() {
+    let mut elf = ptr::null_mut();
+    let code    = unsafe { elf_parse([0_u8; 4].as_ptr(), 4, &mut elf) };
+
+    assert_ne!(code, ELF_OK);
+    assert!(elf.is_null());
+    assert_eq!(ElfError::from_code(code).map(|e| e.code()), Some(code));
+    assert!(matches!(ElfError::from_code(code), Some(ElfError::Parse(_))));
+}
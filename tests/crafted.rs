@@ -0,0 +1,1022 @@
+use elf_loader::*;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+fn load(raw: &[u8]) -> Result<LoadedElf<'static>, ElfError> {
+    let elf = Elf::try_parse(raw)?;
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    Ok(elf.try_load(mem)?)
+}
+
+
+
+#[test]
+fn dynamic_outside_of_loads_is_rejected() {
+    let dyns = dyn_table(&[]);
+    let mut b = ElfBuilder::new();
+    let off   = b.blob(&dyns);
+
+    b.segment(PT_LOAD, PF_RX, 0x0000, 0x1000, &ret_imm32(42));
+    b.phdr(Phdr {
+        p_type: PT_DYNAMIC, p_flags: PF_RW,
+        p_offset: off, p_vaddr: 0x3000, p_paddr: 0x3000,
+        p_filesz: dyns.len() as u64, p_memsz: dyns.len() as u64,
+        p_align: 8,
+    });
+
+    let raw = b.build();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    // The `Dyn` entries are still in bounds, they just aren't loaded.
+    assert!(elf.mem_len() as usize >= 0x3000 + dyns.len());
+    assert_eq!(load(&raw).err(), Some(ElfError::Load(LoadElfError::DynamicNotLoadable)));
+}
+
+#[test]
+fn dynamic_running_past_the_loaded_memory_is_rejected() {
+    let dyns  = dyn_table(&[]);
+    let mut b = ElfBuilder::new();
+
+    b.segment(PT_LOAD, PF_RX, 0x0000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x1000, 0x1000, &dyns);
+
+    // Starts within the data segment, but its `p_memsz` reaches far past the end of it.
+    let data = b.phdrs[1];
+    b.phdr(Phdr { p_type: PT_DYNAMIC, p_memsz: 0x3000, p_align: 8, ..data });
+
+    let raw = b.build();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    // Past all `PT_LOAD` segments, but not past `mem_len`, as that covers every segment.
+    assert_eq!(elf.mem_len(), 0x4000);
+    assert_eq!(load(&raw).err(), Some(ElfError::Load(LoadElfError::DynamicNotLoadable)));
+}
+
+#[test]
+fn dynamic_past_4gib_is_rejected() {
+    let dyns = dyn_table(&[]);
+    let mut b = ElfBuilder::new();
+    let off   = b.blob(&dyns);
+
+    b.segment(PT_LOAD, PF_RX, 0x0000, 0x1000, &ret_imm32(42));
+    b.phdr(Phdr {
+        p_type: PT_DYNAMIC, p_flags: PF_RW,
+        p_offset: off, p_vaddr: 0xFFFF_FFF8, p_paddr: 0,
+        p_filesz: dyns.len() as u64, p_memsz: dyns.len() as u64,
+        p_align: 8,
+    });
+
+    let raw = b.build();
+
+    assert_eq!(Elf::try_parse(&raw).err(), Some(ParseElfError::BadVmemRange));
+}
+
+#[test]
+fn unknown_elf_versions_are_rejected() {
+    let mut b = ElfBuilder::new();
+    b.dynamic(0x0000, &[]);
+
+    let raw = b.build();
+    assert!(Elf::try_parse(&raw).is_ok());
+
+    for (at, version) in [(6, 0), (6, 2), (0x14, 0), (0x14, 2)] {
+        let mut raw = raw.clone();
+        raw[at] = version;
+
+        assert_eq!(Elf::try_parse(&raw).err(), Some(ParseElfError::BadVersion), "at {:#x}", at);
+        assert_eq!(Elf::try_parse_lazy(&raw).err(), Some(ParseElfError::BadVersion));
+    }
+}
+
+#[test]
+fn dynamic_entries_stop_at_dt_null() {
+    let mut b = ElfBuilder::new();
+    b.dynamic(0x0000, &[(DT_SONAME, 1), (DT_FLAGS, 8), (DT_NULL, 0), (DT_FLAGS_1, 1)]);
+
+    let loaded = load(&b.build()).expect("loading failed");
+
+    assert_eq!(loaded.dynamic_entries().collect::<Vec<_>>(), [(DT_SONAME, 1), (DT_FLAGS, 8)]);
+}
+
+#[test]
+fn unknown_segments_may_be_rejected() {
+    const PT_NOTE: u32 = 4;
+    const PT_PHDR: u32 = 6;
+
+    let with = |p_type: u32| {
+        let mut b = ElfBuilder::new();
+        b.dynamic(0x0000, &[]);
+        b.phdr(Phdr { p_type, p_flags: PF_R, ..Phdr::default() });
+        b.build()
+    };
+
+    let strict = LoadOptions { strict_unknown_segments: true, ..LoadOptions::default() };
+    let load   = |raw: &[u8], opts: LoadOptions| {
+        let elf = Elf::try_parse(raw).expect("parsing failed");
+        let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+        elf.try_load_with(mem, opts).map(drop)
+    };
+
+    let unknown = with(0x6000_1234);
+
+    assert_eq!(load(&unknown, LoadOptions::default()), Ok(()));
+    assert_eq!(load(&unknown, strict),
+               Err(LoadElfError::UnsupportedSegment { p_type: 0x6000_1234 }));
+
+    // Types that are of no concern to loading are fine, though.
+    assert_eq!(load(&with(PT_NOTE), strict), Ok(()));
+    assert_eq!(load(&with(PT_PHDR), strict), Ok(()));
+}
+
+#[test]
+fn truncated_segment_is_rejected() {
+    let code = ret_imm32(42);
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &code);
+
+    // Pretend the file holds more data than it actually does.
+    b.phdrs[2].p_filesz = 0x100;
+
+    let raw = b.build_unpadded();
+
+    assert_eq!(Elf::try_parse(&raw).err(), Some(ParseElfError::BadPhRange));
+}
+
+#[test]
+fn segment_ending_at_the_end_of_the_file_is_fine() {
+    let code = ret_imm32(42);
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &code);
+
+    let raw = b.build_unpadded();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+    let ph  = elf.program_headers().last().expect("no program headers");
+
+    assert_eq!(ph.copy_from, &code[..]);
+}
+
+#[test]
+fn segment_bigger_in_file_than_in_memory_is_rejected() {
+    let code = ret_imm32(42);
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 2, &code);
+
+    assert_eq!(Elf::try_parse(&b.build()).err(), Some(ParseElfError::PhSmallerThanVmem));
+}
+
+#[test]
+fn reload_into_keeps_the_base() {
+    let build = |imm| {
+        let mut b = ElfBuilder::new();
+        b.dynamic(0x0000, &[]);
+        b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(imm));
+        b.entry(0x1000);
+        b.build()
+    };
+
+    let (raw1, raw2) = (build(1), build(2));
+    let elf1 = Elf::try_parse(&raw1).expect("parsing failed");
+    let elf2 = Elf::try_parse(&raw2).expect("parsing failed");
+    let mem  = os::alloc_aligned(elf1.mem_len() as usize, elf1.mem_align() as usize);
+    let base = mem.as_mut_ptr();
+
+    let entry1 = {
+        let ready = elf1.try_reload_into(&mut *mem, base, Some(os::protection_fn), None)
+                        .expect("loading failed");
+        let main: extern "C" fn() -> u32 = unsafe { core::mem::transmute(ready.p_entry()) };
+
+        assert_eq!(main(), 1);
+        ready.p_entry()
+    };
+
+    let ready = elf2.try_reload_into(&mut *mem, base, Some(os::protection_fn), None)
+                    .expect("reloading failed");
+    let main: extern "C" fn() -> u32 = unsafe { core::mem::transmute(ready.p_entry()) };
+
+    assert_eq!(ready.p_entry(), entry1);
+    assert_eq!(main(), 2);
+}
+
+#[test]
+fn strtab_out_of_bounds_is_rejected() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[(DT_STRTAB, 0x1F00), (DT_STRSZ, 0x200)]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+
+    let mut loaded = load(&b.build()).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    assert_eq!(loaded.try_reloc(base, None, None).err().map(|(_, e)| e),
+               Some(RelocElfError::BadStrTabRange));
+}
+
+#[test]
+fn symtab_out_of_bounds_is_rejected() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[(DT_SYMTAB, 0x1FF0)]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+
+    let mut loaded = load(&b.build()).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    assert_eq!(loaded.try_reloc(base, None, None).err().map(|(_, e)| e),
+               Some(RelocElfError::BadSymTabRange));
+}
+
+#[test]
+fn misaligned_symtab_is_rejected() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[(DT_SYMTAB, 0x1004)]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+
+    let mut loaded = load(&b.build()).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    assert_eq!(loaded.try_reloc(base, None, None).err().map(|(_, e)| e),
+               Some(RelocElfError::BadSymTabRange));
+}
+
+fn reloc_with_relas(relas: &[(u64, u64, i64)]) -> Result<(), RelocElfError> {
+    let table = rela_table(relas);
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[(DT_RELA, 0x2000), (DT_RELASZ, table.len() as u64), (DT_RELAENT, 24)]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x2000, 0x1000, &table);
+
+    let mut loaded = load(&b.build()).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    loaded.try_reloc(base, None, None).map(drop).map_err(|(_, e)| e)
+}
+
+#[test]
+fn copy_relocations_are_not_silently_ignored() {
+    // There is not even a symbol table to find a symbol to copy in.
+    assert_eq!(reloc_with_relas(&[(0x2800, R_X86_64_COPY, 0)]),
+               Err(RelocElfError::BadSymbolIndex));
+}
+
+#[test]
+fn copy_relocations_come_last() {
+    // The broken `RELATIVE` entry must be hit first, despite following the `COPY` entry.
+    assert_eq!(reloc_with_relas(&[
+        (0x2800,  R_X86_64_COPY,     0),
+        (0x10000, R_X86_64_RELATIVE, 0),
+    ]), Err(RelocElfError::BadRelaOffset));
+}
+
+#[test]
+fn align_base_rounds_up_to_mem_align() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+
+    let raw = b.build();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    let align = |p: usize| elf.align_base(p as *mut u8) as usize;
+
+    assert_eq!(elf.mem_align(), 0x1000);
+    assert_eq!(align(0x0000), 0x0000);
+    assert_eq!(align(0x0001), 0x1000);
+    assert_eq!(align(0x1000), 0x1000);
+    assert_eq!(align(0x1FFF), 0x2000);
+}
+
+/// Sets up a single `JUMP_SLOT` GOT entry at `0x2800` that refers to the null symbol, which
+/// re-locating fills with `base + target`.
+fn ready_with_plt_target(target: i64) -> ReadyElf<'static> {
+    let data  = rela_table(&[(0x2800, R_X86_64_JUMP_SLOT, target)]);
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[(DT_JMPREL, 0x2000), (DT_PLTRELSZ, 24), (DT_PLTREL, DT_RELA)]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x2000, 0x1000, &data);
+
+    let mut loaded = load(&b.build()).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    loaded.try_reloc(base, None, None).map_err(|(_, e)| e).expect("re-locating failed")
+}
+
+#[test]
+fn verify_plt_accepts_targets_in_code() {
+    assert_eq!(ready_with_plt_target(0x1000).verify_plt(), Ok(()));
+}
+
+#[test]
+fn verify_plt_rejects_targets_in_data() {
+    assert_eq!(ready_with_plt_target(0x2000).verify_plt(), Err(0x2800));
+}
+
+#[test]
+fn the_null_symbol_resolves_to_the_base() {
+    let data  = rela_table(&[(0x2800, R_X86_64_64, 0x10), (0x2808, R_X86_64_PC32, 0x10)]);
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[(DT_RELA, 0x2000), (DT_RELASZ, 48), (DT_RELAENT, 24)]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x2000, 0x1000, &data);
+
+    let mut loaded = load(&b.build()).expect("loading failed");
+    let base       = loaded.loader_base();
+    let ready      = loaded.try_reloc(base, None, None)
+                           .map_err(|(_, e)| e)
+                           .expect("re-locating failed");
+
+    // `B + A` and `B + A - P`, just as `ld.so` computes them.
+    assert_eq!(u64_at(ready.p_mem(), 0x2800), (base as u64) + 0x10);
+    assert_eq!(u64_at(ready.p_mem(), 0x2808) as u32, (0x10 - 0x2808_i32) as u32);
+}
+
+#[test]
+fn init_arrays_are_re_located_and_bounds_checked() {
+    let data  = rela_table(&[
+        (0x2800, R_X86_64_RELATIVE, 0x1000),
+        (0x2808, R_X86_64_RELATIVE, 0x1002),
+    ]);
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[
+        (DT_RELA,          0x2000), (DT_RELASZ,          48), (DT_RELAENT, 24),
+        (DT_INIT_ARRAY,    0x2800), (DT_INIT_ARRAYSZ,    16),
+        (DT_PREINIT_ARRAY, 0x2FF8), (DT_PREINIT_ARRAYSZ, 16),
+    ]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x2000, 0x1000, &data);
+
+    let mut loaded = load(&b.build()).expect("loading failed");
+    let base       = loaded.loader_base() as u64;
+    let ready      = loaded.try_reloc(base as *mut u8, None, None)
+                           .map_err(|(_, e)| e)
+                           .expect("re-locating failed");
+
+    assert_eq!(ready.init_array(), Some(&[base + 0x1000, base + 0x1002][..]));
+    assert_eq!(ready.fini_array(), Some(&[][..]));
+
+    // The table reaches past the end of the loaded memory.
+    assert_eq!(ready.preinit_array(), None);
+}
+
+/// Builds an ELF with `count` single-page `PT_LOAD` segments of alternating protection, after
+/// the one holding the `PT_DYNAMIC` segment.
+fn with_many_loads(count: u64) -> Vec<u8> {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+
+    for i in 1..=count {
+        let prot = if (i % 2) == 0 { PF_RW } else { PF_RX };
+        b.segment(PT_LOAD, prot, i * 0x1000, 0x1000, &ret_imm32(i as u32));
+    }
+
+    b.build()
+}
+
+#[test]
+fn many_load_segments_are_fine() {
+    let mut loaded = load(&with_many_loads(12)).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    let ready = loaded.try_reloc(base, Some(os::protection_fn), None)
+                      .map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+
+    // The dynamic table's page, and then one range per page.
+    assert_eq!(ready.effective_protection_map().count(), 1 + 12);
+}
+
+#[cfg(not(feature = "alloc"))]
+#[test]
+fn too_many_load_segments_are_rejected() {
+    let err = load(&with_many_loads(20)).err();
+
+    // All of the `PT_LOAD`s, plus the dynamic table's `PT_LOAD` and `PT_DYNAMIC`.
+    assert_eq!(err, Some(ElfError::Load(LoadElfError::TooManySegments { seen: 22, max: 16 })));
+    assert_eq!(err.unwrap().to_string(),
+               "Error trying to load an ELF: The program headers describe 22 segments, but at \
+                most 16 are supported");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn alloc_lifts_the_segment_limit() {
+    let mut loaded = load(&with_many_loads(40)).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    let ready = loaded.try_reloc(base, None, None)
+                      .map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+
+    assert_eq!(ready.effective_protection_map().count(), 1 + 40);
+
+    // That's more protection ranges than a boot header can hold.
+    let mut header = BootHeader::default();
+    ready.to_bootable(&mut header);
+
+    assert!(!header.is_valid());
+}
+
+/// Builds an ELF whose code segment is writable, too, and which wants an executable stack.
+fn with_rwx_code() -> Vec<u8> {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RWX, 0x1000, 0x1000, &ret_imm32(42));
+    b.phdr(Phdr { p_type: PT_GNU_STACK, p_flags: PF_RWX, ..Phdr::default() });
+    b.entry(0x1000);
+    b.build()
+}
+
+#[test]
+fn rwx_segments_are_downgraded_by_default() {
+    let loaded = load(&with_rwx_code()).expect("loading failed");
+    let map    = loaded.effective_protection_map().collect::<Vec<_>>();
+
+    assert_eq!(map[1], (0x1000 .. 0x2000, SegmentProtection::RX));
+}
+
+#[test]
+fn rwx_segments_are_rejected_when_strict() {
+    assert_eq!(Elf::try_parse_strict(&with_rwx_code()).err(),
+               Some(ParseElfError::WritableAndExecutable));
+}
+
+#[test]
+fn unknown_os_abis_are_rejected_when_strict() {
+    let mut b = ElfBuilder::new();
+    b.dynamic(0x0000, &[]);
+
+    let mut raw = b.build();
+
+    for (os_abi, strict) in [(0, true), (3, true), (9, false), (255, false)] {
+        raw[7] = os_abi;
+
+        let res = Elf::try_parse_strict(&raw).map(|elf| elf.os_abi());
+
+        assert_eq!(Elf::try_parse(&raw).map(|elf| elf.os_abi()), Ok(os_abi));
+        assert_eq!(res, if strict { Ok(os_abi) } else { Err(ParseElfError::BadOsAbi) });
+    }
+}
+
+#[test]
+fn executable_stacks_are_fine_when_strict() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.phdr(Phdr { p_type: PT_GNU_STACK, p_flags: PF_RWX, ..Phdr::default() });
+    b.entry(0x1000);
+
+    assert!(Elf::try_parse_strict(&b.build()).is_ok());
+}
+
+#[test]
+fn file_header_fields_are_kept() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.e_flags = 0x0005; // `EF_RISCV_RVC | EF_RISCV_FLOAT_ABI_DOUBLE`, not that anyone checks.
+
+    let raw  = b.build();
+    let elf  = Elf::try_parse(&raw).expect("parsing failed");
+    let lazy = Elf::try_parse_lazy(&raw).expect("lazily parsing failed")
+                   .validate().expect("validating failed");
+
+    for elf in [elf, lazy].iter() {
+        assert_eq!(elf.elf_type(), ET_DYN);
+        assert_eq!(elf.machine(),  EM_X86_64);
+        assert_eq!(elf.flags(),    0x0005);
+    }
+}
+
+#[test]
+fn unaligned_relro_is_shrunk_to_whole_pages() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x2000, 0x2000, &[0xAA; 0x10]);
+    b.phdr(Phdr {
+        p_type:  PT_GNU_RELRO,
+        p_flags: PF_R,
+        p_vaddr: 0x2100,
+        p_memsz: 0x0F80,
+        p_align: 1,
+        ..Phdr::default()
+    });
+    b.entry(0x1000);
+
+    let mut loaded = load(&b.build()).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    // The start is rounded down, but the end too, as the rest of the page holds more data.
+    assert_eq!(loaded.effective_protection_map().collect::<Vec<_>>(), [
+        (0x0000 .. 0x1000, SegmentProtection::RW),
+        (0x1000 .. 0x2000, SegmentProtection::RX),
+        (0x2000 .. 0x3000, SegmentProtection::RO),
+        (0x3000 .. 0x4000, SegmentProtection::RW),
+    ]);
+
+    // `mprotect` refuses unaligned ranges, so this only works if they are rounded.
+    let ready = loaded.try_reloc(base, Some(os::protection_fn), None)
+                      .map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+
+    // The segments themselves are reported as the program headers ask for them.
+    let relro = ready.segments().last().expect("no segments");
+    assert_eq!(relro.p_range.start - (ready.p_mem().as_ptr() as usize), 0x2100);
+
+    // Writing the data sharing the last `GNU_RELRO` page must not fault.
+    let tail = (ready.p_mem().as_ptr() as usize + 0x3080) as *mut u8;
+
+    unsafe {
+        tail.write_volatile(0xBB);
+        assert_eq!(tail.read_volatile(), 0xBB);
+    }
+}
+
+#[test]
+fn relro_within_a_single_page_is_left_alone() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x2000, 0x1000, &[0xAA; 0x10]);
+    b.phdr(Phdr {
+        p_type:  PT_GNU_RELRO,
+        p_flags: PF_R,
+        p_vaddr: 0x2000,
+        p_memsz: 0x0800,
+        p_align: 1,
+        ..Phdr::default()
+    });
+
+    let loaded = load(&b.build()).expect("loading failed");
+
+    assert_eq!(loaded.effective_protection_map().collect::<Vec<_>>(), [
+        (0x0000 .. 0x1000, SegmentProtection::RW),
+        (0x1000 .. 0x2000, SegmentProtection::RX),
+        (0x2000 .. 0x3000, SegmentProtection::RW),
+    ]);
+}
+
+#[test]
+fn relro_is_rounded_to_the_host_page_size() {
+    let mut b = ElfBuilder::new();
+
+    let code = b.blob(&ret_imm32(42));
+    let data = b.blob(&[0xAA; 0x10]);
+
+    b.dynamic(0x0000, &[]);
+    b.phdr(Phdr {
+        p_type: PT_LOAD, p_flags: PF_RX,
+        p_offset: code, p_vaddr: 0x10000, p_paddr: 0x10000,
+        p_filesz: 6, p_memsz: 0x10000,
+        p_align: 0x10000,
+    });
+    b.phdr(Phdr {
+        p_type: PT_LOAD, p_flags: PF_RW,
+        p_offset: data, p_vaddr: 0x20000, p_paddr: 0x20000,
+        p_filesz: 0x10, p_memsz: 0x10000,
+        p_align: 0x10000,
+    });
+    b.phdr(Phdr {
+        p_type:  PT_GNU_RELRO,
+        p_flags: PF_R,
+        p_vaddr: 0x21100,
+        p_memsz: 0x8000,
+        p_align: 1,
+        ..Phdr::default()
+    });
+    b.entry(0x10000);
+
+    let mut loaded = load(&b.build()).expect("loading failed");
+
+    assert_eq!(loaded.mem_align(), 0x10000);
+
+    // 4KiB pages by default, no matter how large `mem_align` is.
+    assert_eq!(loaded.effective_protection_map().collect::<Vec<_>>(), [
+        (0x00000 .. 0x10000, SegmentProtection::RW),
+        (0x10000 .. 0x20000, SegmentProtection::RX),
+        (0x20000 .. 0x21000, SegmentProtection::RW),
+        (0x21000 .. 0x29000, SegmentProtection::RO),
+        (0x29000 .. 0x30000, SegmentProtection::RW),
+    ]);
+
+    // E.g. 16KiB pages on some AArch64 hosts.
+    loaded.set_page_size(0x4000);
+
+    assert_eq!(loaded.effective_protection_map().collect::<Vec<_>>(), [
+        (0x00000 .. 0x10000, SegmentProtection::RW),
+        (0x10000 .. 0x20000, SegmentProtection::RX),
+        (0x20000 .. 0x28000, SegmentProtection::RO),
+        (0x28000 .. 0x30000, SegmentProtection::RW),
+    ]);
+}
+
+#[test]
+fn reloc_fingerprint_only_covers_the_reloc_layout() {
+    let build = |answer: u32, relasz: u64, strsz: u64| {
+        let mut b = ElfBuilder::new();
+
+        b.dynamic(0x0000, &[
+            (DT_RELA,   0x2000), (DT_RELASZ, relasz), (DT_RELAENT, 24),
+            (DT_STRTAB, 0x2000), (DT_STRSZ,  strsz),
+        ]);
+        b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(answer));
+        b.segment(PT_LOAD, PF_RW, 0x2000, 0x1000, &[0; 0x60]);
+        b.entry(0x1000);
+        b.build()
+    };
+
+    let fingerprint = |raw: &[u8]| {
+        Elf::try_parse(raw).expect("parsing failed").reloc_fingerprint()
+    };
+
+    // Neither the code nor unrelated `DYNAMIC` tags matter...
+    assert_eq!(fingerprint(&build(42, 0x30, 1)), fingerprint(&build(42, 0x30, 1)));
+    assert_eq!(fingerprint(&build(42, 0x30, 1)), fingerprint(&build(43, 0x30, 1)));
+    assert_eq!(fingerprint(&build(42, 0x30, 1)), fingerprint(&build(42, 0x30, 9)));
+
+    // ...but the re-location tables do.
+    assert_ne!(fingerprint(&build(42, 0x30, 1)), fingerprint(&build(42, 0x48, 1)));
+}
+
+#[test]
+fn content_digest_only_covers_loaded_bytes() {
+    let build = |answer: u32, flags: u32| {
+        let mut b = ElfBuilder::new();
+
+        b.e_flags = flags;
+        b.dynamic(0x0000, &[]);
+        b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(answer));
+        b.entry(0x1000);
+        b.build()
+    };
+
+    let digest = |raw: &[u8]| Elf::try_parse(raw).expect("parsing failed").content_digest();
+
+    assert_eq!(digest(&build(42, 0)), digest(&build(42, 0)));
+    assert_eq!(digest(&build(42, 0)), digest(&build(42, 7)));
+    assert_ne!(digest(&build(42, 0)), digest(&build(43, 0)));
+}
+
+/// Builds an ELF whose code segment holds 16 bytes of code and a zero-filled tail.
+fn with_entry(entry: u64) -> Vec<u8> {
+    let mut code = ret_imm32(42);
+    code.resize(16, 0xCC);
+
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &code);
+    b.entry(entry);
+    b.build()
+}
+
+#[test]
+fn entry_in_the_zero_filled_tail_is_rejected() {
+    assert!(Elf::try_parse(&with_entry(0x1000)).is_ok());
+    assert!(Elf::try_parse(&with_entry(0x100C)).is_ok());
+    assert_eq!(Elf::try_parse(&with_entry(0x1010)).err(), Some(ParseElfError::BadEntry));
+    assert_eq!(Elf::try_parse(&with_entry(0x1800)).err(), Some(ParseElfError::BadEntry));
+}
+
+#[test]
+fn misaligned_entry_is_rejected_where_instructions_are_aligned() {
+    const EM_AARCH64: u16 = 183;
+
+    let aarch64 = |entry| {
+        let mut raw = with_entry(entry);
+        raw[0x12..0x14].copy_from_slice(&EM_AARCH64.to_le_bytes());
+        raw
+    };
+
+    // It's the ELF's ISA that counts, not the one parsing it.
+    assert!(Elf::try_parse_foreign(&with_entry(0x1002)).is_ok());
+    assert!(Elf::try_parse_foreign(&aarch64(0x1004)).is_ok());
+    assert_eq!(Elf::try_parse_foreign(&aarch64(0x1002)).err(), Some(ParseElfError::BadEntry));
+}
+
+#[test]
+fn data_only_objects_need_no_entry_and_no_code() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RW, 0x1000, 0x1000, &[0xAA; 0x10]);
+
+    let raw = b.build();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    assert_eq!(elf.entry(), 0);
+    assert_eq!(elf.launch_kind(), LaunchKind::Library);
+
+    let mut loaded = load(&raw).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    let ready = loaded.try_reloc(base, Some(os::protection_fn), None)
+                      .map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+
+    assert_eq!(ready.try_p_entry(), None);
+    assert_eq!(ready.try_v_entry(), None);
+
+    // The very same ELF with an entry point needs code for it.
+    b.entry(0x1000);
+    assert_eq!(Elf::try_parse(&b.build()).err(), Some(ParseElfError::BadEntry));
+}
+
+#[test]
+fn entry_points_are_there_if_there_is_one() {
+    let mut loaded = load(&with_entry(0x1000)).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    let ready = loaded.try_reloc(base, None, None)
+                      .map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+
+    assert_eq!(ready.try_p_entry(), Some(ready.p_entry()));
+    assert_eq!(ready.try_v_entry(), Some(base.wrapping_add(0x1000) as *const ()));
+}
+
+#[test]
+fn segment_at_finds_the_segment_of_an_address() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x3000, 0x1000, &[0xAA; 0x10]);
+    b.phdr(Phdr {
+        p_type:  PT_GNU_RELRO,
+        p_flags: PF_R,
+        p_vaddr: 0x3000,
+        p_memsz: 0x0100,
+        p_align: 1,
+        ..Phdr::default()
+    });
+    b.entry(0x1000);
+
+    let mut loaded = load(&b.build()).expect("loading failed");
+    let v_base     = 0x7A00_0000_0000_usize;
+    let p_base     = loaded.loader_base() as usize;
+
+    // Before re-locating, addresses are the loader's.
+    let at = |off: usize| loaded.segment_at(p_base + off).map(|seg| (seg.kind, seg.protection));
+
+    assert_eq!(at(0x1004), Some((SegmentKind::Load,  SegmentProtection::RX)));
+    assert_eq!(at(0x2000), None);
+    assert_eq!(at(0x3080), Some((SegmentKind::Relro, SegmentProtection::RO)));
+    assert_eq!(at(0x3100), Some((SegmentKind::Load,  SegmentProtection::RW)));
+
+    let ready = loaded.try_reloc(v_base as *mut u8, None, None)
+                      .map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+
+    let at = |off: usize| ready.segment_at(v_base + off).map(|seg| (seg.kind, seg.protection));
+
+    assert_eq!(at(0x1004), Some((SegmentKind::Load,  SegmentProtection::RX)));
+    assert_eq!(at(0x2000), None);
+    assert_eq!(at(0x3080), Some((SegmentKind::Relro, SegmentProtection::RO)));
+    assert_eq!(at(0x3100), Some((SegmentKind::Load,  SegmentProtection::RW)));
+    assert_eq!(at(0x4000), None);
+
+    let code = ready.segment_at(v_base + 0x1004).expect("no code segment");
+    assert_eq!(code.v_range, (v_base + 0x1000) .. (v_base + 0x2000));
+}
+
+#[test]
+fn assume_zeroed_skips_zero_filling() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RW, 0x1000, 0x1000, &[0xAA; 0x10]);
+
+    let raw = b.build();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+    let len = elf.mem_len() as usize;
+
+    // Garbage in the `.bss` tail shows that nothing got zero-filled.
+    let mem = os::alloc_aligned(len, elf.mem_align() as usize);
+    mem[0x1800] = 0xCC;
+
+    let opts     = LoadOptions { assume_zeroed: true, ..LoadOptions::default() };
+    let loaded   = elf.try_load_with(mem, opts).expect("loading failed");
+    let (mem, _) = loaded.into_parts();
+
+    assert_eq!(mem[0x1000], 0xAA);
+    assert_eq!(mem[0x1800], 0xCC);
+
+    mem[0x1800] = 0xCC;
+    let (mem, _) = elf.try_load(mem).expect("loading failed").into_parts();
+
+    assert_eq!(mem[0x1800], 0x00);
+}
+
+#[test]
+fn alignments_must_be_powers_of_two() {
+    let with_align = |p_align: u64| {
+        let mut b = ElfBuilder::new();
+
+        b.dynamic(0x0000, &[]);
+        b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+        b.phdr(Phdr { p_type: PT_GNU_STACK, p_flags: PF_RW, p_align, ..Phdr::default() });
+        b.entry(0x1000);
+        b.build()
+    };
+
+    for p_align in [0, 1, 16, 0x1000, 0x8000_0000] {
+        assert!(Elf::try_parse(&with_align(p_align)).is_ok(), "p_align {:#x}", p_align);
+    }
+
+    for p_align in [3, 0x1800, 0xFFFF_FFFF] {
+        assert_eq!(Elf::try_parse(&with_align(p_align)).err(), Some(ParseElfError::BadAlignment));
+    }
+
+    assert_eq!(Elf::try_parse(&with_align(0x8000_0000)).map(|elf| elf.mem_align()).ok(),
+               Some(0x8000_0000));
+}
+
+#[test]
+fn mem_len_and_mem_align_come_in_all_sizes() {
+    let raw = with_entry(0x1000);
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    assert_eq!(elf.mem_len_usize(),   elf.mem_len()   as usize);
+    assert_eq!(elf.mem_align_usize(), elf.mem_align() as usize);
+    assert_eq!(1 << elf.mem_align_log2(), elf.mem_align());
+}
+
+#[test]
+fn program_headers_keep_their_physical_address() {
+    let with_paddr = |p_paddr: u64| {
+        let mut b = ElfBuilder::new();
+
+        b.dynamic(0x0000, &[]);
+        b.phdr(Phdr {
+            p_type:   PT_LOAD,
+            p_flags:  PF_RX,
+            p_offset: 0x1000,
+            p_vaddr:  0x1000,
+            p_paddr,
+            p_memsz:  0x1000,
+            p_align:  0x1000,
+            ..Phdr::default()
+        });
+        b.build()
+    };
+
+    let raw = with_paddr(0x20_0000);
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+    let ph  = elf.program_headers().find(|ph| ph.load_range.start == 0x1000).unwrap();
+
+    let phys = ph.phys_range.expect("no physical range");
+
+    assert_eq!((phys.start,          phys.len),          (0x20_0000, 0x1000));
+    assert_eq!((ph.load_range.start, ph.load_range.len), (0x1000,    0x1000));
+
+    // Physical addresses are of no concern to this loader, so these are no errors.
+    let raw = with_paddr(0xFFFF_F800);
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    assert!(elf.program_headers().all(|ph| ph.phys_range.is_none() | (ph.load_range.start == 0)));
+}
+
+#[test]
+fn dynamic_segments_must_be_loaded() {
+    let with_dyn_at = |vaddr: u64| {
+        let mut b = ElfBuilder::new();
+        let off   = b.blob(&[0; 16]);
+
+        b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+        b.segment(PT_LOAD, PF_RW, 0x2000, 0x1000, &[]);
+        b.phdr(Phdr {
+            p_type: PT_DYNAMIC, p_flags: PF_RW,
+            p_offset: off, p_vaddr: vaddr, p_paddr: vaddr,
+            p_filesz: 16, p_memsz: 16,
+            p_align: 8,
+        });
+        b.entry(0x1000);
+        b.build()
+    };
+
+    assert!(load(&with_dyn_at(0x2000)).is_ok());
+
+    // Straddling the end of a `PT_LOAD` is just as bad as missing them all.
+    assert_eq!(load(&with_dyn_at(0x2FF8)).err(),
+               Some(ElfError::Load(LoadElfError::DynamicNotLoadable)));
+}
+
+#[test]
+fn debug_output_summarises_the_image() {
+    let raw = with_entry(0x1000);
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+    let out = format!("{:?}", elf);
+
+    assert!(out.starts_with("Elf { machine: 62, elf_type: 3, entry: 0x1000, mem_len: 0x2000"),
+            "{}", out);
+    assert!(out.contains("Load RX 0x1000..0x2000"), "{}", out);
+
+    let loaded = load(&raw).expect("loading failed");
+    assert!(format!("{:?}", loaded).contains("Dynamic RW 0x0.."));
+
+    let v_base = 0x7A00_0000_0000_usize;
+    let ready  = loaded.try_reloc(v_base as *mut u8, None, None)
+                       .map_err(|(_, e)| e)
+                       .expect("re-locating failed");
+
+    assert!(format!("{:#?}", ready).contains("Load RX 0x7a0000001000..0x7a0000002000,\n"));
+}
+
+#[test]
+fn overlapping_loads_are_rejected() {
+    let with_data_at = |vaddr: u64, kind: u32| {
+        let mut b = ElfBuilder::new();
+
+        b.dynamic(0x0000, &[]);
+        b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+        b.segment(kind,    PF_R,  vaddr,  0x1000, &[0xAA; 16]);
+        b.entry(0x1000);
+        b.build()
+    };
+
+    for vaddr in [0x1800, 0x0800, 0x1000] {
+        assert_eq!(Elf::try_parse(&with_data_at(vaddr, PT_LOAD)).err(),
+                   Some(ParseElfError::OverlappingSegments), "p_vaddr {:#x}", vaddr);
+    }
+
+    // Merely touching is fine, and `PT_GNU_RELRO` overlaps loads by design.
+    assert!(Elf::try_parse(&with_data_at(0x2000, PT_LOAD)).is_ok());
+    assert!(Elf::try_parse(&with_data_at(0x1000, PT_GNU_RELRO)).is_ok());
+}
+
+#[test]
+fn used_len_ignores_spare_buffer_space() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x2000, 0x1800, &[0xAA; 16]);
+
+    let raw    = b.build();
+    let elf    = Elf::try_parse(&raw).expect("parsing failed");
+    let mem    = os::alloc_aligned(elf.mem_len_usize() + 0x3000, elf.mem_align_usize());
+    let loaded = elf.try_load(mem).expect("loading failed");
+
+    assert_eq!(loaded.mem_len(),  0x3800 + 0x3000);
+    assert_eq!(loaded.used_len(), 0x3800);
+}
+
+#[test]
+fn buffers_may_be_larger_than_4gib() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.phdr(Phdr {
+        p_type: PT_LOAD, p_flags: PF_R,
+        p_offset: 0, p_vaddr: 0x2000, p_paddr: 0x2000, p_filesz: 0x10, p_memsz: 0x1000,
+        p_align: 0x1000,
+    });
+    b.entry(0x1000);
+
+    let small = b.build();
+    let ph_at = |idx: usize| 0x40 + (idx * 0x38);
+
+    // Like an ELF with a huge debug section at its end. Untouched pages cost nothing.
+    let huge = os::alloc_aligned(0x1_0000_1000, 0x1000);
+    huge[..small.len()].copy_from_slice(&small);
+
+    let elf = Elf::try_parse(huge).expect("parsing failed");
+    let mem = os::alloc_aligned(elf.mem_len_usize(), elf.mem_align_usize());
+
+    elf.try_load(mem).expect("loading failed");
+
+    // Data past the first 4GiB is another story, even if it's in the buffer.
+    huge[(ph_at(3) + 8)..(ph_at(3) + 16)].copy_from_slice(&0xFFFF_FFF8_u64.to_le_bytes());
+    assert_eq!(Elf::try_parse(huge).err(), Some(ParseElfError::BadPhRange));
+
+    huge[(ph_at(3) + 8)..(ph_at(3) + 16)].copy_from_slice(&0x1_0000_0000_u64.to_le_bytes());
+    assert_eq!(Elf::try_parse(huge).err(), Some(ParseElfError::BadPhRange));
+}
@@ -0,0 +1,51 @@
+use elf_loader::*;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+fn dyn_flags(dyns: &[(u64, u64)]) -> DynFlags {
+    let mut b = ElfBuilder::new();
+    b.dynamic(0x0000, dyns);
+
+    let raw = b.build();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    elf.try_load(mem).expect("loading failed").dyn_flags()
+}
+
+
+
+#[test]
+fn no_flags_by_default() {
+    assert_eq!(dyn_flags(&[]), DynFlags::default());
+}
+
+#[test]
+fn flags_and_flags_1_are_read() {
+    let flags = dyn_flags(&[(DT_FLAGS, 0x08 | 0x04), (DT_FLAGS_1, 0x0800_0008)]);
+
+    assert_eq!(flags.flags(),   0x0C);
+    assert_eq!(flags.flags_1(), 0x0800_0008);
+    assert!(flags.bind_now());
+    assert!(flags.text_rel());
+    assert!(flags.no_delete());
+    assert!(flags.pie());
+    assert!(!flags.symbolic());
+    assert!(!flags.static_tls());
+}
+
+#[test]
+fn legacy_tags_are_folded_in() {
+    let flags = dyn_flags(&[(DT_TEXTREL, 0), (DT_FLAGS_1, 0x01)]);
+
+    assert!(flags.text_rel());
+    assert!(flags.bind_now());
+    assert_eq!(flags.flags(), 0x04);
+}
@@ -0,0 +1,57 @@
+use elf_loader::*;
+
+
+
+mod os;
+
+
+
+/// `symbols.elf`, but built for the x32 ABI, which uses ELF32.
+static SYMBOLS_X32: &[u8] = include_bytes!("./symbols_x32.elf");
+
+
+
+#[cfg(not(feature = "elf32"))]
+#[test]
+fn elf32_is_rejected_by_default() {
+    let buf = Vec::from(SYMBOLS_X32);
+
+    assert_eq!(Elf::try_parse(&buf[..]).err(), Some(ParseElfError::NotElf64));
+}
+
+#[cfg(feature = "elf32")]
+#[test]
+fn elf32_is_parsed() {
+    let buf = Vec::from(SYMBOLS_X32);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `symbols_x32.elf` failed");
+
+    let kinds = elf.program_headers().map(|ph| ph.kind).collect::<Vec<_>>();
+
+    assert_eq!(kinds, [
+        SegmentKind::Load, SegmentKind::Load, SegmentKind::Load, SegmentKind::Load,
+        SegmentKind::Dynamic, SegmentKind::Unsupported, SegmentKind::Unsupported,
+        SegmentKind::Relro,
+    ]);
+    assert_eq!(elf.mem_len(),     0x4004);
+    assert_eq!(elf.mem_align(),   0x1000);
+    assert_eq!(elf.soname(),      Some("libsymbols32.so"));
+    assert_eq!(elf.launch_kind(), LaunchKind::Library);
+}
+
+#[cfg(feature = "elf32")]
+#[test]
+fn elf32_is_loaded_but_not_relocated() {
+    let buf = Vec::from(SYMBOLS_X32);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `symbols_x32.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `symbols_x32.elf` failed");
+    let base       = loaded.loader_base();
+    let p_mem      = unsafe { std::slice::from_raw_parts(base, loaded.mem_len()) };
+
+    // `.text` lies at the same offset in the file and in memory.
+    assert_eq!(p_mem[0x1000..0x1014], buf[0x1000..0x1014]);
+
+    assert_eq!(loaded.try_reloc(base, None, None).err().map(|(_, e)| e),
+               Some(RelocElfError::UnsupportedElfClass));
+}
@@ -5,3 +5,15 @@
 cargo xbuild --target ./x86_64-unknown-none.json --release
 cp ./target/x86_64-unknown-none/release/simple ../simple.elf
 cp ./target/x86_64-unknown-none/release/bss_rodata_data ../bss_rodata_data.elf
+
+# Plain shared objects, built by the system's C tool-chain.
+gcc -shared -nostdlib -fPIC -O2 -Wl,--hash-style=gnu -Wl,-soname,libsymbols.so -o ../symbols.elf ./symbols.c
+gcc -shared -nostdlib -fPIC -O2 -Wl,--hash-style=sysv -o ../symbols_sysv.elf ./symbols.c
+gcc -shared -nostdlib -fPIC -O2 -Wl,--hash-style=gnu -o ../needs.elf ./needs.c ../symbols.elf
+gcc -shared -nostdlib -fPIC -O2 -ftls-model=initial-exec -Wl,--hash-style=gnu -o ../tls.elf ./tls.c
+gcc -shared -nostdlib -fPIC -O2 -Wl,--hash-style=gnu -Wl,-Bsymbolic -Wl,-z,pack-relative-relocs -o ../relr.elf ./relr.c
+gcc -mx32 -shared -nostdlib -fPIC -O2 -Wl,--hash-style=gnu -Wl,-soname,libsymbols32.so -o ../symbols_x32.elf ./symbols.c
+gcc -shared -nostdlib -fPIC -O2 -Wl,--hash-style=gnu -o ../ifunc.elf ./ifunc.c
+gcc -shared -nostdlib -fPIC -O2 -Wl,--hash-style=both -o ../symbols_both.elf ./symbols.c
+gcc -shared -nostdlib -fPIC -O2 -Wl,--hash-style=gnu -o ../weak.elf ./weak.c
+gcc -shared -nostdlib -fPIC -O2 -Wl,--hash-style=gnu -Wl,--build-id=none -Wl,-z,noseparate-code -Wl,-z,norelro -Wl,-T,./shared_page.ld -o ../shared_page.elf ./shared_page.c
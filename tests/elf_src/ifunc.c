@@ -0,0 +1,11 @@
+static int answer_slow(void) { return 41; }
+static int answer_fast(void) { return 42; }
+
+static int fast = 1;
+
+// Picks an implementation at load time, like glibc does for `memcpy` and friends.
+static int (*resolve_answer(void))(void) { return fast ? answer_fast : answer_slow; }
+
+__attribute__((visibility("hidden"), ifunc("resolve_answer"))) int answer(void);
+
+int call_answer(void) { return answer(); }
@@ -0,0 +1,4 @@
+int answer(void);
+extern int counter;
+
+int call_answer(void) { return answer() + counter - 7; }
@@ -0,0 +1,6 @@
+int counter = 42;
+
+// Lands in `.rodata`, whose segment shares a page with `.text`.
+const int answer[64] = { 7 };
+
+int bump(void) { return counter++ + answer[0]; }
@@ -0,0 +1,3 @@
+int answer(void) { return 42; }
+int twice(int x) { return 2 * x; }
+int counter = 7;
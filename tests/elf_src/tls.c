@@ -0,0 +1,5 @@
+__thread int tls_data = 5;
+__thread int tls_bss;
+
+int *data_ptr(void) { return &tls_data; }
+int *bss_ptr(void)  { return &tls_bss;  }
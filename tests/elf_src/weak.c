@@ -0,0 +1,4 @@
+// An optional hook, like `__gmon_start__`. Nobody has to define it.
+extern int hook(void) __attribute__((weak));
+
+int call_hook(void) { return hook ? hook() : 42; }
@@ -0,0 +1,27 @@
+#![cfg(feature = "std")]
+
+use elf_loader::*;
+use std::error::Error;
+
+
+
+#[test]
+fn elf_errors_have_their_sub_error_as_source() {
+    let err: Box<dyn Error> = Box::new(ElfError::from(LoadElfError::BadBufferSize));
+    let src = err.source().expect("no source");
+
+    assert_eq!(src.to_string(), LoadElfError::BadBufferSize.as_str());
+    assert!(src.source().is_none());
+}
+
+#[test]
+fn errors_compose_with_question_marks() {
+    fn parse(raw: &[u8]) -> Result<(), Box<dyn Error>> {
+        Elf::try_parse(raw)?;
+        Ok(())
+    }
+
+    let err = parse(&[]).expect_err("parsing nothing succeeded");
+
+    assert!(err.downcast_ref::<ParseElfError>().is_some());
+}
@@ -0,0 +1,115 @@
+use elf_loader::*;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+const ET_REL:  u16 = 1;
+const ET_EXEC: u16 = 2;
+
+/// Builds a static, fixed-address executable linked at `base`.
+fn exec_at(base: u64) -> Vec<u8> {
+    let mut b = ElfBuilder::new();
+
+    b.e_type = ET_EXEC;
+    b.segment(PT_LOAD, PF_RX, base,          0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, base + 0x1000, 0x0800, &[1, 2, 3, 4]);
+    b.phdr(Phdr { p_type: PT_GNU_STACK, p_flags: PF_RW, ..Phdr::default() });
+    b.entry(base);
+    b.build()
+}
+
+
+
+#[test]
+fn exec_needs_opting_in() {
+    assert_eq!(Elf::try_parse(&exec_at(0x40_0000)).err(), Some(ParseElfError::NotPic));
+}
+
+#[test]
+fn object_files_are_no_executables() {
+    let mut raw = exec_at(0x40_0000);
+    raw[0x10] = ET_REL as u8;
+
+    assert_eq!(Elf::try_parse     (&raw).err(), Some(ParseElfError::NotExecutable));
+    assert_eq!(Elf::try_parse_exec(&raw).err(), Some(ParseElfError::NotExecutable));
+}
+
+#[test]
+fn exec_is_relative_to_its_link_base() {
+    let raw = exec_at(0xFFFF_FFFF_8000_0000);
+    let elf = Elf::try_parse_exec(&raw).expect("parsing failed");
+
+    assert_eq!(elf.elf_type(),   ET_EXEC);
+    assert_eq!(elf.fixed_base(), Some(0xFFFF_FFFF_8000_0000));
+    assert_eq!(elf.mem_len(),    0x1800);
+    assert_eq!(elf.entry(),      0);
+    assert_eq!(elf.launch_kind(), LaunchKind::FixedExec);
+}
+
+#[test]
+fn pie_has_no_fixed_base() {
+    let mut b = ElfBuilder::new();
+    b.dynamic(0x0000, &[]);
+
+    let raw = b.build();
+    let elf = Elf::try_parse_exec(&raw).expect("parsing failed");
+
+    assert_eq!(elf.fixed_base(), None);
+}
+
+#[test]
+fn exec_must_fit_in_4gib_above_its_base() {
+    let mut b = ElfBuilder::new();
+
+    b.e_type = ET_EXEC;
+    b.segment(PT_LOAD, PF_RX, 0x40_0000,                0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x40_0000 + 0x1_0000_0000, 0x1000, &[1, 2, 3, 4]);
+    b.entry(0x40_0000);
+
+    assert_eq!(Elf::try_parse_exec(&b.build()).err(), Some(ParseElfError::BadVmemRange));
+}
+
+#[test]
+fn exec_only_relocates_to_its_link_base() {
+    let raw = exec_at(0x40_0000);
+    let elf = Elf::try_parse_exec(&raw).expect("parsing failed");
+
+    let load = || {
+        let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+        elf.try_load(mem).expect("loading failed")
+    };
+
+    let err = load().try_reloc(0x80_0000 as *mut u8, None, None).err().map(|(_, e)| e);
+    assert_eq!(err, Some(RelocElfError::BadFixedBase));
+
+    let ready = load().try_reloc(0x40_0000 as *mut u8, None, None)
+                      .map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+
+    assert_eq!(ready.v_entry() as usize, 0x40_0000);
+    assert_eq!(&ready.p_mem()[0x1000..0x1004], &[1, 2, 3, 4]);
+}
+
+#[test]
+fn dynamic_exec_is_rejected() {
+    let mut b = ElfBuilder::new();
+
+    b.e_type = ET_EXEC;
+    b.dynamic(0x40_0000, &[]);
+
+    let raw = b.build();
+    let elf = Elf::try_parse_exec(&raw).expect("parsing failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let err = elf.try_load(mem).expect("loading failed")
+                 .try_reloc(0x40_0000 as *mut u8, None, None)
+                 .err().map(|(_, e)| e);
+
+    assert_eq!(err, Some(RelocElfError::DynamicFixedBase));
+}
@@ -0,0 +1,124 @@
+use elf_loader::*;
+
+
+
+mod os;
+
+
+
+static SYMBOLS: &[u8] = include_bytes!("./symbols.elf");
+
+const PT_DYNAMIC: u32 = 2;
+
+
+
+fn swap_at(raw: &mut [u8], off: usize, len: usize) {
+    raw[off..(off + len)].reverse();
+}
+
+/// Turns `symbols.elf` into big-endian ELF data, as far as the ELF header, the program
+/// headers and the `PT_DYNAMIC` segment are concerned.
+fn big_endian_symbols() -> Vec<u8> {
+    let mut raw = Vec::from(SYMBOLS);
+    let read    = |raw: &[u8], off: usize| {
+        let mut w = [0; 8];
+        w.copy_from_slice(&raw[off..(off + 8)]);
+        u64::from_le_bytes(w) as usize
+    };
+
+    let phoff = read(&raw, 0x20);
+    let phnum = u16::from_le_bytes([raw[0x38], raw[0x39]]) as usize;
+
+    for ph in (0..phnum).map(|i| phoff + (56 * i)) {
+        if u32::from_le_bytes([raw[ph], raw[ph + 1], raw[ph + 2], raw[ph + 3]]) == PT_DYNAMIC {
+            let (off, len) = (read(&raw, ph + 8), read(&raw, ph + 32));
+
+            for field in (off..(off + len)).step_by(8) { swap_at(&mut raw, field, 8); }
+        }
+
+        for (field, len) in [(0, 4), (4, 4), (8, 8), (16, 8), (24, 8), (32, 8), (40, 8), (48, 8)] {
+            swap_at(&mut raw, ph + field, len);
+        }
+    }
+
+    let header_fields = [
+        (0x10, 2), (0x12, 2), (0x14, 4), (0x18, 8), (0x20, 8), (0x28, 8), (0x30, 4),
+        (0x34, 2), (0x36, 2), (0x38, 2), (0x3A, 2), (0x3C, 2), (0x3E, 2),
+    ];
+
+    for (field, len) in header_fields { swap_at(&mut raw, field, len); }
+
+    raw[5] = 2; // `ELFDATA2MSB`
+    raw
+}
+
+
+
+#[test]
+fn foreign_byte_order_is_rejected_by_try_parse() {
+    let raw = big_endian_symbols();
+
+    assert_eq!(Elf::try_parse(&raw[..]).err(), Some(ParseElfError::BadEndian));
+}
+
+#[test]
+fn foreign_byte_order_is_swapped_on_access() {
+    let native  = Vec::from(SYMBOLS);
+    let foreign = big_endian_symbols();
+
+    let native  = Elf::try_parse(&native[..]).expect("Parsing `symbols.elf` failed");
+    let foreign = Elf::try_parse_foreign(&foreign[..])
+                      .expect("Parsing swapped `symbols.elf` failed");
+
+    let ranges = |elf: &Elf| elf.program_headers()
+        .map(|ph| (ph.kind, ph.protection, ph.load_range.start, ph.load_range.len))
+        .collect::<Vec<_>>();
+
+    assert_eq!(foreign.mem_len(),           native.mem_len());
+    assert_eq!(foreign.mem_align(),         native.mem_align());
+    assert_eq!(foreign.entry(),             native.entry());
+    assert_eq!(ranges(&foreign),            ranges(&native));
+    assert_eq!(foreign.soname(),            Some("libsymbols.so"));
+    assert_eq!(foreign.reloc_fingerprint(), native.reloc_fingerprint());
+}
+
+#[test]
+fn foreign_elfs_are_not_loaded() {
+    let raw = big_endian_symbols();
+    let elf = Elf::try_parse_foreign(&raw[..]).expect("Parsing swapped `symbols.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    assert_eq!(elf.try_load(mem).err(), Some(LoadElfError::ForeignElf));
+}
+
+#[test]
+fn other_isas_may_be_accepted_for_inspection() {
+    const EM_X86_64:  u16 =  62;
+    const EM_AARCH64: u16 = 183;
+
+    let mut raw = Vec::from(SYMBOLS);
+    raw[0x12..0x14].copy_from_slice(&EM_AARCH64.to_le_bytes());
+
+    let any = |_| true;
+    let x64 = |machine| machine == EM_X86_64;
+
+    assert_eq!(Elf::try_parse(&raw[..]).err(),          Some(ParseElfError::BadIsa));
+    assert_eq!(Elf::try_parse_for(&raw[..], x64).err(), Some(ParseElfError::BadIsa));
+
+    let elf = Elf::try_parse_for(&raw[..], any).expect("Parsing AArch64 `symbols.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    assert_eq!(elf.machine(), EM_AARCH64);
+    assert_eq!(elf.soname(),  Some("libsymbols.so"));
+    assert_eq!(elf.try_load(mem).err(), Some(LoadElfError::ForeignElf));
+
+    // Accepted native ELFs load just fine, but byte order is not up to `accept`.
+    let raw = Vec::from(SYMBOLS);
+    let elf = Elf::try_parse_for(&raw[..], x64).expect("Parsing `symbols.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    assert!(elf.try_load(mem).is_ok());
+
+    let raw = big_endian_symbols();
+    assert_eq!(Elf::try_parse_for(&raw[..], any).err(), Some(ParseElfError::BadEndian));
+}
@@ -0,0 +1,85 @@
+use elf_loader::*;
+
+
+
+mod os;
+
+
+
+static IFUNC: &[u8] = include_bytes!("./ifunc.elf");
+
+
+
+fn reloc(run: bool, base_off: usize) -> Result<i32, RelocElfError> {
+    let buf = Vec::from(IFUNC);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `ifunc.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `ifunc.elf` failed");
+    let base       = loaded.loader_base().wrapping_add(base_off);
+
+    unsafe { loaded.run_ifunc_resolvers(run) };
+
+    let ready = loaded.try_reloc(base, Some(os::protection_fn), None).map_err(|(_, e)| e)?;
+    let call  = ready.lookup("call_answer").expect("`call_answer` not found");
+    let call: extern "C" fn() -> i32 = unsafe { std::mem::transmute(call) };
+
+    Ok((call)())
+}
+
+
+
+#[test]
+fn ifunc_resolvers_are_opt_in() {
+    assert_eq!(reloc(false, 0), Err(RelocElfError::IfuncDisabled));
+}
+
+#[test]
+fn ifunc_resolvers_pick_the_implementation() {
+    assert_eq!(reloc(true, 0), Ok(42));
+}
+
+#[test]
+fn ifunc_resolvers_need_the_loader_base() {
+    assert_eq!(reloc(true, 0x10_0000), Err(RelocElfError::IfuncBaseMismatch));
+}
+
+#[test]
+fn ifunc_resolvers_run_for_each_reloc_into() {
+    let buf = Vec::from(IFUNC);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `ifunc.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `ifunc.elf` failed");
+    let base       = loaded.loader_base();
+
+    unsafe { loaded.run_ifunc_resolvers(true) };
+
+    // Without `prot`, the code would not be executable yet.
+    for _ in 0 .. 2 {
+        assert_eq!(loaded.reloc_into(base, Some(os::protection_fn), None), Ok(()));
+    }
+
+    let ready = loaded.try_reloc(base, Some(os::protection_fn), None).map_err(|(_, e)| e);
+    let call  = ready.expect("re-locating failed").lookup("call_answer");
+    let call: extern "C" fn() -> i32 = unsafe { std::mem::transmute(call.expect("not found")) };
+
+    assert_eq!((call)(), 42);
+}
+
+#[test]
+fn validating_runs_no_ifunc_resolvers() {
+    let buf = Vec::from(IFUNC);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `ifunc.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `ifunc.elf` failed");
+    let base       = loaded.loader_base();
+
+    assert_eq!(loaded.validate_reloc(base, None), Err(RelocElfError::IfuncDisabled));
+
+    // The loaded memory is not even executable, so running a resolver would crash.
+    unsafe { loaded.run_ifunc_resolvers(true) };
+
+    assert_eq!(loaded.validate_reloc(base, None), Ok(()));
+}
@@ -0,0 +1,123 @@
+use elf_loader::*;
+use std::mem;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+/// Bytes in the file right past the data segment's `p_filesz`, which loading must zero.
+const STALE: [u8; 16] = [0xAA; 16];
+
+/// Hand-crafts an ELF whose file layout is its memory layout, i.e. `p_offset == p_vaddr`,
+/// with the headers at zero, code at `0x1000`, and a data segment at `0x2000`.
+fn in_place_elf() -> Vec<u8> {
+    let mut code = ret_imm32(42);
+    code.resize(0x1000, 0);
+
+    let mut data = dyn_table(&[]);
+    let dyn_len  = data.len() as u64;
+    data.extend_from_slice(&STALE);
+
+    let mut b = ElfBuilder::new();
+
+    let code_off = b.blob(&code);
+    let data_off = b.blob(&data);
+
+    assert_eq!((code_off, data_off), (0x1000, 0x2000));
+
+    b.phdr(Phdr {
+        p_type: PT_LOAD, p_flags: PF_R,
+        p_offset: 0, p_vaddr: 0, p_paddr: 0,
+        p_filesz: 0x100, p_memsz: 0x100,
+        p_align: 0x1000,
+    });
+    b.phdr(Phdr {
+        p_type: PT_LOAD, p_flags: PF_RX,
+        p_offset: 0x1000, p_vaddr: 0x1000, p_paddr: 0x1000,
+        p_filesz: 6, p_memsz: 0x1000,
+        p_align: 0x1000,
+    });
+    b.phdr(Phdr {
+        p_type: PT_LOAD, p_flags: PF_RW,
+        p_offset: 0x2000, p_vaddr: 0x2000, p_paddr: 0x2000,
+        p_filesz: dyn_len, p_memsz: 0x100,
+        p_align: 0x1000,
+    });
+    b.phdr(Phdr {
+        p_type: PT_DYNAMIC, p_flags: PF_RW,
+        p_offset: 0x2000, p_vaddr: 0x2000, p_paddr: 0x2000,
+        p_filesz: dyn_len, p_memsz: dyn_len,
+        p_align: 8,
+    });
+    b.entry(0x1000);
+    b.build()
+}
+
+/// Copies `raw` into a buffer big enough to load it in place.
+fn map(raw: &[u8]) -> &'static mut [u8] {
+    let len = Elf::try_parse(raw).expect("parsing failed").mem_len_usize().max(raw.len());
+    let mem = os::alloc_aligned(len, 0x1000);
+    mem[..raw.len()].copy_from_slice(raw);
+
+    mem
+}
+
+
+
+#[test]
+fn elfs_laid_out_like_memory_load_in_place() {
+    let raw        = in_place_elf();
+    let mem        = map(&raw);
+    let at         = mem.as_ptr();
+    let mut loaded = Elf::try_load_in_place(mem).expect("loading failed");
+
+    assert_eq!(loaded.loader_base() as *const u8, at);
+
+    let base  = loaded.loader_base();
+    let ready = loaded.try_reloc(base, Some(os::protection_fn), None)
+                      .map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+
+    // The tail past `p_filesz` is zero-filled, just as if the segment had been copied.
+    assert_eq!(&ready.p_mem()[0x2010..0x2020], &[0; 16]);
+
+    let main: extern "C" fn() -> i32 = unsafe { mem::transmute(ready.p_entry()) };
+
+    assert_eq!((main)(), 42);
+}
+
+#[test]
+fn moved_segments_are_not_loadable_in_place() {
+    let mut b = ElfBuilder::new();
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.entry(0x1000);
+
+    let raw = b.build();
+    let mem = map(&raw);
+
+    assert_eq!(Elf::try_load_in_place(&mut *mem).err(),
+               Some(ElfError::Load(LoadElfError::NotInPlaceLoadable)));
+
+    // Nothing is written before the check, so the file is still intact.
+    assert_eq!(&mem[..raw.len()], &raw[..]);
+}
+
+#[test]
+fn in_place_loading_needs_room_for_all_the_memory() {
+    let mut raw = in_place_elf();
+
+    // The data segment's `p_memsz` reaches past the end of the file.
+    raw.truncate(0x2010);
+
+    let mem = os::alloc_aligned(raw.len(), 0x1000);
+    mem.copy_from_slice(&raw);
+
+    assert_eq!(Elf::try_load_in_place(mem).err(),
+               Some(ElfError::Load(LoadElfError::BadBufferSize)));
+}
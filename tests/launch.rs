@@ -0,0 +1,109 @@
+use elf_loader::*;
+
+
+
+mod builder;
+
+use self::builder::*;
+
+
+
+static BSS_RODATA_DATA: &[u8] = include_bytes!("./bss_rodata_data.elf");
+static SYMBOLS:         &[u8] = include_bytes!("./symbols.elf");
+
+
+
+fn exe(f: impl FnOnce(&mut ElfBuilder)) -> Vec<u8> {
+    let mut b = ElfBuilder::new();
+
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.entry(0x1000);
+    f(&mut b);
+    b.build()
+}
+
+
+
+#[test]
+fn relative_only_executable_is_static_pie() {
+    let buf = Vec::from(BSS_RODATA_DATA);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `bss_rodata_data.elf` failed");
+
+    assert_eq!(elf.interpreter(), None);
+    assert_eq!(elf.launch_kind(), LaunchKind::StaticPie);
+}
+
+#[test]
+fn object_without_entry_is_library() {
+    let buf = Vec::from(SYMBOLS);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `symbols.elf` failed");
+
+    assert_eq!(elf.launch_kind(), LaunchKind::Library);
+}
+
+#[test]
+fn interpreter_makes_dynamic_pie() {
+    let raw = exe(|b| {
+        b.dynamic(0x0000, &[]);
+        b.segment(PT_INTERP, PF_R, 0x2000, 0x20, b"/lib/ld-linux-x86-64.so.2\0");
+    });
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    assert_eq!(elf.interpreter().map(|i| i.to_bytes()), Some(&b"/lib/ld-linux-x86-64.so.2"[..]));
+    assert_eq!(elf.interpreter_str(), Some("/lib/ld-linux-x86-64.so.2"));
+    assert_eq!(elf.launch_kind(), LaunchKind::DynamicPie);
+}
+
+#[test]
+fn broken_interpreter_paths_are_none() {
+    for path in [&b"/lib/ld.so"[..], &b"/lib/\xFF.so\0"[..]] {
+        let raw = exe(|b| {
+            b.dynamic(0x0000, &[]);
+            b.segment(PT_INTERP, PF_R, 0x2000, 0x20, path);
+        });
+        let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+        assert_eq!(elf.interpreter_str(), None);
+    }
+}
+
+#[test]
+fn symbol_relocations_make_dynamic_pie() {
+    let with_sym = |sym: u64| exe(|b| {
+        let table = rela_table(&[(0x2800, (sym << 32) | R_X86_64_RELATIVE, 0)]);
+
+        b.dynamic(0x0000, &[(DT_RELA, 0x2000), (DT_RELASZ, table.len() as u64)]);
+        b.segment(PT_LOAD, PF_RW, 0x2000, 0x1000, &table);
+    });
+
+    let (raw0, raw1) = (with_sym(0), with_sym(1));
+
+    assert_eq!(Elf::try_parse(&raw0).expect("parsing failed").launch_kind(), LaunchKind::StaticPie);
+    assert_eq!(Elf::try_parse(&raw1).expect("parsing failed").launch_kind(), LaunchKind::DynamicPie);
+}
+
+#[test]
+fn stack_defaults_to_non_executable() {
+    let raw = exe(|b| { b.dynamic(0x0000, &[]); });
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    assert!(!elf.stack_is_executable());
+    assert_eq!(elf.stack_size_hint(), None);
+}
+
+#[test]
+fn gnu_stack_flags_and_size_are_reported() {
+    let with_stack = |p_flags: u32, p_memsz: u64| exe(|b| {
+        b.dynamic(0x0000, &[]);
+        b.phdr(Phdr { p_type: PT_GNU_STACK, p_flags, p_memsz, ..Phdr::default() });
+    });
+
+    let (raw0, raw1) = (with_stack(PF_RW, 0), with_stack(PF_RWX, 0x10_0000));
+    let (elf0, elf1) = (Elf::try_parse(&raw0).expect("parsing failed"),
+                        Elf::try_parse(&raw1).expect("parsing failed"));
+
+    assert!(!elf0.stack_is_executable());
+    assert_eq!(elf0.stack_size_hint(), None);
+    assert!(elf1.stack_is_executable());
+    assert_eq!(elf1.stack_size_hint(), Some(0x10_0000));
+}
@@ -0,0 +1,56 @@
+use elf_loader::*;
+
+
+
+mod builder;
+
+use self::builder::*;
+
+
+
+static ELF: &[u8] = include_bytes!("./bss_rodata_data.elf");
+
+
+
+#[test]
+fn lazy_headers_match_eager_ones() {
+    let buf   = Vec::from(ELF);
+    let eager = Elf::try_parse(&buf[..]).expect("Parsing `bss_rodata_data.elf` failed");
+    let lazy  = Elf::try_parse_lazy(&buf[..]).expect("Lazily parsing `bss_rodata_data.elf` failed");
+
+    let eager_phs = eager.program_headers().collect::<Vec<_>>();
+    let lazy_phs  = lazy.program_headers().collect::<Result<Vec<_>, _>>()
+                        .expect("Checking lazy program headers failed");
+
+    assert_eq!(eager_phs.len(), lazy_phs.len());
+
+    for (e, l) in eager_phs.iter().zip(&lazy_phs) {
+        assert_eq!(e.kind,       l.kind);
+        assert_eq!(e.protection, l.protection);
+        assert_eq!(e.load_range, l.load_range);
+        assert_eq!(e.copy_from,  l.copy_from);
+    }
+
+    let valid = lazy.validate().expect("Validating `bss_rodata_data.elf` failed");
+
+    assert_eq!(valid.mem_len(),   eager.mem_len());
+    assert_eq!(valid.mem_align(), eager.mem_align());
+}
+
+#[test]
+fn lazy_parsing_defers_range_checks() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+
+    // Pretend the file holds more data than it actually does.
+    b.phdrs[2].p_filesz = 0x100;
+
+    let raw  = b.build_unpadded();
+    let lazy = Elf::try_parse_lazy(&raw).expect("Lazily parsing failed");
+    let phs  = lazy.program_headers().map(|ph| ph.err()).collect::<Vec<_>>();
+
+    assert_eq!(phs, [None, None, Some(ParseElfError::BadPhRange)]);
+    assert_eq!(lazy.validate().err(), Some(ParseElfError::BadPhRange));
+}
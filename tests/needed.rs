@@ -0,0 +1,45 @@
+use elf_loader::*;
+
+
+
+mod builder;
+
+use self::builder::*;
+
+
+
+static NEEDS:   &[u8] = include_bytes!("./needs.elf");
+static SYMBOLS: &[u8] = include_bytes!("./symbols.elf");
+
+
+
+#[test]
+fn needed_libraries_are_listed() {
+    let buf = Vec::from(NEEDS);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `needs.elf` failed");
+
+    assert_eq!(elf.needed_libraries().collect::<Vec<_>>(), ["libsymbols.so"]);
+
+    let buf = Vec::from(SYMBOLS);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `symbols.elf` failed");
+
+    assert_eq!(elf.needed_libraries().count(), 0);
+}
+
+#[test]
+fn broken_needed_entries_are_skipped() {
+    let strs  = b"\0liba.so\0lib\xFF.so\0libb.so\0";
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[
+        (DT_STRTAB, 0x2000), (DT_STRSZ, strs.len() as u64),
+        (DT_NEEDED, 1), (DT_NEEDED, 9), (DT_NEEDED, 1000), (DT_NEEDED, 17),
+    ]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_R,  0x2000, 0x1000, strs);
+
+    let raw = b.build();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    assert_eq!(elf.needed_libraries().collect::<Vec<_>>(), ["liba.so", "libb.so"]);
+}
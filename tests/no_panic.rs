@@ -0,0 +1,230 @@
+//! Feeds mangled ELFs through the whole pipeline, none of which must `panic!`.
+//!
+//! Errors are fine, and so is success, as long as every step returns. Nothing gets to run, as
+//! ifunc resolvers are disabled by default, and nothing gets memory-protected.
+
+use elf_loader::*;
+
+
+
+static CORPUS: &[&[u8]] = &[
+    include_bytes!("./bss_rodata_data.elf"),
+    include_bytes!("./ifunc.elf"),
+    include_bytes!("./needs.elf"),
+    include_bytes!("./relr.elf"),
+    include_bytes!("./shared_page.elf"),
+    include_bytes!("./simple.elf"),
+    include_bytes!("./symbols.elf"),
+    include_bytes!("./symbols_both.elf"),
+    include_bytes!("./symbols_sysv.elf"),
+    include_bytes!("./symbols_x32.elf"),
+    include_bytes!("./tls.elf"),
+    include_bytes!("./weak.elf"),
+];
+
+/// Mangled ELFs wanting more memory than this are only parsed.
+const MAX_MEM: usize = 1 << 20;
+
+/// Values that tend to sit right at the edge of some bounds check.
+const NASTY: &[u64] = &[
+    0, 1, 7, 8, 0xFFF, 0x1000, 0x7FFF_FFFF, 0x8000_0000, 0xFFFF_FFFF, 0x1_0000_0000,
+    i64::MAX as u64, u64::MAX - 7, u64::MAX,
+];
+
+extern "C" fn resolve(_: *const u8, _: usize) -> *const () {
+    0xDEAD_B000 as *const ()
+}
+
+/// A tiny xorshift, so that failures are reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() >> 8) as usize % n
+    }
+}
+
+/// Runs `raw` through as much of the pipeline as it survives.
+fn run_pipeline(raw: &[u8]) {
+    if let Ok(lazy) = Elf::try_parse_lazy(raw) {
+        lazy.program_headers().for_each(drop);
+        let _ = lazy.validate();
+    }
+
+    let elf = match Elf::try_parse(raw) {
+        Ok(elf) => elf,
+        Err(_)  => return,
+    };
+
+    elf.program_headers().for_each(drop);
+
+    let (len, align) = (elf.mem_len_usize(), elf.mem_align_usize());
+
+    if len.saturating_add(align) > MAX_MEM {
+        return;
+    }
+
+    // A plain `Vec`, lest thousands of runs exhaust the address space.
+    let mut buf    = vec![0_u8; len + align];
+    let off        = buf.as_ptr().align_offset(align.max(1));
+    let mut loaded = match elf.try_load(&mut buf[off..(off + len)]) {
+        Ok(loaded) => loaded,
+        Err(_)     => return,
+    };
+
+    let base = loaded.loader_base();
+
+    let _ = loaded.validate_reloc(base, Some(resolve));
+    let _ = loaded.effective_protection_map().count();
+    let _ = loaded.dynamic_entries().count();
+    let _ = loaded.tls_template();
+
+    if let Ok(ready) = loaded.try_reloc(base, None, Some(resolve)) {
+        let _ = ready.lookup("main");
+        let _ = ready.lookup_versioned("main", "GLIBC_2.2.5");
+        let _ = ready.symbols().count();
+        let _ = ready.segments().count();
+        let _ = ready.try_p_entry();
+        let _ = ready.tls_template();
+    }
+}
+
+/// Copies `elf`, and overwrites a few bytes with random ones, mostly within the headers.
+fn flip_bytes(elf: &[u8], rng: &mut Rng) -> Vec<u8> {
+    let mut raw = Vec::from(elf);
+
+    for _ in 0 .. (1 + rng.below(8)) {
+        let at = match rng.below(4) {
+            0 => rng.below(raw.len()),
+            _ => rng.below(raw.len().min(0x400)),
+        };
+
+        raw[at] = rng.next() as u8;
+    }
+
+    raw
+}
+
+/// Copies `elf`, and overwrites an aligned word with one of the `NASTY` values, be it in the
+/// headers, or in e.g. the dynamic, re-location or symbol tables.
+fn nasty_word(elf: &[u8], rng: &mut Rng) -> Vec<u8> {
+    let mut raw = Vec::from(elf);
+    let words   = raw.len() / 8;
+    let at      = rng.below(words) * 8;
+    let val     = NASTY[rng.below(NASTY.len())];
+
+    match rng.below(2) {
+        0 => raw[at .. (at + 8)].copy_from_slice(&val.to_le_bytes()),
+        _ => raw[at .. (at + 4)].copy_from_slice(&(val as u32).to_le_bytes()),
+    }
+
+    raw
+}
+
+#[test]
+fn truncated_elfs_never_panic() {
+    for elf in CORPUS {
+        for len in (0 ..= elf.len()).step_by(7) {
+            run_pipeline(&Vec::from(&elf[..len]));
+        }
+    }
+}
+
+#[test]
+fn garbage_never_panics() {
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+
+    for _ in 0 .. 2048 {
+        let len = rng.below(0x200);
+        let raw = (0 .. len).map(|_| rng.next() as u8).collect::<Vec<_>>();
+
+        run_pipeline(&raw);
+
+        // Garbage behind a valid identification gets a bit further.
+        let mut raw = raw;
+        if raw.len() >= 16 {
+            raw[..16].copy_from_slice(&CORPUS[0][..16]);
+            run_pipeline(&raw);
+        }
+    }
+}
+
+#[test]
+fn mangled_elfs_never_panic() {
+    let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+
+    for elf in CORPUS {
+        for _ in 0 .. 4096 {
+            run_pipeline(&flip_bytes(elf, &mut rng));
+            run_pipeline(&nasty_word(elf, &mut rng));
+        }
+    }
+}
+
+/// Every number within `val`, depth first.
+#[cfg(feature = "serde")]
+fn numbers(val: &mut serde_json::Value) -> Vec<&mut serde_json::Value> {
+    use serde_json::Value;
+
+    match val {
+        Value::Number(_) => vec![val],
+        Value::Array(vs) => vs.iter_mut().flat_map(numbers).collect(),
+        Value::Object(o) => o.values_mut().flat_map(numbers).collect(),
+        _                => Vec::new(),
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn forged_metadata_never_panics() {
+    for elf in CORPUS {
+        let raw     = Vec::from(*elf);
+        let elf     = Elf::try_parse(&raw).expect("parsing failed");
+        let len     = elf.mem_len_usize();
+        let align   = elf.mem_align_usize();
+        let mut buf = vec![0_u8; len + align];
+        let off     = buf.as_ptr().align_offset(align);
+        let loaded  = elf.try_load(&mut buf[off..(off + len)]).expect("loading failed");
+
+        let (mem, meta) = loaded.into_parts();
+        let mem         = Vec::from(&*mem);
+        let json        = serde_json::to_value(&meta).expect("serialising failed");
+        let count       = numbers(&mut json.clone()).len();
+
+        for idx in 0 .. count {
+            for val in NASTY {
+                let mut json = json.clone();
+                *numbers(&mut json)[idx] = (*val).into();
+
+                let meta = match serde_json::from_value::<LoadedElfMeta>(json) {
+                    Ok(meta) => meta,
+                    Err(_)   => continue,
+                };
+
+                let mut buf = vec![0_u8; len + align];
+                let off     = buf.as_ptr().align_offset(align);
+                let forged  = &mut buf[off..(off + len)];
+
+                forged.copy_from_slice(&mem);
+
+                let mut loaded = match LoadedElf::try_from_parts(forged, meta) {
+                    Ok(loaded) => loaded,
+                    Err(_)     => continue,
+                };
+
+                let base = loaded.loader_base();
+
+                let _ = loaded.tls_template().map(|tls| tls.zero_fill);
+                let _ = loaded.effective_protection_map().count();
+                let _ = loaded.try_reloc(base, None, Some(resolve)).map(|ready| ready.p_entry());
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use elf_loader::*;
+
+
+
+mod builder;
+
+use self::builder::*;
+
+
+
+const PT_NOTE:                u32 = 4;
+const PT_GNU_PROPERTY:        u32 = 0x6474E553;
+const NT_GNU_ABI_TAG:         u32 = 1;
+const NT_GNU_BUILD_ID:        u32 = 3;
+const NT_GNU_PROPERTY_TYPE_0: u32 = 5;
+
+const GNU_PROPERTY_X86_ISA_1_NEEDED:  u32 = 0xC000_8002;
+const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xC000_0002;
+
+static SYMBOLS: &[u8] = include_bytes!("./symbols.elf");
+static SIMPLE:  &[u8] = include_bytes!("./simple.elf");
+
+/// Serialises a note entry, padding name and descriptor to 4 bytes.
+fn note(name: &[u8], kind: u32, desc: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    out.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    out.extend_from_slice(&kind.to_le_bytes());
+    out.extend_from_slice(name);
+    while out.len() % 4 != 0 { out.push(0); }
+    out.extend_from_slice(desc);
+    while out.len() % 4 != 0 { out.push(0); }
+
+    out
+}
+
+/// Serialises a GNU property entry, padding its data to 8 bytes.
+fn property(kind: u32, data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    out.extend_from_slice(&kind.to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    while out.len() % 8 != 0 { out.push(0); }
+
+    out
+}
+
+/// Builds an ELF with a `PT_NOTE` header covering the given notes.
+fn with_notes(notes: &[u8]) -> Vec<u8> {
+    with_note_segment(PT_NOTE, 4, notes)
+}
+
+/// Builds an ELF with a `PT_GNU_PROPERTY` header covering a note of the given properties.
+fn with_properties(props: &[u8]) -> Vec<u8> {
+    with_note_segment(PT_GNU_PROPERTY, 8, &note(b"GNU\0", NT_GNU_PROPERTY_TYPE_0, props))
+}
+
+fn with_note_segment(p_type: u32, p_align: u64, notes: &[u8]) -> Vec<u8> {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+
+    let off = b.blob(notes);
+
+    b.phdr(Phdr {
+        p_type, p_flags: PF_R,
+        p_offset: off, p_vaddr: 0x1000, p_paddr: 0x1000,
+        p_filesz: notes.len() as u64, p_memsz: notes.len() as u64,
+        p_align,
+    });
+
+    b.build()
+}
+
+fn build_id(raw: &[u8]) -> Option<Vec<u8>> {
+    let elf = Elf::try_parse(raw).expect("parsing failed");
+    elf.build_id().map(|id| id.to_vec())
+}
+
+fn cet_features(raw: &[u8]) -> CetFeatures {
+    Elf::try_parse(raw).expect("parsing failed").cet_features()
+}
+
+
+
+#[test]
+fn linkers_build_ids_are_found() {
+    let buf = Vec::from(SYMBOLS);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `symbols.elf` failed");
+    let id  = elf.build_id().expect("no build ID");
+
+    // As reported by `readelf -n`.
+    let hex = id.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+
+    assert_eq!(hex, "e7635c1b537decbfd82e48cb6e8666a656be63fc");
+}
+
+#[test]
+fn no_notes_no_build_id() {
+    let buf = Vec::from(SIMPLE);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `simple.elf` failed");
+
+    assert_eq!(elf.build_id(), None);
+}
+
+#[test]
+fn other_notes_are_skipped() {
+    let mut notes = note(b"GNU\0", NT_GNU_ABI_TAG, &[0; 16]);
+    notes.extend(note(b"Xen\0", NT_GNU_BUILD_ID, &[1, 2, 3]));
+    notes.extend(note(b"GNU\0", NT_GNU_BUILD_ID, &[4, 5, 6, 7, 8]));
+
+    assert_eq!(build_id(&with_notes(&notes)), Some(vec![4, 5, 6, 7, 8]));
+}
+
+#[test]
+fn the_last_note_may_lack_padding() {
+    let mut notes = note(b"GNU\0", NT_GNU_BUILD_ID, &[1, 2, 3]);
+    notes.pop();
+
+    assert_eq!(build_id(&with_notes(&notes)), Some(vec![1, 2, 3]));
+}
+
+#[test]
+fn broken_notes_are_no_build_ids() {
+    let id = note(b"GNU\0", NT_GNU_BUILD_ID, &[1, 2, 3, 4]);
+
+    // Huge name and descriptor sizes, which must neither overflow nor read out of bounds.
+    for (at, len) in [(0, u32::MAX), (0, 0xFFFF_FFFD), (4, u32::MAX), (4, 5)].iter() {
+        let mut notes = id.clone();
+        notes[*at..(*at + 4)].copy_from_slice(&len.to_le_bytes());
+
+        assert_eq!(build_id(&with_notes(&notes)), None);
+    }
+
+    // A broken note hides all notes after it.
+    let mut notes = note(b"GNU\0", NT_GNU_ABI_TAG, &[0; 16]);
+    notes[4..8].copy_from_slice(&0x100_u32.to_le_bytes());
+    notes.extend(id);
+
+    assert_eq!(build_id(&with_notes(&notes)), None);
+}
+
+#[test]
+fn cet_features_are_read_from_gnu_properties() {
+    let mut props = property(GNU_PROPERTY_X86_ISA_1_NEEDED, &1_u32.to_le_bytes());
+    props.extend(property(GNU_PROPERTY_X86_FEATURE_1_AND, &0b11_u32.to_le_bytes()));
+
+    assert_eq!(cet_features(&with_properties(&props)), CetFeatures { ibt: true, shstk: true });
+
+    let ibt = property(GNU_PROPERTY_X86_FEATURE_1_AND, &0b01_u32.to_le_bytes());
+
+    assert_eq!(cet_features(&with_properties(&ibt)), CetFeatures { ibt: true, shstk: false });
+}
+
+#[test]
+fn no_gnu_properties_no_cet_features() {
+    let buf  = Vec::from(SIMPLE);
+    let both = property(GNU_PROPERTY_X86_FEATURE_1_AND, &0b11_u32.to_le_bytes());
+
+    assert_eq!(cet_features(&buf), CetFeatures::default());
+
+    // The very same note, but in a `PT_NOTE` segment, where the kernel does not look for it.
+    let notes = note(b"GNU\0", NT_GNU_PROPERTY_TYPE_0, &both);
+
+    assert_eq!(cet_features(&with_note_segment(PT_NOTE, 8, &notes)), CetFeatures::default());
+}
+
+#[test]
+fn broken_gnu_properties_are_no_cet_features() {
+    let both = property(GNU_PROPERTY_X86_FEATURE_1_AND, &0b11_u32.to_le_bytes());
+
+    // Huge data sizes, which must neither overflow nor read out of bounds.
+    for len in [u32::MAX, 0xFFFF_FFF8, 9].iter() {
+        let mut props = both.clone();
+        props[4..8].copy_from_slice(&len.to_le_bytes());
+
+        assert_eq!(cet_features(&with_properties(&props)), CetFeatures::default());
+    }
+
+    // A broken property hides all properties after it.
+    let mut props = property(GNU_PROPERTY_X86_ISA_1_NEEDED, &1_u32.to_le_bytes());
+    props[4..8].copy_from_slice(&0x100_u32.to_le_bytes());
+    props.extend(both);
+
+    assert_eq!(cet_features(&with_properties(&props)), CetFeatures::default());
+}
@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 
 use std::ops::Range;
 use crate::SegmentProtection;
@@ -8,6 +10,7 @@ pub fn alloc_aligned(len: usize, align: usize) -> &'static mut [u8] {
     self::os_impl::alloc_aligned(len, align)
 }
 
+#[allow(improper_ctypes_definitions)]
 pub extern "C" fn protection_fn(
     prot:    SegmentProtection,
     p_base:  *mut u8,
@@ -0,0 +1,95 @@
+#![cfg(feature = "alloc")]
+
+use elf_loader::*;
+use std::mem;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+fn ret_42() -> Vec<u8> {
+    let code = ret_imm32(42);
+
+    let mut b = ElfBuilder::new();
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1800, &code);
+    b.entry(0x1000);
+    b.build()
+}
+
+
+
+#[test]
+fn boxed_elf_runs() {
+    let raw = ret_42();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    // Freeing protected memory must not upset the allocator, so do this a few times.
+    for _ in 0..8 {
+        let mut loaded = elf.load_boxed().expect("loading failed");
+        let base       = loaded.loader_base();
+
+        assert_eq!(loaded.mem_len(), 0x3000);
+        assert_eq!((base as usize) % (loaded.mem_align() as usize), 0);
+
+        let ready = loaded.try_reloc(base, Some(os::protection_fn), None)
+                          .expect("re-locating failed");
+        let main: extern "C" fn() -> i32 = unsafe { mem::transmute(ready.p_entry()) };
+
+        assert_eq!((main)(), 42);
+    }
+}
+
+#[test]
+fn failed_reloc_still_frees() {
+    let raw = ret_42();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    for _ in 0..8 {
+        let mut loaded = elf.load_boxed().expect("loading failed");
+        let base       = loaded.loader_base().wrapping_add(1);
+
+        let err = loaded.try_reloc(base, Some(os::protection_fn), None).err();
+
+        assert_eq!(err, Some(RelocElfError::BadBaseAddressAlignment));
+    }
+}
+
+#[test]
+fn boxed_loading_checks_like_try_load() {
+    let raw = ret_42();
+    let elf = Elf::try_parse_foreign(&raw).expect("parsing failed");
+
+    assert!(matches!(elf.load_boxed(), Err(LoadElfError::ForeignElf)));
+}
+
+#[test]
+fn bootstrapped_elf_runs() {
+    let raw   = ret_42();
+    let ready = Elf::bootstrap(&raw, BasePolicy::LoaderAddress, Some(os::protection_fn))
+                    .expect("bootstrapping failed");
+    let main: extern "C" fn() -> i32 = unsafe { ready.entry_fn() };
+
+    assert_eq!((main)(), 42);
+}
+
+#[test]
+fn bootstrapping_reports_every_step() {
+    let raw = ret_42();
+
+    assert!(matches!(Elf::bootstrap(&raw[..16], BasePolicy::LoaderAddress, None),
+                     Err(ElfError::Parse(ParseElfError::BadBufferSize))));
+
+    // Memory is freed on failure, so do this a few times.
+    for _ in 0..8 {
+        let base = 0x1001 as *mut u8;
+
+        assert!(matches!(Elf::bootstrap(&raw, BasePolicy::Fixed(base), None),
+                         Err(ElfError::Reloc(RelocElfError::BadBaseAddressAlignment))));
+    }
+}
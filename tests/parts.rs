@@ -0,0 +1,96 @@
+use elf_loader::*;
+use std::mem;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+fn ret_42() -> Vec<u8> {
+    let mut b = ElfBuilder::new();
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x2000, 0x1000, &[1, 2, 3, 4]);
+    b.entry(0x1000);
+    b.build()
+}
+
+/// Loads `ret_42`, and splits it into a copy of its memory and its metadata.
+fn load_parts() -> (Vec<u8>, LoadedElfMeta) {
+    let raw    = ret_42();
+    let elf    = Elf::try_parse(&raw).expect("parsing failed");
+    let mem    = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+    let loaded = elf.try_load(mem).expect("loading failed");
+
+    let (mem, meta) = loaded.into_parts();
+
+    (mem.to_vec(), meta)
+}
+
+/// Re-creates the ELF in a fresh buffer, and runs it.
+fn run_parts(bytes: &[u8], meta: LoadedElfMeta) -> i32 {
+    let mem = os::alloc_aligned(bytes.len(), meta.mem_align() as usize);
+    mem.copy_from_slice(bytes);
+
+    let mut loaded = LoadedElf::try_from_parts(mem, meta).expect("re-creating failed");
+    let base       = loaded.loader_base();
+
+    let ready = loaded.try_reloc(base, Some(os::protection_fn), None)
+                      .map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+    let main: extern "C" fn() -> i32 = unsafe { mem::transmute(ready.p_entry()) };
+
+    (main)()
+}
+
+
+
+#[test]
+fn parts_make_a_whole_elf() {
+    let (bytes, meta) = load_parts();
+
+    assert_eq!(&bytes[0x2000..0x2004], &[1, 2, 3, 4]);
+    assert_eq!(run_parts(&bytes, meta), 42);
+}
+
+#[test]
+fn parts_must_fit_the_buffer() {
+    let (bytes, meta) = load_parts();
+    let mem           = os::alloc_aligned(bytes.len(), meta.mem_align() as usize);
+
+    assert_eq!(LoadedElf::try_from_parts(&mut mem[..0x2800], meta).err(),
+               Some(LoadElfError::BadBufferSize));
+
+    let (_, meta) = load_parts();
+
+    assert_eq!(LoadedElf::try_from_parts(&mut mem[1..], meta).err(),
+               Some(LoadElfError::BadBufferAlignment));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn metadata_survives_serialisation() {
+    let (bytes, meta) = load_parts();
+
+    let json = serde_json::to_string(&meta).expect("serialising failed");
+    let meta = serde_json::from_str::<LoadedElfMeta>(&json).expect("deserialising failed");
+
+    assert_eq!(run_parts(&bytes, meta), 42);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn broken_metadata_is_rejected() {
+    let (bytes, meta) = load_parts();
+    let mem           = os::alloc_aligned(bytes.len(), meta.mem_align() as usize);
+
+    let json = serde_json::to_string(&meta).expect("serialising failed")
+        .replace("\"mem_align\":4096", "\"mem_align\":4095");
+    let meta = serde_json::from_str::<LoadedElfMeta>(&json).expect("deserialising failed");
+
+    assert_eq!(LoadedElf::try_from_parts(mem, meta).err(), Some(LoadElfError::BadMetadata));
+}
@@ -0,0 +1,128 @@
+use elf_loader::*;
+use std::convert::TryInto;
+
+
+
+#[test]
+fn new_accepts_data_that_fits() {
+    let data = [0xC3_u8; 4];
+    let ph   = ProgramHeader::new(
+        SegmentKind::Load, SegmentProtection::RX, Slice32::new(0x1000, 0x10), &data
+    ).expect("constructing failed");
+
+    assert_eq!(ph.kind,             SegmentKind::Load);
+    assert_eq!(ph.protection,       SegmentProtection::RX);
+    assert_eq!(ph.load_range.start, 0x1000);
+    assert_eq!(ph.copy_from.len(),  4);
+}
+
+#[test]
+fn new_rejects_data_longer_than_load_range() {
+    let data = [0_u8; 0x11];
+
+    assert!(ProgramHeader::new(
+        SegmentKind::Load, SegmentProtection::RW, Slice32::new(0, 0x10), &data
+    ).is_none());
+}
+
+#[test]
+fn only_parsed_headers_have_raw_ones() {
+    let data = [0xC3_u8; 4];
+    let ph   = ProgramHeader::new(
+        SegmentKind::Load, SegmentProtection::RX, Slice32::new(0x1000, 0x10), &data
+    ).expect("constructing failed");
+
+    assert!(ph.raw().is_none());
+
+    let buf = Vec::from(&include_bytes!("./bss_rodata_data.elf")[..]);
+    let elf = Elf::try_parse(&buf).expect("parsing failed");
+    let raw = elf.program_headers().map(|ph| *ph.raw().unwrap()).collect::<Vec<_>>();
+
+    // `PT_LOAD`s, `PT_DYNAMIC` and `PT_GNU_RELRO`, but no `PT_GNU_STACK`, which is no segment.
+    assert_eq!(raw.iter().map(|ph| ph.p_type).collect::<Vec<_>>(), [1, 1, 1, 2, 0x6474_E552]);
+    assert_eq!((raw[1].p_offset, raw[1].p_flags, raw[1].p_align), (0x2000, 5, 0x1000));
+    assert_eq!(raw[4].p_memsz, 0x1000);
+}
+
+#[test]
+fn unsupported_headers_tell_their_type() {
+    let buf = Vec::from(&include_bytes!("./symbols.elf")[..]);
+    let elf = Elf::try_parse(&buf).expect("parsing failed");
+
+    let mut types = elf.program_headers()
+        .filter(|ph| ph.kind == SegmentKind::Unsupported)
+        .map(|ph| ph.raw().unwrap().p_type);
+
+    // `PT_NOTE` for the build ID.
+    assert!(types.any(|ty| ty == 4));
+}
+
+#[test]
+fn program_headers_filter_by_kind() {
+    let buf = Vec::from(&include_bytes!("./bss_rodata_data.elf")[..]);
+    let elf = Elf::try_parse(&buf).expect("parsing failed");
+
+    assert_eq!(elf.program_headers().loadable().count(), 3);
+    assert_eq!(elf.program_headers().dynamic() .count(), 1);
+    assert_eq!(elf.program_headers().relro()   .count(), 1);
+    assert_eq!(elf.program_headers().of_kind(SegmentKind::Tls).count(), 0);
+
+    assert!(elf.program_headers().loadable().all(|ph| ph.kind == SegmentKind::Load));
+    assert_eq!(
+        elf.program_headers().dynamic().next().map(|ph| ph.load_range),
+        elf.program_headers().find(|ph| ph.kind == SegmentKind::Dynamic).map(|ph| ph.load_range),
+    );
+}
+
+/// Walks all program headers any parser hands out, which must neither `panic!` nor hand out
+/// data past the end of `raw`.
+fn walk_headers(raw: &[u8]) {
+    let in_raw = |ph: &ProgramHeader| {
+        let (start, end) = (raw.as_ptr() as usize, raw.as_ptr() as usize + raw.len());
+        let data         = ph.copy_from.as_ptr() as usize;
+
+        ph.copy_from.is_empty() || ((data >= start) && (data + ph.copy_from.len() <= end))
+    };
+
+    if let Ok(elf) = Elf::try_parse(raw) {
+        assert!(elf.program_headers().all(|ph| in_raw(&ph)));
+    }
+
+    if let Ok(lazy) = Elf::try_parse_lazy(raw) {
+        assert!(lazy.program_headers().filter_map(Result::ok).all(|ph| in_raw(&ph)));
+        let _ = lazy.validate();
+    }
+}
+
+#[test]
+fn truncated_elfs_never_panic() {
+    let elf = include_bytes!("./bss_rodata_data.elf");
+
+    for len in 0..=elf.len() {
+        walk_headers(&Vec::from(&elf[..len]));
+    }
+}
+
+#[test]
+fn mangled_program_headers_never_panic() {
+    let elf = include_bytes!("./bss_rodata_data.elf");
+
+    // A tiny xorshift, so that failures are reproducible.
+    let mut rng = 0x2545_F491_4F6C_DD1D_u64;
+    let phoff   = u64::from_le_bytes(elf[0x20..0x28].try_into().unwrap()) as usize;
+
+    for _ in 0..4096 {
+        let mut raw = Vec::from(&elf[..]);
+
+        for _ in 0..4 {
+            rng ^= rng << 13;
+            rng ^= rng >> 7;
+            rng ^= rng << 17;
+
+            let at = phoff + ((rng >> 8) as usize % 0x100);
+            if at < raw.len() { raw[at] = rng as u8; }
+        }
+
+        walk_headers(&raw);
+    }
+}
@@ -0,0 +1,262 @@
+use elf_loader::*;
+use elf_loader::SegmentProtection::*;
+use std::cell::RefCell;
+use std::ops::Range;
+
+
+
+mod os;
+
+
+
+static ELF: &[u8] = include_bytes!("./bss_rodata_data.elf");
+
+
+
+#[test]
+fn effective_protection_map_layers_segments() {
+    let buf = Vec::from(ELF);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `bss_rodata_data.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let loaded = elf.try_load(mem).expect("Loading `bss_rodata_data.elf` failed");
+    let map    = loaded.effective_protection_map().collect::<Vec<_>>();
+
+    // `GNU_RELRO` overrules the `RW` of the `DYNAMIC` segment, and `.text` is widened to
+    // whole pages.
+    assert_eq!(map, [
+        (0x0000 .. 0x1000, RO),
+        (0x1000 .. 0x3000, RX),
+        (0x3000 .. 0x30AC, RW),
+    ]);
+}
+
+#[test]
+fn boot_header_matches_protection_map() {
+    let buf = Vec::from(ELF);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `bss_rodata_data.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `bss_rodata_data.elf` failed");
+    let base       = loaded.loader_base();
+    let map        = loaded.effective_protection_map().collect::<Vec<_>>();
+    let ready      = loaded.try_reloc(base, Some(os::protection_fn), None)
+                           .map_err(|(_, e)| e)
+                           .expect("Re-locating `bss_rodata_data.elf` failed");
+
+    let mut hdr = BootHeader::default();
+    assert!(!hdr.is_valid());
+
+    ready.to_bootable(&mut hdr);
+
+    let segs = hdr.segments().iter()
+        .map(|s| ((s.start as usize) .. ((s.start + s.len) as usize), s.protect))
+        .collect::<Vec<_>>();
+
+    assert!(hdr.is_valid());
+    assert_eq!(hdr.mem_len as usize, ready.p_mem().len());
+    assert_eq!(hdr.entry_off as usize, (ready.p_entry() as usize) - (base as usize));
+    assert_eq!(segs, map);
+}
+
+type Requests = Vec<(Range<usize>, SegmentProtection)>;
+
+thread_local! {
+    static REQUESTS: RefCell<Requests> = const { RefCell::new(Vec::new()) };
+}
+
+#[allow(improper_ctypes_definitions)]
+extern "C" fn record_protection(
+    prot:    SegmentProtection,
+    p_base:  *mut u8,
+    v_base:  *mut u8,
+    mem_len: usize,
+    range:   Range<usize>
+) -> Result<(), ()> {
+    REQUESTS.with(|r| r.borrow_mut().push((range.clone(), prot)));
+
+    os::protection_fn(prot, p_base, v_base, mem_len, range)
+}
+
+fn protection_requests(coalesce: bool) -> (Requests, usize) {
+    let buf = Vec::from(ELF);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `bss_rodata_data.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `bss_rodata_data.elf` failed");
+    let base       = loaded.loader_base();
+    let map_len    = loaded.effective_protection_map().count();
+
+    loaded.coalesce_protection(coalesce);
+    REQUESTS.with(|r| r.borrow_mut().clear());
+
+    loaded.try_reloc(base, Some(record_protection), None)
+          .map_err(|(_, e)| e)
+          .expect("Re-locating `bss_rodata_data.elf` failed");
+
+    (REQUESTS.with(|r| r.take()), map_len)
+}
+
+#[test]
+fn protection_requests_overlap_by_default() {
+    let (reqs, map_len) = protection_requests(false);
+
+    assert_eq!(reqs[0], (0x0000 .. 0x30AC, RO));
+    assert!(reqs.len() > map_len);
+}
+
+#[test]
+fn coalesced_protection_requests_match_protection_map() {
+    let (reqs, _) = protection_requests(true);
+
+    assert_eq!(reqs, [
+        (0x0000 .. 0x1000, RO),
+        (0x1000 .. 0x3000, RX),
+        (0x3000 .. 0x30AC, RW),
+    ]);
+}
+
+thread_local! {
+    static BATCHES: RefCell<Vec<Requests>> = const { RefCell::new(Vec::new()) };
+}
+
+#[allow(improper_ctypes_definitions)]
+extern "C" fn record_batch(
+    p_base:   *mut u8,
+    v_base:   *mut u8,
+    mem_len:  usize,
+    segments: *const SegmentDesc,
+    count:    usize,
+) -> Result<(), ()> {
+    let descs = unsafe { std::slice::from_raw_parts(segments, count) };
+    let batch = descs.iter().map(|d| (d.range.clone(), d.protection)).collect::<Requests>();
+
+    BATCHES.with(|b| b.borrow_mut().push(batch.clone()));
+
+    batch.into_iter().try_for_each(|(range, prot)| {
+        os::protection_fn(prot, p_base, v_base, mem_len, range)
+    })
+}
+
+#[test]
+fn batched_protection_gets_the_whole_map_at_once() {
+    let buf = Vec::from(ELF);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `bss_rodata_data.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `bss_rodata_data.elf` failed");
+    let base       = loaded.loader_base();
+    let map        = loaded.effective_protection_map().collect::<Vec<_>>();
+
+    BATCHES.with(|b| b.borrow_mut().clear());
+
+    loaded.try_reloc_batched(base, Some(record_batch), None)
+          .map_err(|(_, e)| e)
+          .expect("Re-locating `bss_rodata_data.elf` failed");
+
+    assert_eq!(BATCHES.with(|b| b.take()), [map]);
+}
+
+#[test]
+fn segments_know_both_address_spaces() {
+    let buf = Vec::from(ELF);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `bss_rodata_data.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `bss_rodata_data.elf` failed");
+    let p_base     = loaded.loader_base() as usize;
+    let v_base     = 0x4000_0000_usize;
+    let ready      = loaded.try_reloc(v_base as *mut u8, None, None)
+                           .map_err(|(_, e)| e)
+                           .expect("Re-locating `bss_rodata_data.elf` failed");
+
+    let segs = ready.segments().collect::<Vec<_>>();
+
+    for seg in &segs {
+        assert_eq!(seg.p_range.start - p_base, seg.v_range.start - v_base);
+        assert_eq!(seg.p_range.len(), seg.v_range.len());
+    }
+
+    // The `DYNAMIC` segment and its `LOAD` segment are listed separately, and `GNU_RELRO`
+    // comes last, overruling them both.
+    let segs = segs.into_iter()
+        .map(|s| ((s.p_range.start - p_base) .. (s.p_range.end - p_base), s.protection))
+        .collect::<Vec<_>>();
+
+    assert_eq!(segs, [
+        (0x0000 .. 0x00A0, RW),
+        (0x1000 .. 0x2B49, RX),
+        (0x3000 .. 0x30AC, RW),
+        (0x0000 .. 0x00A0, RW),
+        (0x0000 .. 0x1000, RO),
+    ]);
+}
+
+#[test]
+fn segments_know_their_zero_fill() {
+    let buf = Vec::from(ELF);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `bss_rodata_data.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `bss_rodata_data.elf` failed");
+    let base       = loaded.loader_base();
+    let ready      = loaded.try_reloc(base, None, None)
+                           .map_err(|(_, e)| e)
+                           .expect("Re-locating `bss_rodata_data.elf` failed");
+
+    let bss = ready.segments()
+        .filter(|seg| seg.zero_fill_len != 0)
+        .map(|seg| (seg.kind, seg.file_len, seg.zero_fill_len))
+        .collect::<Vec<_>>();
+
+    let phs = elf.program_headers()
+        .filter(|ph| ph.copy_from.len() != (ph.load_range.len as usize))
+        .map(|ph| (ph.kind, ph.copy_from.len(), (ph.load_range.len as usize) - ph.copy_from.len()))
+        .collect::<Vec<_>>();
+
+    // E.g. `.bss` is not in the file, which the loaded ELF still knows without the buffer.
+    assert!(!bss.is_empty());
+    assert_eq!(bss, phs);
+
+    for seg in ready.segments() {
+        assert_eq!(seg.file_len + seg.zero_fill_len, seg.p_range.len());
+    }
+}
+
+#[allow(improper_ctypes_definitions)]
+extern "C" fn refuse_code(
+    prot:    SegmentProtection,
+    _:       *mut u8,
+    _:       *mut u8,
+    _:       usize,
+    range:   Range<usize>
+) -> Result<(), ()> {
+    REQUESTS.with(|r| r.borrow_mut().push((range, prot)));
+
+    match prot {
+        RX => Err(()),
+        _  => Ok(()),
+    }
+}
+
+#[test]
+fn failed_protection_requests_are_reported() {
+    let buf = Vec::from(ELF);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `bss_rodata_data.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `bss_rodata_data.elf` failed");
+    let base       = loaded.loader_base();
+
+    REQUESTS.with(|r| r.borrow_mut().clear());
+
+    let err = loaded.try_reloc(base, Some(refuse_code), None).err().map(|(_, e)| e);
+    let err = err.expect("protecting code should have failed");
+
+    // The failed request is the last one made.
+    let reqs = REQUESTS.with(|r| r.take());
+
+    assert_eq!(err.protect_failure(), Some((0x1000 .. 0x3000, RX)));
+    assert_eq!(reqs.last(), Some(&(0x1000 .. 0x3000, RX)));
+    assert_eq!(err, RelocElfError::MemProtectFailed { start: 0x1000, end: 0x3000, protect: RX });
+}
@@ -0,0 +1,736 @@
+use elf_loader::*;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+const SHNDX_TEXT: u16 = 1;
+
+/// Names of the symbols in the string table at `0x2600`.
+const STRTAB: &[u8] = b"\0puts\0missing\0environ\0";
+const PUTS:    u32 = 1;
+const MISSING: u32 = 6;
+const ENVIRON: u32 = 14;
+
+const PUTS_ADDR: usize = 0xDEAD_B000;
+
+/// What `environ` resolves to, as if some other object defined it.
+static ENVIRON_DATA: [u8; 16] = *b"defined in libc\0";
+
+type Syms<'a>  = &'a [(u32, u8, u16, u64)];
+type Relas<'a> = &'a [(u64, u64, i64)];
+
+extern "C" fn resolve(name: *const u8, name_len: usize) -> *const () {
+    match unsafe { std::slice::from_raw_parts(name, name_len) } {
+        b"puts"    => PUTS_ADDR as *const (),
+        b"environ" => ENVIRON_DATA.as_ptr() as *const (),
+        _          => std::ptr::null(),
+    }
+}
+
+/// Loads `raw`, ready to be re-located.
+fn load(raw: &[u8]) -> LoadedElf<'static> {
+    let elf = Elf::try_parse(raw).expect("parsing failed");
+    let mem = os::alloc_aligned(elf.mem_len_usize(), elf.mem_align_usize());
+
+    elf.try_load(mem).expect("loading failed")
+}
+
+/// Loads and re-locates `raw` to `v_base`, or to the loader base if there is none, and returns
+/// the base and a copy of the re-located memory.
+fn reloc_raw(raw: &[u8], resolve: Option<ResolveFn>, v_base: Option<usize>)
+-> Result<(usize, Vec<u8>), RelocElfError> {
+    let mut loaded = load(raw);
+    let base       = v_base.map(|b| b as *mut u8).unwrap_or_else(|| loaded.loader_base());
+
+    let ready = loaded.try_reloc(base, None, resolve).map_err(|(_, e)| e)?;
+
+    Ok((base as usize, ready.p_mem().to_vec()))
+}
+
+fn reloc(syms: Syms, relas: Relas, plt: Relas) -> Result<(usize, Vec<u8>), RelocElfError> {
+    reloc_with(syms, relas, plt, None)
+}
+
+/// Loads and re-locates a `RelocElf` with the given symbols, named after `STRTAB`, and the
+/// given `Rela` and PLT `Rela` entries.
+fn reloc_with(syms: Syms, relas: Relas, plt: Relas, resolve: Option<ResolveFn>)
+-> Result<(usize, Vec<u8>), RelocElfError> {
+    reloc_raw(&with_syms(syms, RelocElf { relas, plt_relas: plt, ..RelocElf::default() }).build(),
+              resolve, None)
+}
+
+/// Adds the given symbols, named after `STRTAB`, to `elf`.
+fn with_syms<'a>(syms: Syms, elf: RelocElf<'a>) -> RelocElf<'a> {
+    RelocElf { symtab: sym_table(syms), strtab: STRTAB, ..elf }
+}
+
+
+
+#[test]
+fn glob_dat_and_jump_slot_resolve_defined_symbols() {
+    let (base, mem) = reloc(
+        &[(0, ST_INFO_GLOBAL_FUNC, SHNDX_TEXT, 0x1000)],
+        &[(0x2800, (1 << 32) | R_X86_64_GLOB_DAT,  8)],
+        &[(0x2808, (1 << 32) | R_X86_64_JUMP_SLOT, 0)],
+    ).expect("re-locating failed");
+
+    assert_eq!(u64_at(&mem, 0x2800) as usize, base + 0x1008);
+    assert_eq!(u64_at(&mem, 0x2808) as usize, base + 0x1000);
+}
+
+#[test]
+fn reloc_to_a_foreign_base_writes_foreign_addresses() {
+    // Some address the loaded memory is surely not at, as if shared with a sandbox.
+    let v_base = 0x7A00_0000_0000;
+
+    let elf = with_syms(&[
+        (0,    ST_INFO_GLOBAL_FUNC, SHNDX_TEXT, 0x1000),
+        (PUTS, ST_INFO_GLOBAL_FUNC, 0,          0),
+    ], RelocElf {
+        relas: &[
+            (0x2800, R_X86_64_RELATIVE, 0x1234),
+            (0x2808, (1 << 32) | R_X86_64_64, 8),
+            (0x2810, (2 << 32) | R_X86_64_GLOB_DAT, 0),
+        ],
+        plt_relas: &[(0x2818, (1 << 32) | R_X86_64_JUMP_SLOT, 0)],
+        ..RelocElf::default()
+    });
+
+    let (base, mem) = reloc_raw(&elf.build(), Some(resolve), Some(v_base))
+                          .expect("re-locating failed");
+
+    assert_eq!(base, v_base);
+    assert_eq!(u64_at(&mem, 0x2800) as usize, v_base + 0x1234);
+    assert_eq!(u64_at(&mem, 0x2808) as usize, v_base + 0x1008);
+    assert_eq!(u64_at(&mem, 0x2818) as usize, v_base + 0x1000);
+
+    // Addresses of other objects are already in the foreign address space.
+    assert_eq!(u64_at(&mem, 0x2810) as usize, PUTS_ADDR);
+}
+
+#[test]
+fn rel_and_rela_tables_may_be_mixed() {
+    for &plt_rel in &[DT_REL, DT_RELA] {
+        for &reversed in &[false, true] {
+            let elf = with_syms(&[(0, ST_INFO_GLOBAL_FUNC, SHNDX_TEXT, 0x1000)], RelocElf {
+                rels:  &[(0x2800, R_X86_64_RELATIVE)],
+                relas: &[(0x2808, R_X86_64_RELATIVE, 0x200), (0x2810, (1 << 32) | R_X86_64_64, 8)],
+                words: &[0x100],
+                reversed,
+                ..RelocElf::default()
+            });
+
+            // The PLT table is of its own kind, no matter the others.
+            let slot = (0x2818, (1 << 32) | R_X86_64_JUMP_SLOT);
+            let elf  = match plt_rel {
+                DT_REL => RelocElf { plt_rels:  &[slot],                 ..elf },
+                _      => RelocElf { plt_relas: &[(slot.0, slot.1, 0)], ..elf },
+            };
+
+            let (base, mem) = reloc_raw(&elf.build(), None, None).expect("re-locating failed");
+            let base        = base as u64;
+
+            assert_eq!(u64_at(&mem, 0x2800), base + 0x0100, "Rel");
+            assert_eq!(u64_at(&mem, 0x2808), base + 0x0200, "Rela");
+            assert_eq!(u64_at(&mem, 0x2810), base + 0x1008, "Rela");
+            assert_eq!(u64_at(&mem, 0x2818), base + 0x1000, "PLT of kind {}", plt_rel);
+        }
+    }
+}
+
+#[test]
+fn loaded_elfs_may_be_re_located_many_times() {
+    let mut loaded = load(&with_syms(&[(0, ST_INFO_GLOBAL_FUNC, SHNDX_TEXT, 0x1000)], RelocElf {
+        relas: &[(0x2800, R_X86_64_RELATIVE, 0x1234), (0x2808, (1 << 32) | R_X86_64_64, 8)],
+        ..RelocElf::default()
+    }).build());
+
+    let word = |loaded: &mut LoadedElf, off: usize| unsafe {
+        (loaded.loader_base().add(off) as *const u64).read_unaligned() as usize
+    };
+
+    for v_base in [0x7A00_0000_0000, 0x1000_0000, 0x7A00_0000_0000] {
+        loaded.reloc_into(v_base as *mut u8, None, None).expect("re-locating failed");
+
+        assert_eq!(word(&mut loaded, 0x2800), v_base + 0x1234);
+        assert_eq!(word(&mut loaded, 0x2808), v_base + 0x1008);
+    }
+
+    let base  = loaded.loader_base();
+    let ready = loaded.try_reloc(base, None, None).map_err(|(_, e)| e).expect("finishing failed");
+
+    assert_eq!(u64_at(ready.p_mem(), 0x2800) as usize, base as usize + 0x1234);
+}
+
+#[test]
+fn implicit_addends_can_be_re_located_only_once() {
+    let mut loaded = load(&RelocElf {
+        rels: &[(0x2800, R_X86_64_RELATIVE)],
+        ..RelocElf::default()
+    }.build());
+
+    assert_eq!(loaded.reloc_into(0x1000_0000 as *mut u8, None, None), Ok(()));
+    assert_eq!(loaded.reloc_into(0x2000_0000 as *mut u8, None, None),
+               Err(RelocElfError::ImplicitAddends));
+    assert_eq!(loaded.validate_reloc(0x2000_0000 as *mut u8, None),
+               Err(RelocElfError::ImplicitAddends));
+}
+
+#[test]
+fn implicit_addends_stay_re_located_across_parts() {
+    let mut loaded = load(&RelocElf {
+        rels: &[(0x2800, R_X86_64_RELATIVE)],
+        ..RelocElf::default()
+    }.build());
+
+    assert_eq!(loaded.reloc_into(0x1000_0000 as *mut u8, None, None), Ok(()));
+
+    let (mem, meta) = loaded.into_parts();
+    let mut loaded  = LoadedElf::try_from_parts(mem, meta).expect("re-creating failed");
+
+    let word = unsafe { (loaded.loader_base().add(0x2800) as *const u64).read_unaligned() };
+
+    assert_eq!(word, 0x1000_0000);
+    assert_eq!(loaded.reloc_into(0x2000_0000 as *mut u8, None, None),
+               Err(RelocElfError::ImplicitAddends));
+}
+
+#[test]
+fn snapshots_are_copied_and_re_located_for_many_bases() {
+    let loaded = load(&RelocElf {
+        rels: &[(0x2800, R_X86_64_RELATIVE)],
+        ..RelocElf::default()
+    }.build());
+    let len    = loaded.mem_len();
+    let align  = loaded.mem_align() as usize;
+
+    // `Rel` addends survive in the snapshot, so every copy may be re-located.
+    for v_base in [0x7A00_0000_0000_usize, 0x1000_0000] {
+        let dst   = os::alloc_aligned(len, align);
+        let ready = loaded.copy_and_reloc(dst, v_base as *mut u8, None, None)
+                          .expect("re-locating the copy failed");
+
+        assert_eq!(u64_at(ready.p_mem(), 0x2800) as usize, v_base);
+    }
+
+    assert_eq!(u64_at(loaded.snapshot(), 0x2800), 0);
+
+    let small = os::alloc_aligned(len - 1, align);
+    let moved = &mut os::alloc_aligned(len + 1, align)[1..];
+
+    assert_eq!(loaded.copy_and_reloc(small, 0x1000_0000 as *mut u8, None, None).err(),
+               Some(ElfError::Load(LoadElfError::BadBufferSize)));
+    assert_eq!(loaded.copy_and_reloc(moved, 0x1000_0000 as *mut u8, None, None).err(),
+               Some(ElfError::Load(LoadElfError::BadBufferAlignment)));
+}
+
+#[test]
+fn small_code_model_relocs_write_32_bits() {
+    let v_base = 0x1000_0000;
+
+    let elf = with_syms(&[(0, ST_INFO_GLOBAL_FUNC, SHNDX_TEXT, 0x1000)], RelocElf {
+        relas: &[
+            (0x2800, (1 << 32) | R_X86_64_PC32,  4),
+            (0x2808, (1 << 32) | R_X86_64_32,    8),
+            (0x2810, (1 << 32) | R_X86_64_32S,  -8),
+        ],
+        ..RelocElf::default()
+    });
+
+    let (_, mem) = reloc_raw(&elf.build(), None, Some(v_base)).expect("re-locating failed");
+
+    // `S + A - P`, i.e. backwards from `0x2800` to `0x1004`.
+    assert_eq!(u64_at(&mem, 0x2800), u64::from((0x1004_i32 - 0x2800) as u32));
+    assert_eq!(u64_at(&mem, 0x2808), 0x1000_1008);
+    assert_eq!(u64_at(&mem, 0x2810), 0x1000_0FF8);
+}
+
+#[test]
+fn small_code_model_relocs_must_not_overflow() {
+    let syms: Syms = &[
+        (0,    ST_INFO_GLOBAL_FUNC, SHNDX_TEXT, 0x1000),
+        (PUTS, ST_INFO_GLOBAL_FUNC, 0,          0),
+    ];
+
+    let reloc_one = |rela, v_base| {
+        let relas = &[rela];
+        let elf   = with_syms(syms, RelocElf { relas, ..RelocElf::default() });
+        reloc_raw(&elf.build(), Some(resolve), Some(v_base)).map(|_| ())
+    };
+
+    let high = 0x7A00_0000_0000;
+    let low  = 0x1000_0000;
+
+    assert_eq!(reloc_one((0x2800, (1 << 32) | R_X86_64_32,   0), high),
+               Err(RelocElfError::RelocationOverflow));
+    assert_eq!(reloc_one((0x2800, (1 << 32) | R_X86_64_32S,  0), high),
+               Err(RelocElfError::RelocationOverflow));
+    assert_eq!(reloc_one((0x2800, (2 << 32) | R_X86_64_PC32, 0), high),
+               Err(RelocElfError::RelocationOverflow));
+
+    // `puts` lives at `0xDEAD_B000`, which fits a `u32`, but not an `i32`.
+    assert_eq!(reloc_one((0x2800, (2 << 32) | R_X86_64_32,   0), low), Ok(()));
+    assert_eq!(reloc_one((0x2800, (2 << 32) | R_X86_64_32S,  0), low),
+               Err(RelocElfError::RelocationOverflow));
+
+    // Within the ELF, `PC32` fits no matter the base.
+    assert_eq!(reloc_one((0x2800, (1 << 32) | R_X86_64_PC32, 0), high), Ok(()));
+}
+
+#[test]
+fn bases_above_4gib_are_fine() {
+    let v_base = 0x7F00_0000_0000_usize;
+
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[(DT_RELA, 0x2000), (DT_RELASZ, 24), (DT_RELAENT, 24)]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x2000, 0x1000, &rela_table(&[(0x2800, R_X86_64_RELATIVE, 0x1234)]));
+    b.entry(0x1000);
+
+    let raw        = b.build();
+    let elf        = Elf::try_parse(&raw).expect("parsing failed");
+    let mem        = os::alloc_aligned(elf.mem_len_usize(), elf.mem_align_usize());
+    let mut loaded = elf.try_load(mem).expect("loading failed");
+    let p_base     = loaded.loader_base() as usize;
+
+    let ready = loaded.try_reloc(v_base as *mut u8, None, None)
+                      .map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+
+    // The full 64-bit address, not just its lower 32 bits.
+    assert_eq!(u64_at(ready.p_mem(), 0x2800), 0x7F00_0000_1234);
+    assert_eq!(ready.v_entry() as usize, v_base + 0x1000);
+    assert_eq!(ready.p_entry() as usize, p_base + 0x1000);
+
+    let code = ready.segment_at(v_base + 0x1800).expect("no segment at the code");
+
+    assert_eq!(code.v_range, (v_base + 0x1000) .. (v_base + 0x2000));
+    assert_eq!(code.p_range, (p_base + 0x1000) .. (p_base + 0x2000));
+    assert!(ready.segment_at(0x1800).is_none());
+}
+
+#[test]
+fn elfs_linked_above_zero_are_relative_to_their_link_base() {
+    const LINKED: u64 = 0x40_0000;
+
+    let mut data = rela_table(&[
+        (LINKED + 0x2800, R_X86_64_RELATIVE, (LINKED + 0x1234) as i64),
+        (LINKED + 0x2808, (1 << 32) | R_X86_64_64, 8),
+    ]);
+
+    // SysV hash at 0x2100, with a single bucket holding `puts`.
+    data.resize(0x100, 0);
+    for x in &[1_u32, 2, 1, 0, 0] { data.extend_from_slice(&x.to_le_bytes()); }
+    data.resize(0x200, 0);
+    data.extend(sym_table(&[(PUTS, ST_INFO_GLOBAL_FUNC, SHNDX_TEXT, LINKED + 0x1000)]));
+    data.resize(0x300, 0);
+    data.extend_from_slice(STRTAB);
+
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(LINKED, &[
+        (DT_RELA,   LINKED + 0x2000), (DT_RELASZ, 48), (DT_RELAENT, 24),
+        (DT_HASH,   LINKED + 0x2100),
+        (DT_SYMTAB, LINKED + 0x2200),
+        (DT_STRTAB, LINKED + 0x2300), (DT_STRSZ, STRTAB.len() as u64),
+    ]);
+    b.segment(PT_LOAD, PF_RX, LINKED + 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, LINKED + 0x2000, 0x1000, &data);
+    b.entry(LINKED + 0x1000);
+
+    let raw = b.build();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    // Still position-independent, just without wasting 4MiB on nothing.
+    assert_eq!(elf.link_base(), LINKED);
+    assert_eq!(elf.fixed_base(), None);
+    assert_eq!(elf.mem_len(), 0x3000);
+    assert_eq!(elf.entry(), 0x1000);
+
+    let mem        = os::alloc_aligned(elf.mem_len_usize(), elf.mem_align_usize());
+    let mut loaded = elf.try_load(mem).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    let ready = loaded.try_reloc(base, None, None)
+                      .map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+    let base  = base as usize;
+
+    assert_eq!(u64_at(ready.p_mem(), 0x2800) as usize, base + 0x1234);
+    assert_eq!(u64_at(ready.p_mem(), 0x2808) as usize, base + 0x1008);
+    assert_eq!(ready.p_entry() as usize, base + 0x1000);
+    assert_eq!(ready.lookup("puts").map(|p| p as usize), Some(base + 0x1000));
+}
+
+/// Every `(done, total)` pair `record_progress` got, for `progress_is_reported_in_steps`.
+static PROGRESS: std::sync::Mutex<Vec<(usize, usize)>> = std::sync::Mutex::new(Vec::new());
+
+extern "C" fn record_progress(done: usize, total: usize) {
+    PROGRESS.lock().expect("poisoned").push((done, total));
+}
+
+#[test]
+fn progress_is_reported_in_steps() {
+    // Way more entries than get applied between two reports.
+    const COUNT:  u64 = 5000;
+    const PLACES: u64 = 0x2_2000;
+
+    let relas = (0..COUNT).map(|i| (PLACES + (8 * i), R_X86_64_RELATIVE, i as i64))
+                          .collect::<Vec<_>>();
+
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[(DT_RELA, 0x2000), (DT_RELASZ, 24 * COUNT), (DT_RELAENT, 24)]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x2000, 0x3_0000, &rela_table(&relas));
+
+    let raw        = b.build();
+    let elf        = Elf::try_parse(&raw).expect("parsing failed");
+    let mem        = os::alloc_aligned(elf.mem_len_usize(), elf.mem_align_usize());
+    let mut loaded = elf.try_load(mem).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    let ready = loaded.try_reloc_with_progress(base, None, None, Some(record_progress))
+                      .map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+
+    assert_eq!(*PROGRESS.lock().expect("poisoned"), vec![(4096, 5000), (5000, 5000)]);
+    assert_eq!(u64_at(ready.p_mem(), (PLACES + 8 * 4999) as usize), (base as u64) + 4999);
+}
+
+#[test]
+fn copy_relocations_copy_from_the_resolved_symbol() {
+    const SHNDX_DATA: u16 = 2;
+
+    // Executables define the symbol right at the place, but the data is in another object.
+    let reloc_copy = |place, size, resolve| {
+        let relas  = &[(place, (1 << 32) | R_X86_64_COPY, 0)];
+        let symtab = sized_sym_table(&[(ENVIRON, ST_INFO_GLOBAL_OBJ, SHNDX_DATA, place, size)]);
+
+        reloc_raw(&RelocElf { relas, symtab, strtab: STRTAB, ..RelocElf::default() }.build(),
+                  resolve, None)
+    };
+
+    let (_, mem) = reloc_copy(0x2800, 16, Some(resolve)).expect("re-locating failed");
+
+    assert_eq!(&mem[0x2800..0x2810], &ENVIRON_DATA);
+    assert_eq!(u64_at(&mem, 0x2810), 0);
+
+    // Copying right up to the end of memory is fine, but not past it.
+    assert!(reloc_copy(0x2FF0, 16, Some(resolve)).is_ok());
+    assert_eq!(reloc_copy(0x2FF8, 16, Some(resolve)).err(), Some(RelocElfError::BadRelaOffset));
+    assert_eq!(reloc_copy(0x2800, u64::MAX, Some(resolve)).err(),
+               Some(RelocElfError::BadRelaOffset));
+
+    assert_eq!(reloc_copy(0x2800, 16, None).err(), Some(RelocElfError::UnresolvedSymbol));
+}
+
+#[test]
+fn null_symbol_works_like_relative() {
+    let (base, mem) = reloc(
+        &[],
+        &[(0x2800, R_X86_64_GLOB_DAT, 0x10)],
+        &[],
+    ).expect("re-locating failed");
+
+    assert_eq!(u64_at(&mem, 0x2800) as usize, base + 0x10);
+}
+
+#[test]
+fn symbol_index_past_the_end_is_rejected() {
+    assert_eq!(
+        reloc(&[], &[(0x2800, (1000 << 32) | R_X86_64_GLOB_DAT, 0)], &[]).err(),
+        Some(RelocElfError::BadSymbolIndex)
+    );
+}
+
+#[test]
+fn undefined_symbols_need_a_resolver() {
+    assert_eq!(
+        reloc(&[(PUTS, ST_INFO_GLOBAL_FUNC, 0, 0)], &[], &[
+            (0x2800, (1 << 32) | R_X86_64_JUMP_SLOT, 0),
+        ]).err(),
+        Some(RelocElfError::UnresolvedSymbol)
+    );
+}
+
+#[test]
+fn undefined_symbols_are_resolved() {
+    let (_, mem) = reloc_with(
+        &[(PUTS, ST_INFO_GLOBAL_FUNC, 0, 0)],
+        &[(0x2800, (1 << 32) | R_X86_64_64, 4)],
+        &[(0x2808, (1 << 32) | R_X86_64_JUMP_SLOT, 0)],
+        Some(resolve),
+    ).expect("re-locating failed");
+
+    assert_eq!(u64_at(&mem, 0x2800) as usize, PUTS_ADDR + 4);
+    assert_eq!(u64_at(&mem, 0x2808) as usize, PUTS_ADDR);
+}
+
+#[test]
+fn unknown_symbols_are_rejected() {
+    assert_eq!(
+        reloc_with(
+            &[(MISSING, ST_INFO_GLOBAL_FUNC, 0, 0)],
+            &[(0x2800, (1 << 32) | R_X86_64_64, 0)],
+            &[],
+            Some(resolve),
+        ).err(),
+        Some(RelocElfError::UnresolvedSymbol)
+    );
+}
+
+#[test]
+fn unknown_weak_symbols_are_zero() {
+    let (_, mem) = reloc_with(
+        &[(MISSING, ST_INFO_WEAK_FUNC, 0, 0)],
+        &[(0x2800, (1 << 32) | R_X86_64_64, 0)],
+        &[],
+        Some(resolve),
+    ).expect("re-locating failed");
+
+    assert_eq!(u64_at(&mem, 0x2800), 0);
+}
+
+#[test]
+fn r_x86_64_64_adds_the_addend_to_defined_symbols() {
+    let (base, mem) = reloc(
+        &[(0, ST_INFO_GLOBAL_FUNC, SHNDX_TEXT, 0x1000)],
+        &[(0x2800, (1 << 32) | R_X86_64_64, -8)],
+        &[],
+    ).expect("re-locating failed");
+
+    assert_eq!(u64_at(&mem, 0x2800) as usize, base + 0x1000 - 8);
+}
+
+/// Loads and re-locates a `RelocElf` with the given `Rel` entries and symbols, and the given
+/// implicit addends stored at `0x2800` and following.
+fn reloc_rel(syms: Syms, rels: &[(u64, u64)], addends: &[u64], resolve: Option<ResolveFn>)
+-> Result<(usize, Vec<u8>), RelocElfError> {
+    let elf = with_syms(syms, RelocElf { rels, words: addends, ..RelocElf::default() });
+
+    reloc_raw(&elf.build(), resolve, None)
+}
+
+#[test]
+fn rel_entries_take_their_addend_from_the_place() {
+    let (base, mem) = reloc_rel(
+        &[(0, ST_INFO_GLOBAL_FUNC, SHNDX_TEXT, 0x1000)],
+        &[(0x2800, R_X86_64_RELATIVE), (0x2808, (1 << 32) | R_X86_64_64)],
+        &[0x1234, 8],
+        None,
+    ).expect("re-locating failed");
+
+    assert_eq!(u64_at(&mem, 0x2800) as usize, base + 0x1234);
+    assert_eq!(u64_at(&mem, 0x2808) as usize, base + 0x1008);
+}
+
+#[test]
+fn rel_entries_resolve_undefined_symbols() {
+    let syms: Syms = &[(PUTS, ST_INFO_GLOBAL_FUNC, 0, 0), (MISSING, ST_INFO_GLOBAL_FUNC, 0, 0)];
+
+    let (_, mem) = reloc_rel(syms, &[(0x2800, (1 << 32) | R_X86_64_64)], &[8], Some(resolve))
+        .expect("re-locating failed");
+
+    assert_eq!(u64_at(&mem, 0x2800) as usize, PUTS_ADDR + 8);
+
+    assert_eq!(reloc_rel(syms, &[(0x2800, (1 << 32) | R_X86_64_64)], &[0], None).err(),
+               Some(RelocElfError::UnresolvedSymbol));
+    assert_eq!(reloc_rel(syms, &[(0x2800, (2 << 32) | R_X86_64_64)], &[0], Some(resolve))
+                   .err(),
+               Some(RelocElfError::UnresolvedSymbol));
+}
+
+#[test]
+fn places_must_fit_a_whole_u64() {
+    assert_eq!(reloc_rel(&[], &[(0x2FF9, R_X86_64_RELATIVE)], &[], None).err(),
+               Some(RelocElfError::BadRelOffset));
+    assert_eq!(reloc(&[], &[(0x2FF9, R_X86_64_RELATIVE, 0)], &[]).err(),
+               Some(RelocElfError::BadRelaOffset));
+}
+
+#[test]
+fn small_code_model_places_must_fit_a_u32_only() {
+    let reloc_one = |place| {
+        let relas = &[(place, (1 << 32) | R_X86_64_32, 8)];
+        let elf   = with_syms(&[(0, ST_INFO_GLOBAL_FUNC, SHNDX_TEXT, 0x1000)], RelocElf {
+            relas, ..RelocElf::default()
+        });
+        reloc_raw(&elf.build(), None, Some(0x1000_0000))
+    };
+
+    // The very last four bytes of memory are just fine, if that's all there is to write.
+    let (_, mem) = reloc_one(0x2FFC).expect("re-locating failed");
+
+    assert_eq!(mem.len(), 0x3000);
+    assert_eq!(&mem[0x2FFC..], &0x1000_1008_u32.to_le_bytes());
+
+    assert_eq!(reloc_one(0x2FFD).err(), Some(RelocElfError::BadRelaOffset));
+}
+
+/// Re-locates a single `RELATIVE` entry at `place`, with some extra dynamic entries.
+fn reloc_relative(place: u64, dyns: &[(u64, u64)]) -> Result<(), RelocElfError> {
+    let elf = RelocElf { relas: &[(place, R_X86_64_RELATIVE, 0)], dyns, ..RelocElf::default() };
+
+    reloc_raw(&elf.build(), None, None).map(|_| ())
+}
+
+#[test]
+fn text_relocations_need_df_textrel() {
+    assert_eq!(reloc_relative(0x1008, &[]),               Err(RelocElfError::TextRelocation));
+    assert_eq!(reloc_relative(0x1008, &[(DT_TEXTREL, 0)]), Ok(()));
+
+    // Places straddling the start of code count, too, but data is fine.
+    assert_eq!(reloc_relative(0x0FFC, &[]), Err(RelocElfError::TextRelocation));
+    assert_eq!(reloc_relative(0x2800, &[]), Ok(()));
+}
+
+#[test]
+fn text_relocations_may_be_allowed_either_way() {
+    const DF_TEXTREL: u64 = 0x04;
+
+    // The legacy `DT_TEXTREL` tag and the `DF_TEXTREL` bit of `DT_FLAGS` mean just the same.
+    assert_eq!(reloc_relative(0x1008, &[(DT_TEXTREL, 0)]),                Ok(()));
+    assert_eq!(reloc_relative(0x1008, &[(DT_FLAGS, DF_TEXTREL)]),         Ok(()));
+    assert_eq!(reloc_relative(0x1008, &[(DT_FLAGS, 0), (DT_TEXTREL, 0)]), Ok(()));
+
+    // Other flags don't.
+    assert_eq!(reloc_relative(0x1008, &[(DT_FLAGS, !DF_TEXTREL)]),
+               Err(RelocElfError::TextRelocation));
+}
+
+#[test]
+fn rela_tables_may_end_at_the_end_of_memory() {
+    let mut data = vec![0; 0xFE8];
+    data.extend(rela_table(&[(0x2800, R_X86_64_RELATIVE, 0x1234)]));
+
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[(DT_RELA, 0x2FE8), (DT_RELASZ, 24), (DT_RELAENT, 24)]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x2000, 0x1000, &data);
+
+    let raw        = b.build();
+    let elf        = Elf::try_parse(&raw).expect("parsing failed");
+    let mem        = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+    let mut loaded = elf.try_load(mem).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    assert_eq!(loaded.mem_len(), 0x3000);
+
+    let ready = loaded.try_reloc(base, None, None).map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+
+    assert_eq!(u64_at(ready.p_mem(), 0x2800) as usize, base as usize + 0x1234);
+}
+
+/// Re-locates an ELF whose only re-location table is a PLT one, as described by the given
+/// `DT_JMPREL`, `DT_PLTRELSZ` and `DT_PLTREL` values.
+fn reloc_plt(jmprel: u64, pltrelsz: u64, pltrel: u64) -> Result<(), RelocElfError> {
+    let table = rela_table(&[(0x2800, R_X86_64_RELATIVE, 0)]);
+
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[(DT_JMPREL, jmprel), (DT_PLTRELSZ, pltrelsz), (DT_PLTREL, pltrel)]);
+    b.segment(PT_LOAD, PF_RW, 0x2000, 0x1000, &table);
+
+    let raw        = b.build();
+    let elf        = Elf::try_parse(&raw).expect("parsing failed");
+    let mem        = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+    let mut loaded = elf.try_load(mem).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    loaded.try_reloc(base, None, None).map(|_| ()).map_err(|(_, e)| e)
+}
+
+#[test]
+fn plt_tables_are_checked() {
+    assert_eq!(reloc_plt(0x2000, 24, DT_RELA), Ok(()));
+    assert_eq!(reloc_plt(0x2000, 23, DT_RELA), Err(RelocElfError::BadPltSize));
+    assert_eq!(reloc_plt(0x2000, 24, DT_RELR), Err(RelocElfError::BadPltSize));
+    assert_eq!(reloc_plt(0x2FF0, 24, DT_RELA), Err(RelocElfError::BadPltRange));
+    assert_eq!(reloc_plt(0x2004, 24, DT_RELA), Err(RelocElfError::BadPltRange));
+}
+
+/// Re-locates a `RelocElf` with the given `Rela` entries, and the given count of leading
+/// `RELATIVE` ones, i.e. `DT_RELACOUNT`, or `DT_RELCOUNT` if `count_tag` says so.
+fn reloc_counted(relas: Relas, count_tag: u64, count: u64)
+-> Result<(usize, Vec<u8>), RelocElfError> {
+    let dyns = &[(count_tag, count)];
+    let elf  = with_syms(&[(0, ST_INFO_GLOBAL_FUNC, SHNDX_TEXT, 0x1000)], RelocElf {
+        relas, dyns, ..RelocElf::default()
+    });
+
+    reloc_raw(&elf.build(), None, None)
+}
+
+#[test]
+fn counted_relative_relocations_come_first() {
+    let (base, mem) = reloc_counted(&[
+        (0x2800, R_X86_64_RELATIVE, 0x10),
+        (0x2808, R_X86_64_RELATIVE, 0x20),
+        (0x2810, (1 << 32) | R_X86_64_64, 8),
+    ], DT_RELACOUNT, 2).expect("re-locating failed");
+
+    assert_eq!(u64_at(&mem, 0x2800) as usize, base + 0x10);
+    assert_eq!(u64_at(&mem, 0x2808) as usize, base + 0x20);
+    assert_eq!(u64_at(&mem, 0x2810) as usize, base + 0x1008);
+}
+
+#[test]
+fn counted_relative_relocations_are_bounds_checked() {
+    assert_eq!(reloc_counted(&[(0x2FF9, R_X86_64_RELATIVE, 0)], DT_RELACOUNT, 1).err(),
+               Some(RelocElfError::BadRelaOffset));
+}
+
+#[test]
+fn rel_counts_must_fit_their_table() {
+    let relas: Relas = &[(0x2800, R_X86_64_RELATIVE, 0)];
+
+    assert_eq!(reloc_counted(relas, DT_RELACOUNT, 1).err(), None);
+    assert_eq!(reloc_counted(relas, DT_RELACOUNT, 2).err(), Some(RelocElfError::BadRelCount));
+    assert_eq!(reloc_counted(relas, DT_RELCOUNT,  1).err(), Some(RelocElfError::BadRelCount));
+}
+
+/// Dry-runs re-locating a `RelocElf` with the given `Rela` entries and a single defined symbol,
+/// returning the loaded memory afterwards.
+fn validate(relas: Relas) -> Result<Vec<u8>, RelocElfError> {
+    let elf = with_syms(&[(0, ST_INFO_GLOBAL_FUNC, SHNDX_TEXT, 0x1000)], RelocElf {
+        relas, ..RelocElf::default()
+    });
+
+    let mut loaded = load(&elf.build());
+    let base       = loaded.loader_base();
+
+    loaded.validate_reloc(base, None)?;
+
+    Ok(loaded.into_parts().0.to_vec())
+}
+
+#[test]
+fn validating_writes_nothing() {
+    let mem = validate(&[
+        (0x2800, R_X86_64_RELATIVE, 0x10),
+        (0x2808, (1 << 32) | R_X86_64_64, 8),
+    ]).expect("validating failed");
+
+    assert_eq!(u64_at(&mem, 0x2800), 0);
+    assert_eq!(u64_at(&mem, 0x2808), 0);
+}
+
+#[test]
+fn validating_catches_what_re_locating_would() {
+    assert_eq!(validate(&[(0x2FF9, R_X86_64_RELATIVE, 0)]).err(),
+               Some(RelocElfError::BadRelaOffset));
+    assert_eq!(validate(&[(0x2800, R_X86_64_COPY, 0)]).err(),
+               Some(RelocElfError::UnresolvedSymbol));
+    assert_eq!(validate(&[(0x2800, (2 << 32) | R_X86_64_64, 0)]).err(),
+               Some(RelocElfError::UnresolvedSymbol));
+}
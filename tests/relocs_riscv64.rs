@@ -0,0 +1,80 @@
+use elf_loader::*;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+const EM_RISCV: u16 = 243;
+
+const R_RISCV_64:        u64 = 2;
+const R_RISCV_RELATIVE:  u64 = 3;
+const R_RISCV_JUMP_SLOT: u64 = 5;
+
+/// `li a0, 42; ret`
+const RET_42: &[u8] = &[0x13, 0x05, 0xA0, 0x02, 0x67, 0x80, 0x00, 0x00];
+
+
+
+/// An ELF with one of each supported RV64 re-location, all referring to its entry point.
+fn riscv64_reloc_elf() -> Vec<u8> {
+    let mut b = RelocElf {
+        code:   RET_42,
+        relas:  &[
+            (0x2800,             R_RISCV_RELATIVE,  0x10),
+            (0x2808, (1 << 32) | R_RISCV_64,        0x08),
+            (0x2810, (1 << 32) | R_RISCV_JUMP_SLOT, 0x00),
+        ],
+        symtab: sym_table(&[(0, ST_INFO_GLOBAL_FUNC, 1, 0x1000)]),
+        ..RelocElf::default()
+    }.builder();
+
+    b.e_machine = EM_RISCV;
+    b.entry(0x1000);
+    b.build()
+}
+
+
+
+#[test]
+fn riscv64_elfs_are_inspectable_on_any_host() {
+    let raw = riscv64_reloc_elf();
+    let elf = Elf::try_parse_foreign(&raw).expect("parsing failed");
+
+    assert_eq!(elf.machine(), EM_RISCV);
+    assert_eq!(elf.launch_kind(), LaunchKind::DynamicPie);
+
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    assert_eq!(elf.try_load(mem).err(), Some(LoadElfError::ForeignElf));
+
+    // Only RV64 hosts may load it for real.
+    let native = Elf::try_parse(&raw);
+
+    match cfg!(target_arch = "riscv64") {
+        true  => assert!(native.is_ok()),
+        false => assert_eq!(native.err(), Some(ParseElfError::BadIsa)),
+    }
+}
+
+#[cfg(target_arch = "riscv64")]
+#[test]
+fn riscv64_relocations_are_applied() {
+    let raw        = riscv64_reloc_elf();
+    let elf        = Elf::try_parse(&raw).expect("parsing failed");
+    let mem        = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+    let mut loaded = elf.try_load(mem).expect("loading failed");
+    let base       = loaded.loader_base() as u64;
+
+    let ready = loaded.try_reloc(base as *mut u8, None, None)
+                      .map_err(|(_, e)| e)
+                      .expect("re-locating failed");
+
+    assert_eq!(u64_at(ready.p_mem(), 0x2800), base + 0x10);
+    assert_eq!(u64_at(ready.p_mem(), 0x2808), base + 0x1008);
+    assert_eq!(u64_at(ready.p_mem(), 0x2810), base + 0x1000);
+}
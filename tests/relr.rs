@@ -0,0 +1,86 @@
+use elf_loader::*;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+static RELR: &[u8] = include_bytes!("./relr.elf");
+
+
+
+/// Loads and re-locates a `RelocElf` with the given `Relr` table and `DT_RELRENT`, whose
+/// `0x2800..0x3000` is filled with the word offsets, i.e. `0, 1, 2, ...`.
+fn reloc_relr(relr: &[u64], relrent: u64) -> Result<(usize, Vec<u8>), RelocElfError> {
+    let words = (0..0x100).collect::<Vec<_>>();
+    let dyns  = &[(DT_RELRENT, relrent)];
+    let raw   = RelocElf { relr, words: &words, dyns, ..RelocElf::default() }.build();
+
+    let elf        = Elf::try_parse(&raw).expect("parsing failed");
+    let mem        = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+    let mut loaded = elf.try_load(mem).expect("loading failed");
+    let base       = loaded.loader_base();
+
+    let ready = loaded.try_reloc(base, None, None).map_err(|(_, e)| e)?;
+
+    Ok((base as usize, ready.p_mem().to_vec()))
+}
+
+
+
+#[test]
+fn packed_relative_relocations_are_applied() {
+    let buf = Vec::from(RELR);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `relr.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `relr.elf` failed");
+    let base       = loaded.loader_base();
+    let ready      = loaded.try_reloc(base, Some(os::protection_fn), None)
+                           .map_err(|(_, e)| e)
+                           .expect("Re-locating `relr.elf` failed");
+
+    let ptrs   = ready.lookup("ptrs"  ).expect("`ptrs` not found"  ) as *const *const i32;
+    let values = ready.lookup("values").expect("`values` not found") as *const i32;
+
+    for i in 0..70 {
+        assert_eq!(unsafe { *ptrs.add(i) }, unsafe { values.add(i) });
+    }
+}
+
+#[test]
+fn relr_bitmaps_follow_addresses() {
+    // Re-locate word 0, then words 1 and 3 via the first bitmap, then the last one of the
+    // second bitmap's 63 words, which start at word 64.
+    let (base, mem) = reloc_relr(&[0x2800, 0b1011, (1 << 63) | 1], 8)
+                          .expect("re-locating failed");
+    let base        = base as u64;
+
+    let relocated = [0, 1, 3, 64 + 62];
+
+    for word in 0..0x100 {
+        let expected = match relocated.contains(&word) {
+            true  => base + word as u64,
+            false =>        word as u64,
+        };
+
+        assert_eq!(u64_at(&mem, 0x2800 + (8 * word)), expected, "word {}", word);
+    }
+}
+
+#[test]
+fn bad_relr_entry_size_is_rejected() {
+    assert_eq!(reloc_relr(&[0x2800], 16).err(), Some(RelocElfError::BadRelrSize));
+}
+
+#[test]
+fn relr_out_of_bounds_is_rejected() {
+    assert_eq!(reloc_relr(&[0x10000], 8).err(), Some(RelocElfError::BadRelrRange));
+
+    // A bitmap reaching past the end of memory.
+    assert_eq!(reloc_relr(&[0x2FF8, 0b101], 8).err(), Some(RelocElfError::BadRelrRange));
+}
@@ -0,0 +1,160 @@
+#![cfg(all(feature = "alloc", target_arch = "x86_64"))]
+
+use elf_loader::*;
+use std::ffi::CStr;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+const AT_PHDR:   u8 =  3;
+const AT_PHENT:  u8 =  4;
+const AT_PHNUM:  u8 =  5;
+const AT_BASE:   u8 =  7;
+const AT_RANDOM: u8 = 25;
+
+/// `mov edi, eax; mov eax, 231; syscall`, i.e. `exit_group` with `eax` as the exit code.
+const EXIT_EAX: &[u8] = &[0x89, 0xC7, 0xB8, 0xE7, 0x00, 0x00, 0x00, 0x0F, 0x05];
+
+
+
+fn cs(s: &[u8]) -> &CStr {
+    CStr::from_bytes_with_nul(s).unwrap()
+}
+
+/// Runs `code` followed by `EXIT_EAX` in a child process, returning its exit code.
+///
+/// The program headers are loaded along with the first segment.
+fn run(code: &[u8], args: &[&CStr], env: &[&CStr]) -> i32 {
+    let mut code = code.to_vec();
+    code.extend_from_slice(EXIT_EAX);
+
+    let mut b = ElfBuilder::new();
+    b.phdr(Phdr {
+        p_type: PT_LOAD, p_flags: PF_R,
+        p_offset: 0, p_vaddr: 0, p_paddr: 0,
+        p_filesz: 0x200, p_memsz: 0x200,
+        p_align: 0x1000,
+    });
+    b.segment(PT_LOAD, PF_RX, 0x1000, code.len() as u64, &code);
+    b.dynamic(0x2000, &[]);
+    b.entry(0x1000);
+
+    let raw = b.build();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("loading failed");
+    let base       = loaded.loader_base();
+    let ready      = loaded.try_reloc(base, Some(os::protection_fn), None)
+                           .map_err(|(_, e)| e)
+                           .expect("re-locating failed");
+
+    match unsafe { libc::fork() } {
+        -1 => panic!("`fork` failed"),
+        0  => unsafe { ready.run_main(args, env) },
+        id => {
+            let mut status = 0;
+
+            assert_eq!(unsafe { libc::waitpid(id, &mut status, 0) }, id);
+            assert!(libc::WIFEXITED(status), "the child did not exit: {:#X}", status);
+
+            libc::WEXITSTATUS(status)
+        },
+    }
+}
+
+/// Finds the auxiliary vector entry `key`, with its value ending up in `rdi`.
+fn find_aux(key: u8) -> Vec<u8> {
+    vec![
+        0x48, 0x8B, 0x04, 0x24,       // mov rax, [rsp]
+        0x48, 0x8D, 0x74, 0xC4, 0x10, // lea rsi, [rsp + 8 * rax + 16]
+        // Skip `envp`.
+        0x48, 0x8B, 0x06,             // mov rax, [rsi]
+        0x48, 0x83, 0xC6, 0x08,       // add rsi, 8
+        0x48, 0x85, 0xC0,             // test rax, rax
+        0x75, 0xF4,                   // jnz -12
+        // Look for `key`.
+        0x48, 0x8B, 0x06,             // mov rax, [rsi]
+        0x48, 0x8B, 0x7E, 0x08,       // mov rdi, [rsi + 8]
+        0x48, 0x83, 0xC6, 0x10,       // add rsi, 16
+        0x48, 0x83, 0xF8, key,        // cmp rax, key
+        0x75, 0xEF,                   // jne -17
+    ]
+}
+
+fn aux_value(key: u8) -> i32 {
+    let mut code = find_aux(key);
+    code.extend_from_slice(&[0x89, 0xF8]); // mov eax, edi
+
+    run(&code, &[cs(b"prog\0")], &[cs(b"A=1\0")])
+}
+
+
+
+#[test]
+fn stack_starts_with_argc() {
+    // mov rax, [rsp]
+    let code = [0x48, 0x8B, 0x04, 0x24];
+    let args = [cs(b"a\0"), cs(b"b\0"), cs(b"c\0")];
+
+    assert_eq!(run(&code, &args, &[cs(b"X=1\0")]), 3);
+}
+
+#[test]
+fn argv_follows_argc() {
+    // mov rax, [rsp + 16]; movzx eax, byte [rax]
+    let code = [0x48, 0x8B, 0x44, 0x24, 0x10, 0x0F, 0xB6, 0x00];
+    let args = [cs(b"prog\0"), cs(b"*argv\0")];
+
+    assert_eq!(run(&code, &args, &[]), b'*' as i32);
+}
+
+#[test]
+fn envp_follows_argv() {
+    // mov rax, [rsp + 32]; movzx eax, byte [rax + 1]
+    let code = [0x48, 0x8B, 0x44, 0x24, 0x20, 0x0F, 0xB6, 0x40, 0x01];
+
+    assert_eq!(run(&code, &[cs(b"prog\0")], &[cs(b"A=1\0"), cs(b"B=2\0")]), b'=' as i32);
+}
+
+#[test]
+fn there_is_no_rtld_fini() {
+    // lea eax, [rdx + 7]
+    let code = [0x8D, 0x42, 0x07];
+
+    assert_eq!(run(&code, &[cs(b"prog\0")], &[]), 7);
+}
+
+#[test]
+fn aux_vector_describes_the_program_headers() {
+    assert_eq!(aux_value(AT_PHNUM), 4);
+    assert_eq!(aux_value(AT_PHENT), 56);
+
+    // The first program header's `p_flags`.
+    let mut code = find_aux(AT_PHDR);
+    code.extend_from_slice(&[0x8B, 0x47, 0x04]); // mov eax, [rdi + 4]
+
+    assert_eq!(run(&code, &[cs(b"prog\0")], &[]), PF_R as i32);
+}
+
+#[test]
+fn aux_vector_has_no_interpreter_base() {
+    assert_eq!(aux_value(AT_BASE), 0);
+}
+
+#[test]
+fn aux_vector_points_at_random_bytes() {
+    let mut code = find_aux(AT_RANDOM);
+    code.extend_from_slice(&[
+        0xF3, 0x0F, 0x6F, 0x07,       // movdqu xmm0, [rdi]
+        0xB8, 0x2A, 0x00, 0x00, 0x00, // mov eax, 42
+    ]);
+
+    assert_eq!(run(&code, &[cs(b"prog\0")], &[]), 42);
+}
@@ -0,0 +1,119 @@
+use elf_loader::*;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+static SYMBOLS: &[u8] = include_bytes!("./symbols.elf");
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB:   u32 = 2;
+const SHF_ALLOC:    u64 = 0b010;
+const SHF_EXEC:     u64 = 0b100;
+
+
+
+#[test]
+fn section_headers_are_named() {
+    let buf = Vec::from(SYMBOLS);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `symbols.elf` failed");
+
+    let names = elf.section_headers().map(|sh| sh.name).collect::<Vec<_>>();
+
+    assert_eq!(names, [
+        Some(""), Some(".note.gnu.build-id"), Some(".gnu.hash"), Some(".dynsym"),
+        Some(".dynstr"), Some(".text"), Some(".eh_frame_hdr"), Some(".eh_frame"),
+        Some(".dynamic"), Some(".data"), Some(".comment"), Some(".symtab"), Some(".strtab"),
+        Some(".shstrtab"),
+    ]);
+}
+
+#[test]
+fn section_headers_describe_their_sections() {
+    let buf = Vec::from(SYMBOLS);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `symbols.elf` failed");
+
+    let find = |name| elf.section_headers().find(|sh| sh.name == Some(name)).unwrap();
+
+    let text = find(".text");
+    assert_eq!(text.kind,   SHT_PROGBITS);
+    assert_eq!(text.flags,  SHF_ALLOC | SHF_EXEC);
+    assert_eq!(text.addr,   0x1000);
+    assert_eq!(text.offset, 0x1000);
+    assert_eq!(text.size,   0x14);
+    assert_eq!(text.data(), Some(&buf[0x1000..0x1014]));
+
+    let symtab = find(".symtab");
+    assert_eq!(symtab.kind, SHT_SYMTAB);
+    assert_eq!(symtab.data().map(|d| d.len()), Some(0xC0));
+}
+
+#[test]
+fn stripped_elfs_have_no_sections() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+
+    let raw = b.build();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    assert_eq!(elf.section_headers().count(), 0);
+}
+
+/// Builds a tiny ELF whose header claims `shnum` section headers of `shentsize` bytes each
+/// at `shoff`.
+fn with_section_headers(shoff: u64, shentsize: u16, shnum: u16) -> Vec<u8> {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+
+    let mut raw = b.build();
+    raw[0x28..0x30].copy_from_slice(&shoff    .to_le_bytes());
+    raw[0x3A..0x3C].copy_from_slice(&shentsize.to_le_bytes());
+    raw[0x3C..0x3E].copy_from_slice(&shnum    .to_le_bytes());
+    raw
+}
+
+#[test]
+fn section_headers_past_the_end_are_ignored() {
+    let end = with_section_headers(0x1000, 64, 1).len() as u64;
+
+    // A table ending right at the end of the buffer is fine.
+    let raw = with_section_headers(end - 64, 64, 1);
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+    assert_eq!(elf.try_section_headers().map(|shs| shs.count()).ok(), Some(1));
+
+    for shoff in [end - 63, u64::MAX] {
+        let raw = with_section_headers(shoff, 64, 1);
+        let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+        assert_eq!(elf.section_headers().count(), 0);
+        assert_eq!(elf.try_section_headers().err(), Some(ParseElfError::SectionHeaderOverflow));
+    }
+
+    let raw  = with_section_headers(end, 64, 1);
+    let lazy = Elf::try_parse_lazy(&raw).expect("parsing failed");
+    let elf  = lazy.validate().expect("validating failed");
+
+    assert_eq!(elf.try_section_headers().err(), Some(ParseElfError::SectionHeaderOverflow));
+}
+
+#[test]
+fn bad_section_header_size_is_ignored() {
+    let raw = with_section_headers(0x1000, 40, 1);
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+
+    assert_eq!(elf.section_headers().count(), 0);
+    assert_eq!(elf.try_section_headers().err(), Some(ParseElfError::BadSectionHeaderSize));
+
+    // Loading doesn't care about section headers at all.
+    let mem = os::alloc_aligned(elf.mem_len_usize(), elf.mem_align_usize());
+    assert!(elf.try_load(mem).is_ok());
+}
@@ -0,0 +1,72 @@
+use elf_loader::*;
+use elf_loader::SegmentProtection::*;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+static SHARED_PAGE: &[u8] = include_bytes!("./shared_page.elf");
+
+
+
+#[test]
+fn shared_pages_get_the_union_of_their_protection() {
+    let buf = Vec::from(SHARED_PAGE);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `shared_page.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `shared_page.elf` failed");
+    let base       = loaded.loader_base();
+
+    assert!(loaded.shares_pages());
+
+    // The read-only segment comes last, and would make the code non-executable otherwise.
+    assert_eq!(loaded.effective_protection_map().collect::<Vec<_>>(), [
+        (0x0000 .. 0x1000, RX),
+        (0x1000 .. 0x1104, RW),
+    ]);
+
+    let ready = loaded.try_reloc(base, Some(os::protection_fn), None)
+                      .map_err(|(_, e)| e)
+                      .expect("Re-locating `shared_page.elf` failed");
+    let bump  = ready.lookup("bump").expect("`bump` not found");
+    let bump: extern "C" fn() -> i32 = unsafe { std::mem::transmute(bump) };
+
+    assert_eq!(((bump)(), (bump)()), (49, 50));
+}
+
+#[test]
+fn writable_and_executable_segments_must_not_share_a_page() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x800, &ret_imm32(42));
+    b.segment(PT_LOAD, PF_RW, 0x1800, 0x800, &[]);
+    b.entry(0x1000);
+
+    let raw = b.build();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    assert_eq!(elf.try_load(mem).err(), Some(LoadElfError::SharedPageConflict));
+}
+
+#[test]
+fn page_aligned_segments_share_no_pages() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+    b.entry(0x1000);
+
+    let raw = b.build();
+    let elf = Elf::try_parse(&raw).expect("parsing failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    assert!(!elf.try_load(mem).expect("loading failed").shares_pages());
+}
@@ -1,6 +1,7 @@
+#![allow(clippy::drop_non_drop, clippy::zero_prefixed_literal)]
+
 
 use elf_loader::*;
-use std::mem;
 
 
 
@@ -8,7 +9,7 @@ mod os;
 
 
 
-// FIXME force alignment, using a custom section if necessary.
+// Parsing copies headers out of the buffer, so it needs no alignment.
 static ELF: &[u8] = include_bytes!("./simple.elf");
 
 
@@ -31,12 +32,62 @@ fn simple_elf_works() {
     drop(buf);
 
     let base  = loaded_elf.loader_base();
-    let ready = loaded_elf.try_reloc(base, Some(os::protection_fn))
+    let ready = loaded_elf.try_reloc(base, Some(os::protection_fn), None)
                           .expect("Re-locating `simple.elf` failed");
 
-    let main: fn()->i32 = unsafe { mem::transmute(ready.p_entry()) };
+    let main: fn()->i32 = unsafe { ready.entry_fn() };
 
     let res = (main)();
 
     assert_eq!(res, 0815);
 }
+
+#[test]
+fn one_elf_loads_into_many_buffers() {
+    let buf = Vec::from(ELF);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `simple.elf` failed");
+
+    let mem_len   = elf.mem_len()   as usize;
+    let mem_align = elf.mem_align() as usize;
+
+    let mut slot_a = elf.try_load(os::alloc_aligned(mem_len, mem_align))
+                        .expect("Loading `simple.elf` into slot A failed");
+    let mut slot_b = elf.try_load(os::alloc_aligned(mem_len, mem_align))
+                        .expect("Loading `simple.elf` into slot B failed");
+
+    let (base_a, base_b) = (slot_a.loader_base(), slot_b.loader_base());
+    assert_ne!(base_a, base_b);
+
+    let ready_a = slot_a.try_reloc(base_a, Some(os::protection_fn), None)
+                        .expect("Re-locating slot A failed");
+    let ready_b = slot_b.try_reloc(base_b, Some(os::protection_fn), None)
+                        .expect("Re-locating slot B failed");
+
+    let main_a: fn()->i32 = unsafe { ready_a.entry_fn() };
+    let main_b: fn()->i32 = unsafe { ready_b.entry_fn() };
+
+    assert_ne!(main_a as usize, main_b as usize);
+    assert_eq!((main_a)(), 0815);
+    assert_eq!((main_b)(), 0815);
+}
+
+#[test]
+fn misaligned_buffers_parse_just_fine() {
+    // Odd offsets into a larger buffer, as if the ELF was embedded in some archive.
+    for off in 1..8 {
+        let mut buf = vec![0; off];
+        buf.extend_from_slice(ELF);
+
+        let elf = Elf::try_parse(&buf[off..]).expect("Parsing misaligned `simple.elf` failed");
+        let mem = os::alloc_aligned(elf.mem_len_usize(), elf.mem_align_usize());
+
+        let mut loaded = elf.try_load(mem).expect("Loading misaligned `simple.elf` failed");
+        let base       = loaded.loader_base();
+        let ready      = loaded.try_reloc(base, Some(os::protection_fn), None)
+                               .expect("Re-locating misaligned `simple.elf` failed");
+
+        let main: fn()->i32 = unsafe { ready.entry_fn() };
+
+        assert_eq!((main)(), 0815);
+    }
+}
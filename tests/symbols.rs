@@ -0,0 +1,455 @@
+use elf_loader::*;
+use std::mem;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+static ELF:      &[u8] = include_bytes!("./symbols.elf");
+static ELF_SYSV: &[u8] = include_bytes!("./symbols_sysv.elf");
+static ELF_BOTH: &[u8] = include_bytes!("./symbols_both.elf");
+
+
+
+fn load_and_reloc(raw: &[u8]) -> ReadyElf<'static> {
+    let elf = Elf::try_parse(raw).expect("Parsing failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading failed");
+    let base       = loaded.loader_base();
+
+    loaded.try_reloc(base, Some(os::protection_fn), None)
+          .map_err(|(_, e)| e)
+          .expect("Re-locating failed")
+}
+
+#[test]
+fn lookup_finds_functions_and_data() {
+    let buf   = Vec::from(ELF);
+    let ready = load_and_reloc(&buf);
+
+    let answer: extern "C" fn() -> i32 = unsafe {
+        mem::transmute(ready.lookup("answer").expect("`answer` not found"))
+    };
+    let twice: extern "C" fn(i32) -> i32 = unsafe {
+        mem::transmute(ready.lookup("twice").expect("`twice` not found"))
+    };
+    let counter = ready.lookup("counter").expect("`counter` not found") as *const i32;
+
+    assert_eq!(answer(),             42);
+    assert_eq!(twice(21),            42);
+    assert_eq!(unsafe { *counter },   7);
+}
+
+#[test]
+fn lookup_misses_absent_symbols() {
+    let buf   = Vec::from(ELF);
+    let ready = load_and_reloc(&buf);
+
+    assert!(ready.lookup("nope"   ).is_none());
+    assert!(ready.lookup("answe"  ).is_none());
+    assert!(ready.lookup("answers").is_none());
+    assert!(ready.lookup(""       ).is_none());
+}
+
+#[test]
+fn lookup_falls_back_to_sysv_hash() {
+    let buf   = Vec::from(ELF_SYSV);
+    let ready = load_and_reloc(&buf);
+
+    let answer: extern "C" fn() -> i32 = unsafe {
+        mem::transmute(ready.lookup("answer").expect("`answer` not found"))
+    };
+
+    assert_eq!(answer(), 42);
+    assert!(ready.lookup("counter").is_some());
+    assert!(ready.lookup("nope"   ).is_none());
+}
+
+#[test]
+fn lookup_falls_back_to_sysv_hash_if_gnu_hash_is_out_of_bounds() {
+    let mut buf = Vec::from(ELF_BOTH);
+
+    // Point `DT_GNU_HASH` far past the end of the loaded memory.
+    let dyns = {
+        let elf = Elf::try_parse(&buf).expect("Parsing `symbols_both.elf` failed");
+        let ph  = elf.program_headers().find(|ph| ph.kind == SegmentKind::Dynamic).unwrap();
+        let off = (ph.copy_from.as_ptr() as usize) - (buf.as_ptr() as usize);
+
+        off .. (off + ph.copy_from.len())
+    };
+
+    for entry in dyns.step_by(16) {
+        if buf[entry..(entry + 8)] == DT_GNU_HASH.to_le_bytes() {
+            buf[(entry + 8)..(entry + 16)].copy_from_slice(&0xFFFF_FFF0_u64.to_le_bytes());
+        }
+    }
+
+    let ready = load_and_reloc(&buf);
+
+    assert!(ready.lookup("answer" ).is_some());
+    assert!(ready.lookup("counter").is_some());
+    assert!(ready.lookup("nope"   ).is_none());
+}
+
+#[test]
+fn v_lookup_is_relative_to_base() {
+    let buf = Vec::from(ELF);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    // Pretend to run the ELF in another address space. It won't be called, after all.
+    let v_base = (0x4000_0000 + mem.as_ptr() as usize) as *mut u8;
+    let ready  = elf.try_load(mem).expect("Loading failed")
+                    .try_reloc(v_base, None, None)
+                    .map_err(|(_, e)| e)
+                    .expect("Re-locating failed");
+
+    let p = ready.  lookup("answer").expect("`answer` not found") as usize;
+    let v = ready.v_lookup("answer").expect("`answer` not found") as usize;
+
+    assert_eq!(p - (ready.p_mem().as_ptr() as usize), v - (v_base as usize));
+}
+
+#[test]
+fn lookup_without_symbol_table_finds_nothing() {
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[]);
+    b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+
+    let raw   = b.build();
+    let ready = load_and_reloc(&raw);
+
+    assert!(ready.  lookup("answer").is_none());
+    assert!(ready.v_lookup("answer").is_none());
+}
+
+
+
+/// Hand-crafts an ELF with one exported function `answer` and a GNU hash table with the given
+/// bloom filter word.
+fn gnu_hash_elf(bloom: u64) -> Vec<u8> {
+    let hash = b"answer".iter().fold(5381_u32, |h, c| h.wrapping_mul(33).wrapping_add(*c as u32));
+
+    let mut tabs = Vec::new();
+
+    // GNU hash at 0x1000: 1 bucket, symbols start at index 1, 1 bloom word, shift 6.
+    for x in &[1_u32, 1, 1, 6] { tabs.extend_from_slice(&x.to_le_bytes()); }
+    tabs.extend_from_slice(&bloom.to_le_bytes());
+    tabs.extend_from_slice(&1_u32.to_le_bytes());        // bucket
+    tabs.extend_from_slice(&(hash | 1).to_le_bytes());   // chain
+    tabs.resize(0x40, 0);
+
+    // Symbols at 0x1040, the null symbol and `answer`, a global function.
+    tabs.resize(0x40 + 24, 0);
+    tabs.extend_from_slice(&1_u32.to_le_bytes());
+    tabs.extend_from_slice(&[0x12, 0]);
+    tabs.extend_from_slice(&1_u16.to_le_bytes());
+    tabs.extend_from_slice(&0x2000_u64.to_le_bytes());
+    tabs.extend_from_slice(&6_u64.to_le_bytes());
+
+    // Strings at 0x1080.
+    tabs.resize(0x80, 0);
+    tabs.extend_from_slice(b"\0answer\0");
+
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[
+        (DT_GNU_HASH, 0x1000),
+        (DT_SYMTAB,   0x1040),
+        (DT_STRTAB,   0x1080),
+        (DT_STRSZ,    8),
+    ]);
+    b.segment(PT_LOAD, PF_R,  0x1000, tabs.len() as u64, &tabs);
+    b.segment(PT_LOAD, PF_RX, 0x2000, 6, &ret_imm32(42));
+    b.build()
+}
+
+#[test]
+fn bloom_filter_rejects_before_walking_chains() {
+    let full  = gnu_hash_elf(!0);
+    let empty = gnu_hash_elf( 0);
+
+    assert!(load_and_reloc(&full ).lookup("answer").is_some());
+
+    // The hash chains still contain `answer`, but the bloom filter says it isn't there.
+    assert!(load_and_reloc(&empty).lookup("answer").is_none());
+}
+
+#[test]
+fn soname_is_read_from_the_string_table() {
+    let buf = Vec::from(ELF);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing failed");
+
+    assert_eq!(elf.soname(), Some("libsymbols.so"));
+
+    let buf = Vec::from(ELF_SYSV);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing failed");
+
+    assert_eq!(elf.soname(), None);
+}
+
+#[test]
+fn soname_rejects_broken_names() {
+    let with_strtab = |strs: &[u8], soname: u64| {
+        let mut b = ElfBuilder::new();
+
+        b.dynamic(0x0000, &[(DT_STRTAB, 0x2000), (DT_STRSZ, strs.len() as u64), (DT_SONAME, soname)]);
+        b.segment(PT_LOAD, PF_RX, 0x1000, 0x1000, &ret_imm32(42));
+        b.segment(PT_LOAD, PF_R,  0x2000, 0x1000, strs);
+        b.build()
+    };
+
+    let ok       = with_strtab(b"\0libok.so\0",     1);
+    let bad_utf8 = with_strtab(b"\0lib\xFF.so\0",   1);
+    let no_nul   = with_strtab(b"\0libnonul.so",    1);
+    let past_end = with_strtab(b"\0libok.so\0",  1000);
+
+    let soname = |raw: &[u8]| Elf::try_parse(raw).expect("Parsing failed").soname().map(String::from);
+
+    assert_eq!(soname(&ok      ), Some(String::from("libok.so")));
+    assert_eq!(soname(&bad_utf8), None);
+    assert_eq!(soname(&no_nul  ), None);
+    assert_eq!(soname(&past_end), None);
+}
+
+
+
+/// Hand-crafts an ELF with one exported function `answer` and a SysV hash table with the given
+/// bucket count, single bucket, and chain entry of `answer`.
+fn sysv_hash_elf(nbucket: u32, bucket: u32, chain: u32) -> Vec<u8> {
+    let mut tabs = Vec::new();
+
+    // SysV hash at 0x1000: 2 chain entries, for the null symbol and `answer`.
+    for x in &[nbucket, 2, bucket, 0, chain] { tabs.extend_from_slice(&x.to_le_bytes()); }
+    tabs.resize(0x40 + 24, 0);
+
+    // Symbols at 0x1040, the null symbol and `answer`, a global function.
+    tabs.extend_from_slice(&1_u32.to_le_bytes());
+    tabs.extend_from_slice(&[0x12, 0]);
+    tabs.extend_from_slice(&1_u16.to_le_bytes());
+    tabs.extend_from_slice(&0x2000_u64.to_le_bytes());
+    tabs.extend_from_slice(&6_u64.to_le_bytes());
+
+    // Strings at 0x1080.
+    tabs.resize(0x80, 0);
+    tabs.extend_from_slice(b"\0answer\0");
+
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[
+        (DT_HASH,   0x1000),
+        (DT_SYMTAB, 0x1040),
+        (DT_STRTAB, 0x1080),
+        (DT_STRSZ,  8),
+    ]);
+    b.segment(PT_LOAD, PF_R,  0x1000, tabs.len() as u64, &tabs);
+    b.segment(PT_LOAD, PF_RX, 0x2000, 6, &ret_imm32(42));
+    b.build()
+}
+
+#[test]
+fn sysv_hash_finds_crafted_symbols() {
+    let raw   = sysv_hash_elf(1, 1, 0);
+    let ready = load_and_reloc(&raw);
+
+    assert_eq!(ready.lookup("answer").map(|p| p as usize),
+               Some((ready.p_mem().as_ptr() as usize) + 0x2000));
+    assert!(ready.lookup("nope").is_none());
+}
+
+#[test]
+fn broken_sysv_hash_tables_find_nothing() {
+    // A chain running in circles, a bucket pointing past the chains, and so many buckets that
+    // the chains lie way out of bounds.
+    for &(nbucket, bucket, chain) in &[(1, 1, 1), (1, 0xFFFF, 0), (0xFFFF_FFFF, 1, 0)] {
+        let raw   = sysv_hash_elf(nbucket, bucket, chain);
+        let ready = load_and_reloc(&raw);
+
+        assert!(ready.lookup("nope").is_none());
+    }
+}
+
+#[test]
+fn symbols_lists_all_defined_symbols() {
+    for raw in [ELF, ELF_SYSV, ELF_BOTH].iter() {
+        let buf   = Vec::from(*raw);
+        let ready = load_and_reloc(&buf);
+
+        let mut syms = ready.symbols().collect::<Vec<_>>();
+        syms.sort_by_key(|sym| sym.name);
+
+        let names = syms.iter().map(|sym| sym.name).collect::<Vec<_>>();
+        assert_eq!(names, ["answer", "counter", "twice"]);
+
+        for sym in &syms {
+            assert!(sym.defined);
+            assert_eq!(sym.binding, SymBinding::Global);
+            assert_eq!(Some(sym.value as *const ()), ready.lookup(sym.name));
+        }
+
+        assert_eq!((syms[0].kind, syms[0].size), (SymKind::Func,   6));
+        assert_eq!((syms[1].kind, syms[1].size), (SymKind::Object, 4));
+        assert_eq!((syms[2].kind, syms[2].size), (SymKind::Func,   4));
+    }
+}
+
+#[test]
+fn symbols_keep_their_raw_entries() {
+    let buf   = Vec::from(ELF);
+    let ready = load_and_reloc(&buf);
+    let sym   = ready.symbols().find(|sym| sym.name == "counter").expect("no `counter`");
+    let raw   = sym.raw();
+
+    // `STB_GLOBAL` and `STT_OBJECT`, at its place within the loaded memory.
+    assert_eq!((raw.st_bind(), raw.st_type()), (1, 1));
+    assert_eq!(raw.st_size, sym.size);
+    assert_eq!(raw.st_value as usize, sym.value - (ready.p_mem().as_ptr() as usize));
+    assert_ne!(raw.st_shndx, 0);
+}
+
+#[test]
+fn symbols_skip_broken_names_and_stop_at_the_end_of_memory() {
+    let find = |raw: &[u8], pat: &[u8]| raw.windows(pat.len()).position(|w| w == pat).unwrap();
+
+    // `answer`'s name points past the string table.
+    let mut raw = sysv_hash_elf(1, 1, 0);
+    let at      = find(&raw, &[1, 0, 0, 0, 0x12, 0]);
+    raw[at] = 8;
+
+    assert_eq!(load_and_reloc(&raw).symbols().count(), 0);
+
+    // An absurd chain count, i.e. symbol count, just runs into the end of memory.
+    let mut raw = sysv_hash_elf(1, 1, 0);
+    let at      = find(&raw, &[1, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0]);
+    raw[(at + 4)..(at + 8)].copy_from_slice(&u32::MAX.to_le_bytes());
+
+    let ready = load_and_reloc(&raw);
+    assert!(ready.symbols().any(|sym| sym.name == "answer"));
+}
+
+
+
+/// Serialises little-endian `u16` and `u32` fields, for the version tables.
+fn fields(out: &mut Vec<u8>, at: usize, halves: &[u16], words: &[u32]) {
+    out.resize(at, 0);
+    for h in halves { out.extend_from_slice(&h.to_le_bytes()); }
+    for w in words  { out.extend_from_slice(&w.to_le_bytes()); }
+}
+
+/// Hand-crafts an ELF with `answer@V1` returning 41, `answer@@V2` returning 42, and an import
+/// `puts@GLIBC_2.2.5`. The version tables are at the given offsets.
+fn versioned_elf(versym: u64, verdef: u64) -> Vec<u8> {
+    let mut tabs = Vec::new();
+
+    // SysV hash at 0x1000, with a single chain through all three symbols.
+    fields(&mut tabs, 0x00, &[], &[1, 4, 1, 0, 2, 3, 0]);
+
+    // Symbols at 0x1040.
+    tabs.resize(0x40, 0);
+    tabs.extend(sym_table(&[
+        (1, ST_INFO_GLOBAL_FUNC, 1, 0x2000),
+        (1, ST_INFO_GLOBAL_FUNC, 1, 0x2010),
+        (8, ST_INFO_GLOBAL_FUNC, 0, 0),
+    ]));
+
+    // Strings at 0x10C0.
+    let strs = b"\0answer\0puts\0V1\0V2\0GLIBC_2.2.5\0libc.so.6\0libv.so\0";
+    tabs.resize(0xC0, 0);
+    tabs.extend_from_slice(strs);
+
+    // `DT_VERSYM` at 0x1100, with `V1` hidden.
+    fields(&mut tabs, 0x100, &[0, 0x8002, 3, 4], &[]);
+
+    // `DT_VERDEF` at 0x1110: the base version naming the file, `V1`, and `V2`. Each entry is
+    // followed by its single auxiliary entry.
+    fields(&mut tabs, 0x110, &[1, 1, 1, 1], &[0, 20, 28]);
+    fields(&mut tabs, 0x124, &[],           &[41, 0]);
+    fields(&mut tabs, 0x12C, &[1, 0, 2, 1], &[0, 20, 28]);
+    fields(&mut tabs, 0x140, &[],           &[13, 0]);
+    fields(&mut tabs, 0x148, &[1, 0, 3, 1], &[0, 20, 0]);
+    fields(&mut tabs, 0x15C, &[],           &[16, 0]);
+
+    // `DT_VERNEED` at 0x1170: `GLIBC_2.2.5` of `libc.so.6` as version 4.
+    fields(&mut tabs, 0x170, &[1, 1],       &[31, 16, 0]);
+    fields(&mut tabs, 0x180, &[],           &[0]);
+    fields(&mut tabs, 0x184, &[0, 4],       &[19, 0]);
+
+    let mut code = ret_imm32(41);
+    code.resize(0x10, 0xCC);
+    code.extend(ret_imm32(42));
+
+    let mut b = ElfBuilder::new();
+
+    b.dynamic(0x0000, &[
+        (DT_HASH,   0x1000),
+        (DT_SYMTAB, 0x1040),
+        (DT_STRTAB, 0x10C0),
+        (DT_STRSZ,  strs.len() as u64),
+        (DT_VERSYM, versym),
+        (DT_VERDEF, verdef),
+        (DT_VERDEFNUM,  3),
+        (DT_VERNEED,    0x1170),
+        (DT_VERNEEDNUM, 1),
+    ]);
+    b.segment(PT_LOAD, PF_R,  0x1000, tabs.len() as u64, &tabs);
+    b.segment(PT_LOAD, PF_RX, 0x2000, code.len() as u64, &code);
+    b.build()
+}
+
+#[test]
+fn lookup_picks_the_default_version() {
+    let raw   = versioned_elf(0x1100, 0x1110);
+    let ready = load_and_reloc(&raw);
+    let at    = |off: usize| Some(((ready.p_mem().as_ptr() as usize) + off) as *const ());
+
+    assert_eq!(ready.lookup("answer"), at(0x2010));
+    assert_eq!(ready.lookup_versioned("answer", "V1"), at(0x2000));
+    assert_eq!(ready.lookup_versioned("answer", "V2"), at(0x2010));
+    assert_eq!(ready.lookup_versioned("answer", "V3"), None);
+    assert_eq!(ready.lookup_versioned("puts", "GLIBC_2.2.5"), None);
+
+    let answer = ready.lookup("answer").expect("no `answer`");
+    let call: extern "C" fn() -> i32 = unsafe { mem::transmute(answer) };
+    assert_eq!((call)(), 42);
+}
+
+#[test]
+fn symbols_know_their_versions() {
+    let raw   = versioned_elf(0x1100, 0x1110);
+    let ready = load_and_reloc(&raw);
+    let syms  = ready.symbols().map(|s| (s.name, s.version, s.hidden)).collect::<Vec<_>>();
+
+    assert_eq!(syms, [
+        ("answer", Some("V1"),          true ),
+        ("answer", Some("V2"),          false),
+        ("puts",   Some("GLIBC_2.2.5"), false),
+    ]);
+}
+
+#[test]
+fn broken_version_tables_make_for_unversioned_symbols() {
+    // Without a `DT_VERSYM` table, every symbol is a default one, so the first one wins.
+    let ready = load_and_reloc(&versioned_elf(0xFFFF_0000, 0x1110));
+    let at    = |off: usize| Some(((ready.p_mem().as_ptr() as usize) + off) as *const ());
+    let syms  = ready.symbols().map(|s| (s.version, s.hidden)).collect::<Vec<_>>();
+
+    assert_eq!(syms, [(None, false); 3]);
+    assert_eq!(ready.lookup("answer"), at(0x2000));
+    assert_eq!(ready.lookup_versioned("answer", "V2"), None);
+
+    // Definitions out of bounds only lose the defined versions' names.
+    let ready = load_and_reloc(&versioned_elf(0x1100, 0xFFFF_0000));
+    let at    = |off: usize| Some(((ready.p_mem().as_ptr() as usize) + off) as *const ());
+    let syms  = ready.symbols().map(|s| (s.version, s.hidden)).collect::<Vec<_>>();
+
+    assert_eq!(syms, [(None, true), (None, false), (Some("GLIBC_2.2.5"), false)]);
+    assert_eq!(ready.lookup("answer"), at(0x2010));
+    assert_eq!(ready.lookup_versioned("answer", "V2"), None);
+}
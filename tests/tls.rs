@@ -0,0 +1,114 @@
+use elf_loader::*;
+
+
+
+mod os;
+mod builder;
+
+use self::builder::*;
+
+
+
+static TLS: &[u8] = include_bytes!("./tls.elf");
+
+/// `GOT` entries of `tls.elf` holding the thread pointer offsets of `tls_data` and `tls_bss`.
+const TLS_DATA_GOT: usize = 0x3FD8;
+const TLS_BSS_GOT:  usize = 0x3FE0;
+
+/// Section index of `.tdata` in crafted ELFs. Any defined section will do.
+const SHNDX_TDATA: u16 = 1;
+
+
+
+/// Loads and re-locates a `RelocElf` with a TLS block of 16 bytes, of which 4 are initialised,
+/// and the given symbols and `Rela` entries.
+fn reloc_tls(tls_headers: usize, syms: &[(u32, u8, u16, u64)], relas: &[(u64, u64, i64)])
+-> Result<Vec<u8>, ElfError> {
+    let mut b = RelocElf { relas, symtab: sym_table(syms), ..RelocElf::default() }.builder();
+
+    for _ in 0..tls_headers {
+        let off = b.blob(&[1, 2, 3, 4]);
+
+        b.phdr(Phdr {
+            p_type: PT_TLS, p_flags: PF_R,
+            p_offset: off, p_vaddr: 0x2F00, p_paddr: 0x2F00,
+            p_filesz: 4, p_memsz: 16,
+            p_align: 16,
+        });
+    }
+
+    let raw        = b.build();
+    let elf        = Elf::try_parse(&raw)?;
+    let mem        = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+    let mut loaded = elf.try_load(mem)?;
+    let base       = loaded.loader_base();
+    let ready      = loaded.try_reloc(base, None, None).map_err(|(_, e)| e)?;
+
+    Ok(ready.p_mem().to_vec())
+}
+
+
+
+#[test]
+fn tls_template_covers_tdata_and_tbss() {
+    let buf = Vec::from(TLS);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `tls.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let loaded = elf.try_load(mem).expect("Loading `tls.elf` failed");
+    let tls    = loaded.tls_template().expect("`tls.elf` has a TLS template");
+
+    assert_eq!(tls.image, [5, 0, 0, 0]);
+    assert_eq!(tls.zero_fill, 4);
+    assert_eq!(tls.align,     4);
+}
+
+#[test]
+fn tpoff64_is_relative_to_the_thread_pointer() {
+    let buf = Vec::from(TLS);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `tls.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `tls.elf` failed");
+    let base       = loaded.loader_base();
+    let ready      = loaded.try_reloc(base, Some(os::protection_fn), None)
+                           .map_err(|(_, e)| e)
+                           .expect("Re-locating `tls.elf` failed");
+
+    // The whole 8 byte block sits right below the thread pointer.
+    assert_eq!(u64_at(ready.p_mem(), TLS_DATA_GOT), (-8_i64) as u64);
+    assert_eq!(u64_at(ready.p_mem(), TLS_BSS_GOT ), (-4_i64) as u64);
+    assert!(ready.tls_template().is_some());
+}
+
+#[test]
+fn dtpmod64_and_dtpoff64_refer_to_the_only_module() {
+    let mem = reloc_tls(1, &[(0, ST_INFO_GLOBAL_TLS, SHNDX_TDATA, 8)], &[
+        (0x2800,             R_X86_64_DTPMOD64, 0),
+        (0x2808, (1 << 32) | R_X86_64_DTPOFF64, 2),
+        (0x2810, (1 << 32) | R_X86_64_TPOFF64,  0),
+    ]).expect("re-locating failed");
+
+    assert_eq!(u64_at(&mem, 0x2800), 1);
+    assert_eq!(u64_at(&mem, 0x2808), 10);
+    assert_eq!(u64_at(&mem, 0x2810), (-8_i64) as u64);
+}
+
+#[test]
+fn undefined_tls_symbols_are_rejected() {
+    assert_eq!(reloc_tls(1, &[(0, ST_INFO_GLOBAL_TLS, 0, 0)], &[
+        (0x2800, (1 << 32) | R_X86_64_TPOFF64, 0),
+    ]).err(), Some(ElfError::Reloc(RelocElfError::UnresolvedSymbol)));
+}
+
+#[test]
+fn tpoff64_without_tls_segment_is_rejected() {
+    assert_eq!(reloc_tls(0, &[], &[(0x2800, R_X86_64_TPOFF64, 0)]).err(),
+               Some(ElfError::Reloc(RelocElfError::NoTlsSegment)));
+}
+
+#[test]
+fn multiple_tls_segments_are_rejected() {
+    assert_eq!(reloc_tls(2, &[], &[]).err(),
+               Some(ElfError::Load(LoadElfError::MultipleTlsSegments)));
+}
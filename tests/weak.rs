@@ -0,0 +1,64 @@
+use elf_loader::*;
+
+
+
+mod os;
+
+
+
+static WEAK: &[u8] = include_bytes!("./weak.elf");
+
+extern "C" fn hook() -> i32 { 7 }
+
+extern "C" fn resolve(name: *const u8, name_len: usize) -> *const () {
+    match unsafe { std::slice::from_raw_parts(name, name_len) } {
+        b"hook" => hook as *const (),
+        _       => std::ptr::null(),
+    }
+}
+
+fn call_hook(resolve: Option<ResolveFn>) -> i32 {
+    let buf = Vec::from(WEAK);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `weak.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `weak.elf` failed");
+    let base       = loaded.loader_base();
+
+    let ready = loaded.try_reloc(base, Some(os::protection_fn), resolve)
+                      .expect("Re-locating `weak.elf` failed");
+    let call  = ready.lookup("call_hook").expect("`call_hook` not found");
+    let call: extern "C" fn() -> i32 = unsafe { std::mem::transmute(call) };
+
+    (call)()
+}
+
+
+
+#[test]
+fn missing_weak_symbols_are_null() {
+    assert_eq!(call_hook(None), 42);
+}
+
+#[test]
+fn weak_symbols_can_still_be_resolved() {
+    assert_eq!(call_hook(Some(resolve)), 7);
+}
+
+#[test]
+fn weak_symbols_are_listed_as_undefined() {
+    let buf = Vec::from(WEAK);
+    let elf = Elf::try_parse(&buf[..]).expect("Parsing `weak.elf` failed");
+    let mem = os::alloc_aligned(elf.mem_len() as usize, elf.mem_align() as usize);
+
+    let mut loaded = elf.try_load(mem).expect("Loading `weak.elf` failed");
+    let base       = loaded.loader_base();
+
+    let ready = loaded.try_reloc(base, None, None).expect("Re-locating `weak.elf` failed");
+    let hook  = ready.symbols().find(|sym| sym.name == "hook").expect("`hook` not listed");
+
+    assert!(!hook.defined);
+    assert_eq!(hook.value,   0);
+    assert_eq!(hook.kind,    SymKind::NoType);
+    assert_eq!(hook.binding, SymBinding::Weak);
+}